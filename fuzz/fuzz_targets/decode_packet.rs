@@ -0,0 +1,10 @@
+#![no_main]
+
+use growtopia_proxy::packet_handler::{decode, GamePacket};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok((_, GamePacket::Tank { mut tank_packet, variants: Some(variants), trailing })) = decode(data) {
+        assert_eq!(tank_packet.to_message(&variants.serialize(), &trailing), data);
+    }
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use growtopia_proxy::utils::variant::VariantList;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(list) = VariantList::deserialize(data) {
+        assert_eq!(list.serialize(), data);
+    }
+});
@@ -0,0 +1,97 @@
+use std::collections::VecDeque;
+use std::sync::OnceLock;
+use std::time::SystemTime;
+use crate::global;
+use crate::metrics::Direction;
+use crate::packet_handler::{self, DecodeError, GamePacket};
+use crate::types::epacket_type::EPacketType;
+
+/// Packets kept for the monitors before the oldest is dropped.
+pub const CAPACITY: usize = 2000;
+
+/// A packet as the proxy received it, before any rewrite.
+#[derive(Debug, Clone)]
+pub struct CapturedPacket {
+    /// Increases by one for every captured packet.
+    pub id: u64,
+    pub time: SystemTime,
+    pub session: u64,
+    pub direction: Direction,
+    pub channel_id: u8,
+    pub data: Vec<u8>,
+    /// The packet type, followed by the tank packet type and function name when there are any.
+    pub summary: String,
+    decoded: OnceLock<Option<(EPacketType, GamePacket)>>,
+}
+
+impl CapturedPacket {
+    pub fn decode(&self) -> Result<(EPacketType, GamePacket), DecodeError> {
+        packet_handler::decode(&self.data)
+    }
+
+    /// The decoded packet, decoded once for all the filters run over it. None when it doesn't
+    /// decode.
+    pub fn decoded(&self) -> Option<&(EPacketType, GamePacket)> {
+        self.decoded.get_or_init(|| self.decode().ok()).as_ref()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Capture {
+    /// Off unless the terminal UI runs or `capture.enabled` is set; pushes are ignored meanwhile.
+    pub enabled: bool,
+    packets: VecDeque<CapturedPacket>,
+    next_id: u64,
+}
+
+impl Capture {
+    /// Keeps a copy of the packet. Returns its id, or None when capturing is off.
+    pub fn push(&mut self, session: u64, direction: Direction, channel_id: u8, data: &[u8], summary: String) -> Option<u64> {
+        if !self.enabled {
+            return None;
+        }
+        if self.packets.len() >= CAPACITY {
+            self.packets.pop_front();
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.packets.push_back(CapturedPacket {
+            id,
+            time: SystemTime::now(),
+            session,
+            direction,
+            channel_id,
+            data: data.to_vec(),
+            summary,
+            decoded: OnceLock::new(),
+        });
+        Some(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+
+    /// Captured packets, oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &CapturedPacket> {
+        self.packets.iter()
+    }
+
+    /// Packets captured after the one with the given id.
+    pub fn since(&self, id: u64) -> impl Iterator<Item = &CapturedPacket> {
+        self.packets.iter().filter(move |packet| packet.id > id)
+    }
+
+    pub fn get(&self, id: u64) -> Option<&CapturedPacket> {
+        let first = self.packets.front()?.id;
+        self.packets.get(id.checked_sub(first)? as usize)
+    }
+}
+
+pub fn with<T>(f: impl FnOnce(&mut Capture) -> T) -> T {
+    f(&mut global().capture.lock().unwrap())
+}
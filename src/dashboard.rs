@@ -0,0 +1,267 @@
+use std::io::ErrorKind;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use log::info;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use crate::capture::CapturedPacket;
+use crate::filter::Filter;
+use crate::headless::StartError;
+use crate::snapshot::{self, Snapshot};
+use crate::types::config::Hooks;
+use crate::{capture, global, headless, metrics, utils, world};
+
+/// Most packets returned by one `/api/packets` request.
+const MAX_PACKETS: usize = 200;
+
+/// The dashboard page, its assets and the JSON API behind it. The API needs `token` when it
+/// isn't empty.
+pub fn router(token: String) -> Router {
+    Router::new()
+        .route("/api/session", get(session))
+        .route("/api/headless", post(start_headless).delete(stop_headless))
+        .route("/api/world", get(world))
+        .route("/api/world/snapshot", get(world_snapshot))
+        .route("/api/world/snapshot.png", get(world_snapshot_png))
+        .route("/api/snapshots", get(snapshots).post(save_snapshot))
+        .route("/api/snapshots/diff", get(snapshot_diff))
+        .route("/api/snapshots/{id}", get(saved_snapshot))
+        .route("/api/packets", get(packets))
+        .route("/api/hooks", get(hooks).post(set_hooks))
+        .route("/metrics", get(metrics))
+        .route_layer(middleware::from_fn_with_state(Arc::<str>::from(token), require_token))
+        .route("/dashboard", get(index))
+        .route("/dashboard/app.js", get(script))
+        .route("/dashboard/style.css", get(style))
+}
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+async fn require_token(State(token): State<Arc<str>>, Query(query): Query<TokenQuery>, request: Request, next: Next) -> Response {
+    if token.is_empty() {
+        return next.run(request).await;
+    }
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match bearer.or(query.token.as_deref()) {
+        Some(given) if same_token(given, &token) => next.run(request).await,
+        _ => StatusCode::UNAUTHORIZED.into_response(),
+    }
+}
+
+/// Compares tokens in time that doesn't depend on where they differ.
+fn same_token(given: &str, token: &str) -> bool {
+    given.len() == token.len() && given.bytes().zip(token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+async fn index() -> Html<&'static str> {
+    Html(include_str!("../static/dashboard/index.html"))
+}
+
+async fn script() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/javascript")], include_str!("../static/dashboard/app.js"))
+}
+
+async fn style() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/css")], include_str!("../static/dashboard/style.css"))
+}
+
+async fn session() -> Json<Value> {
+    let ping = metrics::with(|metrics| metrics.ping.clone());
+    let millis = |duration: Option<Duration>| duration.map(|duration| duration.as_millis() as u64);
+    let session = global().session.lock().unwrap();
+    Json(json!({
+        "id": session.id,
+        "state": session.state.to_string(),
+        "headless": session.is_headless(),
+        "ping": {
+            "requests": ping.requests,
+            "client_replies": ping.client_replies,
+            "proxy_replies": ping.proxy_replies,
+            "last_ms": millis(ping.last_round_trip),
+            "min_ms": millis(ping.min_round_trip),
+            "max_ms": millis(ping.max_round_trip),
+            "avg_ms": millis(ping.average_round_trip()),
+        },
+    }))
+}
+
+async fn metrics() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], metrics::render())
+}
+
+async fn start_headless() -> Result<Json<Value>, (StatusCode, String)> {
+    let address = tokio::task::spawn_blocking(headless::start)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(|e| match e {
+            StartError::Running => (StatusCode::CONFLICT, e.to_string()),
+            StartError::Failed(message) => (StatusCode::BAD_GATEWAY, message),
+        })?;
+    Ok(Json(json!({ "address": address.to_string() })))
+}
+
+async fn stop_headless() -> StatusCode {
+    if headless::stop() {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::CONFLICT
+    }
+}
+
+async fn world() -> Json<Value> {
+    world::with(|world| {
+        let players: Vec<Value> = world
+            .players
+            .values()
+            .map(|player| {
+                json!({
+                    "net_id": player.net_id,
+                    "user_id": player.user_id,
+                    "name": player.name,
+                    "local": Some(player.net_id) == world.local_net_id,
+                    "x": player.position.x,
+                    "y": player.position.y,
+                })
+            })
+            .collect();
+        let width = world.width.max(1) as usize;
+        let tiles: Vec<Value> = world
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| tile.foreground != 0 || tile.background != 0)
+            .map(|(index, tile)| {
+                json!({
+                    "x": index % width,
+                    "y": index / width,
+                    "foreground": tile.foreground,
+                    "background": tile.background,
+                })
+            })
+            .collect();
+        Json(json!({
+            "name": world.name,
+            "width": world.width,
+            "height": world.height,
+            "tiles": tiles,
+            "dropped": world.dropped,
+            "players": players,
+        }))
+    })
+}
+
+fn current_snapshot() -> Result<Snapshot, (StatusCode, String)> {
+    snapshot::take().ok_or((StatusCode::NOT_FOUND, "no world loaded".to_string()))
+}
+
+fn load_snapshot(id: &str) -> Result<Snapshot, (StatusCode, String)> {
+    Snapshot::load(&utils::config::get_snapshot_config(), id).map_err(|e| match e.kind() {
+        ErrorKind::NotFound => (StatusCode::NOT_FOUND, e.to_string()),
+        ErrorKind::InvalidInput => (StatusCode::BAD_REQUEST, e.to_string()),
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    })
+}
+
+async fn world_snapshot() -> Result<Json<Snapshot>, (StatusCode, String)> {
+    current_snapshot().map(Json)
+}
+
+async fn world_snapshot_png() -> Result<impl IntoResponse, (StatusCode, String)> {
+    let png = current_snapshot()?
+        .render_png(&utils::config::get_snapshot_config())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(([(header::CONTENT_TYPE, "image/png")], png))
+}
+
+async fn snapshots() -> Json<Vec<String>> {
+    Json(snapshot::list(&utils::config::get_snapshot_config()))
+}
+
+async fn save_snapshot() -> Result<Json<Value>, (StatusCode, String)> {
+    let snapshot = current_snapshot()?;
+    let path = snapshot
+        .save(&utils::config::get_snapshot_config())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    info!("Saved snapshot {} from the dashboard", path.display());
+    Ok(Json(json!({ "id": snapshot.id() })))
+}
+
+async fn saved_snapshot(Path(id): Path<String>) -> Result<Json<Snapshot>, (StatusCode, String)> {
+    load_snapshot(&id).map(Json)
+}
+
+#[derive(Deserialize)]
+struct DiffQuery {
+    from: String,
+    /// Compared with the current world when missing.
+    to: Option<String>,
+}
+
+async fn snapshot_diff(Query(query): Query<DiffQuery>) -> Result<Json<snapshot::SnapshotDiff>, (StatusCode, String)> {
+    let before = load_snapshot(&query.from)?;
+    let after = match query.to.as_deref() {
+        Some(to) => load_snapshot(to)?,
+        None => current_snapshot()?,
+    };
+    Ok(Json(snapshot::diff(&before, &after)))
+}
+
+#[derive(Deserialize)]
+struct PacketsQuery {
+    /// Only return packets captured after this id.
+    since: Option<u64>,
+    /// A `filter` expression the packets must match.
+    filter: Option<String>,
+}
+
+async fn packets(Query(query): Query<PacketsQuery>) -> Result<Json<Value>, (StatusCode, String)> {
+    let filter = match query.filter.as_deref().filter(|filter| !filter.trim().is_empty()) {
+        Some(filter) => Some(Filter::parse(filter).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?),
+        None => None,
+    };
+    capture::with(|capture| {
+        let matching: Vec<&CapturedPacket> = capture
+            .iter()
+            .filter(|packet| query.since.is_none_or(|since| packet.id > since))
+            .filter(|packet| filter.as_ref().is_none_or(|filter| filter.matches_captured(packet)))
+            .collect();
+        let packets: Vec<Value> = matching[matching.len().saturating_sub(MAX_PACKETS)..]
+            .iter()
+            .map(|packet| {
+                json!({
+                    "id": packet.id,
+                    "time": packet.time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64,
+                    "session": packet.session,
+                    "direction": packet.direction.as_str(),
+                    "channel_id": packet.channel_id,
+                    "size": packet.data.len(),
+                    "summary": packet.summary,
+                })
+            })
+            .collect();
+        Ok(Json(Value::Array(packets)))
+    })
+}
+
+async fn hooks() -> Json<Hooks> {
+    Json(utils::config::get_hooks())
+}
+
+async fn set_hooks(Json(hooks): Json<Hooks>) -> Json<Hooks> {
+    info!("Hooks changed from the dashboard: {:?}", hooks);
+    utils::config::set_hooks(hooks.clone());
+    Json(hooks)
+}
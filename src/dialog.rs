@@ -0,0 +1,268 @@
+//! The `OnDialogRequest` dialog format and the `action|dialog_return` replies to it.
+//!
+//! A dialog is one element per line, each a command followed by `|`-separated arguments:
+//!
+//! ```text
+//! set_default_color|`o
+//! add_label_with_icon|big|`wProxy``|left|32|
+//! add_checkbox|spawn_mod_state|Show mod state|1|
+//! add_button|save|Save|noflags|0|0|
+//! end_dialog|proxy_settings|Cancel|OK|
+//! ```
+//!
+//! Pressing a button sends the dialog's name, the button and every input back as text:
+//!
+//! ```text
+//! action|dialog_return
+//! dialog_name|proxy_settings
+//! buttonClicked|save
+//! spawn_mod_state|1
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Element {
+    /// `set_default_color`, the colour code for text without one.
+    DefaultColor(String),
+    Label { size: String, text: String, align: String },
+    LabelWithIcon { size: String, text: String, align: String, item_id: u32 },
+    SmallText(String),
+    Textbox { text: String, align: String },
+    Spacer(String),
+    /// `args` follow the label, such as `noflags|0|0`, and are written back unchanged.
+    Button { id: String, label: String, args: Vec<String> },
+    TextInput { id: String, label: String, value: String, max_length: u32, password: bool },
+    Checkbox { id: String, label: String, checked: bool },
+    ItemPicker { id: String, label: String, hint: String },
+    /// Hidden data returned with the dialog.
+    EmbedData { key: String, value: String },
+    QuickExit,
+    /// Names the dialog and labels its cancel and OK buttons. An empty label hides the button.
+    End { name: String, cancel: String, ok: String },
+    /// A command this module doesn't know, kept as is.
+    Other { command: String, args: Vec<String> },
+}
+
+impl Element {
+    fn parse(line: &str) -> Element {
+        let line = line.strip_suffix('|').unwrap_or(line);
+        let mut parts = line.split('|');
+        let command = parts.next().unwrap_or_default();
+        let args: Vec<&str> = parts.collect();
+        let arg = |index: usize| args.get(index).copied().unwrap_or_default().to_string();
+        let parsed = match (command, args.len()) {
+            ("set_default_color", 1) => Some(Element::DefaultColor(arg(0))),
+            ("add_label", 3) => Some(Element::Label { size: arg(0), text: arg(1), align: arg(2) }),
+            ("add_label_with_icon", 4) => arg(3)
+                .parse()
+                .ok()
+                .map(|item_id| Element::LabelWithIcon { size: arg(0), text: arg(1), align: arg(2), item_id }),
+            ("add_smalltext", 1) => Some(Element::SmallText(arg(0))),
+            ("add_textbox", 2) => Some(Element::Textbox { text: arg(0), align: arg(1) }),
+            ("add_spacer", 1) => Some(Element::Spacer(arg(0))),
+            ("add_button", 2..=5) => Some(Element::Button {
+                id: arg(0),
+                label: arg(1),
+                args: args[2..].iter().map(|arg| arg.to_string()).collect(),
+            }),
+            ("add_text_input" | "add_text_input_password", 4) => arg(3).parse().ok().map(|max_length| Element::TextInput {
+                id: arg(0),
+                label: arg(1),
+                value: arg(2),
+                max_length,
+                password: command == "add_text_input_password",
+            }),
+            ("add_checkbox", 3) => match arg(2).as_str() {
+                "0" => Some(Element::Checkbox { id: arg(0), label: arg(1), checked: false }),
+                "1" => Some(Element::Checkbox { id: arg(0), label: arg(1), checked: true }),
+                _ => None,
+            },
+            ("add_item_picker", 3) => Some(Element::ItemPicker { id: arg(0), label: arg(1), hint: arg(2) }),
+            ("embed_data", 2) => Some(Element::EmbedData { key: arg(0), value: arg(1) }),
+            ("add_quick_exit", 0) => Some(Element::QuickExit),
+            ("end_dialog", 1..=3) => Some(Element::End { name: arg(0), cancel: arg(1), ok: arg(2) }),
+            _ => None,
+        };
+        parsed.unwrap_or_else(|| Element::Other {
+            command: command.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        })
+    }
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Element::DefaultColor(color) => write!(f, "set_default_color|{}", color),
+            Element::Label { size, text, align } => write!(f, "add_label|{}|{}|{}|", size, text, align),
+            Element::LabelWithIcon { size, text, align, item_id } => {
+                write!(f, "add_label_with_icon|{}|{}|{}|{}|", size, text, align, item_id)
+            }
+            Element::SmallText(text) => write!(f, "add_smalltext|{}|", text),
+            Element::Textbox { text, align } => write!(f, "add_textbox|{}|{}|", text, align),
+            Element::Spacer(size) => write!(f, "add_spacer|{}|", size),
+            Element::Button { id, label, args } if args.is_empty() => write!(f, "add_button|{}|{}|", id, label),
+            Element::Button { id, label, args } => write!(f, "add_button|{}|{}|{}|", id, label, args.join("|")),
+            Element::TextInput { id, label, value, max_length, password } => {
+                let command = if *password { "add_text_input_password" } else { "add_text_input" };
+                write!(f, "{}|{}|{}|{}|{}|", command, id, label, value, max_length)
+            }
+            Element::Checkbox { id, label, checked } => write!(f, "add_checkbox|{}|{}|{}|", id, label, *checked as u8),
+            Element::ItemPicker { id, label, hint } => write!(f, "add_item_picker|{}|{}|{}|", id, label, hint),
+            Element::EmbedData { key, value } => write!(f, "embed_data|{}|{}|", key, value),
+            Element::QuickExit => write!(f, "add_quick_exit|"),
+            Element::End { name, cancel, ok } => write!(f, "end_dialog|{}|{}|{}|", name, cancel, ok),
+            Element::Other { command, args } if args.is_empty() => write!(f, "{}|", command),
+            Element::Other { command, args } => write!(f, "{}|{}|", command, args.join("|")),
+        }
+    }
+}
+
+/// A parsed dialog. Builder methods append elements, so the proxy can make its own menus:
+///
+/// ```
+/// # use growtopia_proxy::dialog::Dialog;
+/// let dialog = Dialog::new().label("Settings").checkbox("enabled", "Enabled", true).end("settings", "Close", "Save");
+/// assert_eq!(dialog.name(), Some("settings"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dialog {
+    pub elements: Vec<Element>,
+}
+
+impl Dialog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses the text of an `OnDialogRequest`. Unknown or malformed lines become `Element::Other`.
+    pub fn parse(input: &str) -> Self {
+        let elements = input
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty())
+            .map(Element::parse)
+            .collect();
+        Self { elements }
+    }
+
+    /// The name given by `end_dialog`, which replies carry as `dialog_name`.
+    pub fn name(&self) -> Option<&str> {
+        self.elements.iter().find_map(|element| match element {
+            Element::End { name, .. } => Some(name.as_str()),
+            _ => None,
+        })
+    }
+
+    /// The element with the given button, input or checkbox id.
+    pub fn get(&self, id: &str) -> Option<&Element> {
+        self.elements.iter().find(|element| match element {
+            Element::Button { id: element_id, .. }
+            | Element::TextInput { id: element_id, .. }
+            | Element::Checkbox { id: element_id, .. }
+            | Element::ItemPicker { id: element_id, .. } => element_id == id,
+            _ => false,
+        })
+    }
+
+    pub fn push(mut self, element: Element) -> Self {
+        self.elements.push(element);
+        self
+    }
+
+    pub fn label(self, text: &str) -> Self {
+        self.push(Element::Label { size: "big".to_string(), text: text.to_string(), align: "left".to_string() })
+    }
+
+    pub fn textbox(self, text: &str) -> Self {
+        self.push(Element::Textbox { text: text.to_string(), align: "left".to_string() })
+    }
+
+    pub fn spacer(self) -> Self {
+        self.push(Element::Spacer("small".to_string()))
+    }
+
+    pub fn button(self, id: &str, label: &str) -> Self {
+        let args = ["noflags", "0", "0"].map(str::to_string).to_vec();
+        self.push(Element::Button { id: id.to_string(), label: label.to_string(), args })
+    }
+
+    pub fn checkbox(self, id: &str, label: &str, checked: bool) -> Self {
+        self.push(Element::Checkbox { id: id.to_string(), label: label.to_string(), checked })
+    }
+
+    pub fn text_input(self, id: &str, label: &str, value: &str, max_length: u32) -> Self {
+        self.push(Element::TextInput { id: id.to_string(), label: label.to_string(), value: value.to_string(), max_length, password: false })
+    }
+
+    pub fn end(self, name: &str, cancel: &str, ok: &str) -> Self {
+        self.push(Element::End { name: name.to_string(), cancel: cancel.to_string(), ok: ok.to_string() })
+    }
+}
+
+impl fmt::Display for Dialog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for element in &self.elements {
+            writeln!(f, "{}", element)?;
+        }
+        Ok(())
+    }
+}
+
+/// The game client's reply to a dialog, sent as `action|dialog_return` text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DialogReturn {
+    /// The `end_dialog` name of the dialog.
+    pub name: String,
+    /// The button that closed it. OK is reported with an empty `buttonClicked`.
+    pub button: Option<String>,
+    /// Input, checkbox and embedded values by id.
+    pub values: BTreeMap<String, String>,
+}
+
+impl DialogReturn {
+    /// Parses a client text packet. Returns None for anything but `action|dialog_return`.
+    pub fn parse(input: &str) -> Option<Self> {
+        let mut lines = input.lines().filter_map(|line| line.split_once('|'));
+        if lines.next()? != ("action", "dialog_return") {
+            return None;
+        }
+        let mut reply = DialogReturn::default();
+        for (key, value) in lines {
+            let value = value.trim_end().to_string();
+            match key {
+                "dialog_name" => reply.name = value,
+                "buttonClicked" => reply.button = Some(value),
+                _ => {
+                    reply.values.insert(key.to_string(), value);
+                }
+            }
+        }
+        Some(reply)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&str> {
+        self.values.get(id).map(String::as_str)
+    }
+
+    /// Whether the checkbox `id` was ticked.
+    pub fn checked(&self, id: &str) -> bool {
+        self.get(id) == Some("1")
+    }
+}
+
+impl fmt::Display for DialogReturn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "action|dialog_return")?;
+        writeln!(f, "dialog_name|{}", self.name)?;
+        if let Some(button) = &self.button {
+            writeln!(f, "buttonClicked|{}", button)?;
+        }
+        for (key, value) in &self.values {
+            writeln!(f, "{}|{}", key, value)?;
+        }
+        Ok(())
+    }
+}
@@ -1,2 +1,173 @@
 pub mod client;
-pub mod server;
\ No newline at end of file
+pub mod server;
+
+use std::collections::VecDeque;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::Duration;
+use log::{error, info, warn};
+use rusty_enet as enet;
+use tokio::io::Interest;
+use tokio::sync::mpsc;
+use crate::metrics::{self, Direction, PeerStats};
+use crate::{ping, session};
+
+/// How often an idle host is serviced so ENet can resend, ping and time out peers.
+/// Incoming datagrams and queued commands wake the host immediately.
+const SERVICE_INTERVAL: Duration = Duration::from_millis(50);
+/// Packets kept for a peer that hasn't connected yet. The oldest are dropped past this.
+const MAX_PENDING_PACKETS: usize = 256;
+
+/// A request for the task that owns an ENet host.
+pub enum Command {
+    /// Send a packet to the connected peer. Packets sent before a peer connects are queued
+    /// for it, and dropped when the host connects elsewhere or the peer disconnects.
+    Send { channel_id: u8, packet: enet::Packet },
+    /// Drop the current peer, if any, and connect to `address`.
+    Connect(SocketAddr),
+    /// Drop the current peer and anything queued for it.
+    Disconnect,
+}
+
+/// Sending side of a host's command channel.
+pub struct HostHandle(mpsc::UnboundedSender<Command>);
+
+impl HostHandle {
+    pub fn send(&self, command: Command) -> bool {
+        self.0.send(command).is_ok()
+    }
+}
+
+/// Runs `host` until its command channel closes. `is_client` is true for the host connected
+/// to the Growtopia server and is passed along to `packet_handler`.
+async fn run(mut host: enet::Host<UdpSocket>, socket: UdpSocket, mut commands: mpsc::UnboundedReceiver<Command>, is_client: bool) {
+    let name = if is_client { "Client" } else { "Server" };
+    let socket = match socket.set_nonblocking(true).and_then(|_| tokio::net::UdpSocket::from_std(socket)) {
+        Ok(socket) => socket,
+        Err(err) => {
+            error!("{} Failed to watch ENet socket: {}", name, err);
+            return;
+        }
+    };
+    let mut interval = tokio::time::interval(SERVICE_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut peer_id: Option<enet::PeerID> = None;
+    let mut pending: VecDeque<(u8, enet::Packet)> = VecDeque::new();
+
+    loop {
+        tokio::select! {
+            ready = socket.readable() => {
+                if let Err(err) = ready {
+                    error!("{} ENet socket failed: {}", name, err);
+                    return;
+                }
+                // The host reads through its own handle, so tokio never sees the socket drained.
+                // Clear readiness before servicing so datagrams arriving meanwhile wake us again.
+                let _ = socket.try_io(Interest::READABLE, || Err::<(), _>(io::Error::from(io::ErrorKind::WouldBlock)));
+            }
+            command = commands.recv() => match command {
+                Some(Command::Send { channel_id, packet }) => match peer_id {
+                    Some(peer_id) => {
+                        if let Err(err) = host.peer_mut(peer_id).send(channel_id, &packet) {
+                            error!("{} Failed sending packet: {}", name, err);
+                        }
+                    }
+                    None => {
+                        if pending.len() >= MAX_PENDING_PACKETS {
+                            warn!("{} Pending queue full, dropping oldest packet", name);
+                            pending.pop_front();
+                        }
+                        pending.push_back((channel_id, packet));
+                    }
+                },
+                Some(Command::Connect(address)) => {
+                    if let Some(peer_id) = peer_id.take() {
+                        host.peer_mut(peer_id).disconnect_now(0);
+                    }
+                    pending.clear();
+                    match host.connect(address, 2, 0) {
+                        Ok(..) => info!("{} Connecting to {}", name, address),
+                        Err(err) => error!("{} Unable to connect to {}: {}", name, address, err),
+                    }
+                }
+                Some(Command::Disconnect) => {
+                    if let Some(peer_id) = peer_id.take() {
+                        host.peer_mut(peer_id).disconnect_now(0);
+                    }
+                    pending.clear();
+                }
+                None => return,
+            },
+            _ = interval.tick() => {
+                if is_client {
+                    session::check_timeout();
+                    ping::check_timeout();
+                }
+                let direction = Direction::from_is_client(is_client);
+                match peer_id {
+                    Some(peer_id) => {
+                        let peer = host.peer_mut(peer_id);
+                        let stats = PeerStats {
+                            round_trip_time: peer.round_trip_time(),
+                            // ENet scales packet loss by ENET_PEER_PACKET_LOSS_SCALE.
+                            packet_loss: peer.packet_loss() as f64 / 65536.0,
+                        };
+                        metrics::with(|metrics| metrics.peers.insert(direction, stats));
+                    }
+                    None => {
+                        metrics::with(|metrics| metrics.peers.remove(&direction));
+                    }
+                }
+            }
+        }
+
+        loop {
+            let event = match host.service() {
+                Ok(Some(event)) => event.no_ref(),
+                Ok(None) => break,
+                Err(err) => {
+                    error!("{} Failed servicing ENet host: {:?}", name, err);
+                    break;
+                }
+            };
+
+            match event {
+                enet::EventNoRef::Connect { peer, .. } => {
+                    info!("{} Peer {} connected", name, peer.0);
+                    peer_id = Some(peer);
+                    if is_client {
+                        session::upstream_connected();
+                    } else {
+                        session::client_connected();
+                    }
+                    for (channel_id, packet) in pending.drain(..) {
+                        if let Err(err) = host.peer_mut(peer).send(channel_id, &packet) {
+                            error!("{} Failed sending queued packet: {}", name, err);
+                        }
+                    }
+                }
+                enet::EventNoRef::Disconnect { peer, .. } => {
+                    info!("{} Peer {} disconnected", name, peer.0);
+                    if peer_id == Some(peer) {
+                        peer_id = None;
+                    }
+                    pending.clear();
+                    if is_client {
+                        session::upstream_disconnected();
+                    } else {
+                        session::client_disconnected();
+                    }
+                }
+                enet::EventNoRef::Receive {
+                    peer,
+                    channel_id,
+                    mut packet,
+                } => {
+                    peer_id = Some(peer);
+                    crate::packet_handler::handle(&mut packet, channel_id, is_client);
+                }
+            }
+        }
+        host.flush();
+    }
+}
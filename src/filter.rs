@@ -0,0 +1,561 @@
+//! A small expression language for picking packets, e.g.
+//! `dir=server && tank.type==CallFunction && fn=="OnConsoleMessage"`.
+//!
+//! An expression is a comparison or a bare field, combined with `&&`, `||`, `!` and parentheses.
+//! The left side of a comparison is always a field; the right side is a number, a quoted string
+//! or a bare word. A bare field is true when it is present and non-zero.
+//!
+//! | field | value |
+//! |---|---|
+//! | `dir` | `client` or `server`, the side that sent the packet |
+//! | `channel`, `size` | ENet channel and packet size in bytes |
+//! | `type` | `EPacketType` name, with or without the `NetMessage` prefix |
+//! | `tank.type` | `ETankPacketType` name, with or without the `NetGamePacket` prefix |
+//! | `tank.<field>` | any `TankPacket` field, e.g. `tank.net_id`, `tank.int_x` |
+//! | `tank.flags.<flag>` | whether a `PacketFlag` is set, e.g. `tank.flags.punch` |
+//! | `fn` | name of the called function |
+//! | `var.<n>` | the `n`th variant of a function call |
+//! | `text` | the text of a text packet |
+//! | `text.<key>` | the value of `key|` in a text packet, also as `|key|` in chat |
+//!
+//! Operators are `==` (or `=`), `!=`, `<`, `<=`, `>`, `>=` and `~=` (contains). Names and
+//! strings compare case-insensitively. A comparison with a field the packet doesn't have is
+//! false, except `!=`, which is true. Parentheses and `!` nest at most [`MAX_DEPTH`] deep.
+
+use std::fmt;
+use std::str::FromStr;
+use byteorder::{ByteOrder, LittleEndian};
+use crate::capture::CapturedPacket;
+use crate::metrics::Direction;
+use crate::packet_handler::{self, GamePacket};
+use crate::types::epacket_type::EPacketType;
+use crate::types::packet_flag::PacketFlag;
+use crate::types::tank_packet::TankPacket;
+use crate::utils::text_parse;
+use crate::utils::variant::Variant;
+
+/// How deep parentheses and `!` may nest.
+pub const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterError {
+    /// Byte offset into the expression.
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.position + 1)
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+/// A packet to evaluate a filter against.
+#[derive(Clone, Copy)]
+pub struct FilterInput<'a> {
+    pub direction: Direction,
+    pub channel_id: u8,
+    pub size: usize,
+    pub packet_type: EPacketType,
+    /// None when the packet failed to decode.
+    pub packet: Option<&'a GamePacket>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TankField {
+    Type,
+    Unk1,
+    Unk2,
+    Unk3,
+    NetId,
+    SecId,
+    Flags,
+    Unk6,
+    Value,
+    VectorX,
+    VectorY,
+    VectorX2,
+    VectorY2,
+    Unk12,
+    IntX,
+    IntY,
+    ExtendedDataLength,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Field {
+    Direction,
+    Channel,
+    Size,
+    Type,
+    Tank(TankField),
+    Flag(PacketFlag),
+    Function,
+    Variant(usize),
+    Text,
+    TextKey(String),
+}
+
+impl Field {
+    fn parse(path: &str) -> Option<Field> {
+        let field = match path {
+            "dir" | "direction" => Field::Direction,
+            "channel" => Field::Channel,
+            "size" => Field::Size,
+            "type" => Field::Type,
+            "fn" | "function" => Field::Function,
+            "text" => Field::Text,
+            _ => {
+                if let Some(name) = path.strip_prefix("tank.flags.") {
+                    return PacketFlag::from_name(&name.to_uppercase()).map(Field::Flag);
+                }
+                if let Some(name) = path.strip_prefix("tank.") {
+                    return TankField::parse(name).map(Field::Tank);
+                }
+                if let Some(index) = path.strip_prefix("var.") {
+                    return index.parse().ok().map(Field::Variant);
+                }
+                if let Some(key) = path.strip_prefix("text.") {
+                    return Some(Field::TextKey(key.to_string()));
+                }
+                return None;
+            }
+        };
+        Some(field)
+    }
+
+    fn value(&self, input: &FilterInput) -> Option<Value> {
+        let tank = || match input.packet {
+            Some(GamePacket::Tank { tank_packet, .. }) => Some(tank_packet),
+            _ => None,
+        };
+        let variant = |index: usize| match input.packet {
+            Some(GamePacket::Tank { variants: Some(variants), .. }) => variants.get(index),
+            _ => None,
+        };
+        let text = || match input.packet {
+            Some(GamePacket::Text(text)) => Some(text),
+            _ => None,
+        };
+        let value = match self {
+            Field::Direction => Value::Name(match input.direction {
+                Direction::FromClient => "client".to_string(),
+                Direction::FromServer => "server".to_string(),
+            }),
+            Field::Channel => Value::Number(input.channel_id as f64),
+            Field::Size => Value::Number(input.size as f64),
+            Field::Type => Value::Name(format!("{:?}", input.packet_type)),
+            Field::Tank(field) => field.value(tank()?),
+            Field::Flag(flag) => Value::Bool(tank()?.flags.contains(*flag)),
+            Field::Function => Value::Str(variant(0)?.as_string()),
+            Field::Variant(index) => match variant(*index)? {
+                Variant::Float(value) => Value::Number(*value as f64),
+                Variant::Unsigned(value) => Value::Number(*value as f64),
+                Variant::Signed(value) => Value::Number(*value as f64),
+                other => Value::Str(other.as_string()),
+            },
+            Field::Text => Value::Str(text()?.clone()),
+            Field::TextKey(key) => Value::Str(text_parse::get_field(text()?, key)?.to_string()),
+        };
+        Some(value)
+    }
+}
+
+impl TankField {
+    fn parse(name: &str) -> Option<TankField> {
+        let field = match name {
+            "type" => TankField::Type,
+            "unk1" => TankField::Unk1,
+            "unk2" => TankField::Unk2,
+            "unk3" => TankField::Unk3,
+            "net_id" => TankField::NetId,
+            "sec_id" => TankField::SecId,
+            "flags" => TankField::Flags,
+            "unk6" => TankField::Unk6,
+            "value" => TankField::Value,
+            "vector_x" => TankField::VectorX,
+            "vector_y" => TankField::VectorY,
+            "vector_x2" => TankField::VectorX2,
+            "vector_y2" => TankField::VectorY2,
+            "unk12" => TankField::Unk12,
+            "int_x" => TankField::IntX,
+            "int_y" => TankField::IntY,
+            "extended_data_length" => TankField::ExtendedDataLength,
+            _ => return None,
+        };
+        Some(field)
+    }
+
+    fn value(&self, tank: &TankPacket) -> Value {
+        let number = match self {
+            TankField::Type => return Value::Name(format!("{:?}", tank.r#type)),
+            TankField::Unk1 => tank.unk1 as f64,
+            TankField::Unk2 => tank.unk2 as f64,
+            TankField::Unk3 => tank.unk3 as f64,
+            TankField::NetId => tank.net_id as f64,
+            TankField::SecId => tank.sec_id as f64,
+            TankField::Flags => tank.flags.to_u32() as f64,
+            TankField::Unk6 => tank.unk6 as f64,
+            TankField::Value => tank.value as f64,
+            TankField::VectorX => tank.vector_x as f64,
+            TankField::VectorY => tank.vector_y as f64,
+            TankField::VectorX2 => tank.vector_x2 as f64,
+            TankField::VectorY2 => tank.vector_y2 as f64,
+            TankField::Unk12 => tank.unk12 as f64,
+            TankField::IntX => tank.int_x as f64,
+            TankField::IntY => tank.int_y as f64,
+            TankField::ExtendedDataLength => tank.extended_data_length as f64,
+        };
+        Value::Number(number)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(f64),
+    Str(String),
+    /// An enum name, which also matches without its `NetMessage` / `NetGamePacket` prefix.
+    Name(String),
+    Bool(bool),
+}
+
+impl Value {
+    fn truthy(&self) -> bool {
+        match self {
+            Value::Number(value) => *value != 0.0,
+            Value::Str(value) | Value::Name(value) => !value.is_empty(),
+            Value::Bool(value) => *value,
+        }
+    }
+
+    fn compare(&self, op: Op, literal: &Literal) -> bool {
+        match (self, literal) {
+            (Value::Number(value), Literal::Number(literal)) => op.apply(value.partial_cmp(literal)),
+            (Value::Bool(value), Literal::Word(word)) => match word.as_str() {
+                "true" => op.apply(Some(value.cmp(&true))),
+                "false" => op.apply(Some(value.cmp(&false))),
+                _ => false,
+            },
+            (Value::Bool(value), Literal::Number(literal)) => op.apply((*value as u8 as f64).partial_cmp(literal)),
+            (Value::Name(name), literal) => {
+                let literal = literal.as_string().to_lowercase();
+                let name = name.to_lowercase();
+                match op {
+                    Op::Contains => name.contains(&literal),
+                    Op::Eq | Op::Ne => {
+                        let equal = name == literal
+                            || name.strip_prefix("netmessage") == Some(&literal)
+                            || name.strip_prefix("netgamepacket") == Some(&literal);
+                        equal == (op == Op::Eq)
+                    }
+                    _ => false,
+                }
+            }
+            (Value::Str(value), literal) => {
+                let value = value.to_lowercase();
+                let literal = literal.as_string().to_lowercase();
+                match op {
+                    Op::Contains => value.contains(&literal),
+                    _ => match (value.parse::<f64>(), &literal.parse::<f64>()) {
+                        (Ok(value), Ok(literal)) => op.apply(value.partial_cmp(literal)),
+                        _ => op.apply(Some(value.as_str().cmp(literal.as_str()))),
+                    },
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+impl Op {
+    fn apply(self, ordering: Option<std::cmp::Ordering>) -> bool {
+        use std::cmp::Ordering::*;
+        match (self, ordering) {
+            (_, None) => self == Op::Ne,
+            (Op::Eq, Some(ordering)) => ordering == Equal,
+            (Op::Ne, Some(ordering)) => ordering != Equal,
+            (Op::Lt, Some(ordering)) => ordering == Less,
+            (Op::Le, Some(ordering)) => ordering != Greater,
+            (Op::Gt, Some(ordering)) => ordering == Greater,
+            (Op::Ge, Some(ordering)) => ordering != Less,
+            (Op::Contains, Some(_)) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Literal {
+    Number(f64),
+    Str(String),
+    Word(String),
+}
+
+impl Literal {
+    fn as_string(&self) -> String {
+        match self {
+            Literal::Number(value) => value.to_string(),
+            Literal::Str(value) | Literal::Word(value) => value.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    Present(Field),
+    Compare(Field, Op, Literal),
+}
+
+impl Expr {
+    fn eval(&self, input: &FilterInput) -> bool {
+        match self {
+            Expr::And(exprs) => exprs.iter().all(|expr| expr.eval(input)),
+            Expr::Or(exprs) => exprs.iter().any(|expr| expr.eval(input)),
+            Expr::Not(a) => !a.eval(input),
+            Expr::Present(field) => field.value(input).is_some_and(|value| value.truthy()),
+            Expr::Compare(field, op, literal) => match field.value(input) {
+                Some(value) => value.compare(*op, literal),
+                None => *op == Op::Ne,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Number(f64),
+    Str(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, FilterError> {
+    let bytes = input.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let c = input[i..].chars().next().unwrap_or_default();
+        let two = input.get(i..i + 2).unwrap_or("");
+        let (token, len) = match c {
+            _ if c.is_whitespace() => {
+                i += c.len_utf8();
+                continue;
+            }
+            '(' => (Token::Open, 1),
+            ')' => (Token::Close, 1),
+            _ if two == "&&" => (Token::And, 2),
+            _ if two == "||" => (Token::Or, 2),
+            _ if two == "==" => (Token::Op(Op::Eq), 2),
+            _ if two == "!=" => (Token::Op(Op::Ne), 2),
+            _ if two == "<=" => (Token::Op(Op::Le), 2),
+            _ if two == ">=" => (Token::Op(Op::Ge), 2),
+            _ if two == "~=" => (Token::Op(Op::Contains), 2),
+            '=' => (Token::Op(Op::Eq), 1),
+            '<' => (Token::Op(Op::Lt), 1),
+            '>' => (Token::Op(Op::Gt), 1),
+            '!' => (Token::Not, 1),
+            '"' => {
+                let mut value = String::new();
+                let mut chars = input[i + 1..].char_indices();
+                let len = loop {
+                    match chars.next() {
+                        Some((offset, '"')) => break offset + 2,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, 'n')) => value.push('\n'),
+                            Some((_, escaped)) => value.push(escaped),
+                            None => return Err(error(start, "unterminated string")),
+                        },
+                        Some((_, c)) => value.push(c),
+                        None => return Err(error(start, "unterminated string")),
+                    }
+                };
+                (Token::Str(value), len)
+            }
+            _ if c.is_ascii_digit() || (c == '-' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit)) => {
+                let len = 1 + bytes[i + 1..].iter().take_while(|b| b.is_ascii_alphanumeric() || **b == b'.').count();
+                let text = &input[start..start + len];
+                let number = match text.strip_prefix("0x") {
+                    Some(hex) => u64::from_str_radix(hex, 16).map(|value| value as f64).ok(),
+                    None => text.parse().ok(),
+                };
+                let number = number.ok_or_else(|| error(start, &format!("invalid number {:?}", text)))?;
+                (Token::Number(number), len)
+            }
+            _ if c.is_alphabetic() || c == '_' => {
+                let len: usize = input[i..]
+                    .chars()
+                    .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '.')
+                    .map(char::len_utf8)
+                    .sum();
+                (Token::Word(input[start..start + len].to_string()), len)
+            }
+            _ => return Err(error(start, &format!("unexpected {:?}", c))),
+        };
+        tokens.push((start, token));
+        i += len;
+    }
+    Ok(tokens)
+}
+
+fn error(position: usize, message: &str) -> FilterError {
+    FilterError { position, message: message.to_string() }
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    index: usize,
+    end: usize,
+    /// Parentheses and `!` around the current token.
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(_, token)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.index).map_or(self.end, |(position, _)| *position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.index).map(|(_, token)| token.clone());
+        self.index += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Expr, FilterError> {
+        let mut exprs = vec![self.and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.index += 1;
+            exprs.push(self.and()?);
+        }
+        Ok(if exprs.len() == 1 { exprs.remove(0) } else { Expr::Or(exprs) })
+    }
+
+    fn and(&mut self) -> Result<Expr, FilterError> {
+        let mut exprs = vec![self.unary()?];
+        while self.peek() == Some(&Token::And) {
+            self.index += 1;
+            exprs.push(self.unary()?);
+        }
+        Ok(if exprs.len() == 1 { exprs.remove(0) } else { Expr::And(exprs) })
+    }
+
+    /// Parses what follows a `!` or `(`, one level deeper.
+    fn nested(&mut self, position: usize, parse: fn(&mut Self) -> Result<Expr, FilterError>) -> Result<Expr, FilterError> {
+        if self.depth == MAX_DEPTH {
+            return Err(error(position, "expression nested too deeply"));
+        }
+        self.depth += 1;
+        let expr = parse(self);
+        self.depth -= 1;
+        expr
+    }
+
+    fn unary(&mut self) -> Result<Expr, FilterError> {
+        let position = self.position();
+        match self.next() {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.nested(position, Self::unary)?))),
+            Some(Token::Open) => {
+                let expr = self.nested(position, Self::or)?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err(error(position, "unclosed parenthesis")),
+                }
+            }
+            Some(Token::Word(path)) => {
+                let field = Field::parse(&path).ok_or_else(|| error(position, &format!("unknown field {:?}", path)))?;
+                let Some(Token::Op(op)) = self.peek().cloned() else {
+                    return Ok(Expr::Present(field));
+                };
+                self.index += 1;
+                let position = self.position();
+                let literal = match self.next() {
+                    Some(Token::Number(value)) => Literal::Number(value),
+                    Some(Token::Str(value)) => Literal::Str(value),
+                    Some(Token::Word(value)) => Literal::Word(value),
+                    _ => return Err(error(position, "expected a value")),
+                };
+                Ok(Expr::Compare(field, op, literal))
+            }
+            _ => Err(error(position, "expected a field")),
+        }
+    }
+}
+
+/// A parsed filter expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    expr: Expr,
+}
+
+impl Filter {
+    pub fn parse(input: &str) -> Result<Filter, FilterError> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, index: 0, end: input.len(), depth: 0 };
+        let expr = parser.or()?;
+        if parser.index < parser.tokens.len() {
+            return Err(error(parser.position(), "expected && or ||"));
+        }
+        Ok(Filter { expr })
+    }
+
+    pub fn matches(&self, input: &FilterInput) -> bool {
+        self.expr.eval(input)
+    }
+
+    /// Decodes the raw packet and matches it.
+    pub fn matches_data(&self, direction: Direction, channel_id: u8, data: &[u8]) -> bool {
+        self.matches_decoded(direction, channel_id, data, packet_handler::decode(data).ok().as_ref())
+    }
+
+    /// Matches the raw packet `data`, already decoded as `decoded`.
+    fn matches_decoded(&self, direction: Direction, channel_id: u8, data: &[u8], decoded: Option<&(EPacketType, GamePacket)>) -> bool {
+        let packet_type = match decoded {
+            Some((packet_type, _)) => *packet_type,
+            None if data.len() >= 4 => EPacketType::from(LittleEndian::read_u32(&data[0..4])),
+            None => EPacketType::NetMessageUnknown,
+        };
+        self.matches(&FilterInput {
+            direction,
+            channel_id,
+            size: data.len(),
+            packet_type,
+            packet: decoded.map(|(_, packet)| packet),
+        })
+    }
+
+    pub fn matches_captured(&self, packet: &CapturedPacket) -> bool {
+        self.matches_decoded(packet.direction, packet.channel_id, &packet.data, packet.decoded())
+    }
+}
+
+impl FromStr for Filter {
+    type Err = FilterError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Filter::parse(input)
+    }
+}
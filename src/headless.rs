@@ -0,0 +1,90 @@
+//! Sessions without a game client: the proxy asks `server_data.php` for the login server and
+//! logs in with a login text from `config.json` or the last game client's.
+//!
+//! While headless the proxy answers pings, follows `OnSendToServer` and keeps tracking the
+//! world. A game client connecting through the proxy attaches to the running session, and with
+//! `headless.keep_alive` on, the session carries on when it leaves.
+
+use std::fmt;
+use std::net::SocketAddr;
+use log::info;
+use crate::resolver::{self, ServerDataInput};
+use crate::utils::text_parse;
+use crate::{global, session, utils};
+
+/// The login text for a new headless session, with `meta` from the server data.
+pub fn login_text(configured: &str, remembered: Option<String>, meta: Option<&str>) -> Option<String> {
+    let login = if configured.is_empty() { remembered? } else { configured.to_string() };
+    Some(match meta {
+        Some(meta) => text_parse::set_field(&login, "meta", meta),
+        None => login,
+    })
+}
+
+#[derive(Debug)]
+pub enum StartError {
+    /// A session is already running.
+    Running,
+    /// The login server couldn't be reached or there is nothing to log in with.
+    Failed(String),
+}
+
+impl fmt::Display for StartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StartError::Running => write!(f, "a session is already running"),
+            StartError::Failed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<&str> for StartError {
+    fn from(message: &str) -> Self {
+        StartError::Failed(message.to_string())
+    }
+}
+
+impl From<String> for StartError {
+    fn from(message: String) -> Self {
+        StartError::Failed(message)
+    }
+}
+
+/// Starts a headless session. Blocks on the `server_data.php` request.
+pub fn start() -> Result<SocketAddr, StartError> {
+    if !session::is_idle() {
+        return Err(StartError::Running);
+    }
+    let config = utils::config::get_headless_config();
+    if config.login.is_empty() && session::login_text().is_none() {
+        return Err("no login text configured and no game client logged in yet".into());
+    }
+    let ip = resolver::resolve_ip("www.growtopia1.com").ok_or("failed to resolve the login server")?;
+    let input = ServerDataInput { version: config.version, platform: config.platform, protocol: config.protocol };
+    let server_data = resolver::resolve_server_data(&ip, input).ok_or("failed to get the server data")?;
+    let parsed = text_parse::parse_and_store_as_map(&server_data);
+    let (Some(server), Some(port)) = (parsed.get("server"), parsed.get("port")) else {
+        return Err(format!("server data without a server: {}", server_data).into());
+    };
+    let address: SocketAddr = format!("{}:{}", server, port).parse().map_err(|e| format!("invalid server address: {}", e))?;
+    let login = login_text(&config.login, session::login_text(), parsed.get("meta").map(String::as_str))
+        .ok_or("no login text configured and no game client logged in yet")?;
+    // Another session may have started during the request.
+    if !session::start_headless(address, &login) {
+        return Err(StartError::Running);
+    }
+    info!("Started a headless session on {}", address);
+    *global().server_data.lock().unwrap() = parsed;
+    Ok(address)
+}
+
+/// Ends the headless session and disconnects from the server.
+pub fn stop() -> bool {
+    if !session::is_headless() {
+        return false;
+    }
+    info!("Stopping the headless session");
+    session::reset();
+    crate::packet_handler::disconnect(true);
+    true
+}
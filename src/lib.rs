@@ -0,0 +1,70 @@
+pub mod resolver;
+pub mod utils;
+pub mod enet;
+pub mod types;
+pub mod packet_handler;
+pub mod variant_handler;
+pub mod dialog;
+pub mod menu;
+pub mod session;
+pub mod headless;
+pub mod ping;
+pub mod metrics;
+pub mod logging;
+pub mod world;
+pub mod snapshot;
+pub mod pathfinding;
+pub mod movement;
+pub mod capture;
+pub mod dashboard;
+pub mod filter;
+pub mod rules;
+pub mod scripting;
+#[cfg(feature = "plugins")]
+pub mod plugins;
+#[cfg(feature = "tui")]
+pub mod tui;
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+pub struct GlobalData {
+    pub server_data: Mutex<HashMap<String, String>>,
+    /// Host the game client connects to.
+    pub server_enet: OnceLock<enet::HostHandle>,
+    /// Host connected to the Growtopia server.
+    pub client_enet: OnceLock<enet::HostHandle>,
+    pub session: Mutex<session::Session>,
+    pub pings: Mutex<ping::Pings>,
+    pub metrics: Mutex<metrics::Metrics>,
+    pub world: Mutex<world::World>,
+    /// Loaded from `items_dat` at startup, if it exists.
+    pub items: OnceLock<types::item_database::ItemDatabase>,
+    /// Recent packets for the monitors.
+    pub capture: Mutex<capture::Capture>,
+    pub rules: Mutex<rules::Rules>,
+    pub scripts: Mutex<scripting::Scripts>,
+    #[cfg(feature = "plugins")]
+    pub plugins: Mutex<plugins::Plugins>,
+}
+
+pub fn global() -> &'static GlobalData {
+    static GLOBAL: OnceLock<GlobalData> = OnceLock::new();
+    GLOBAL.get_or_init(|| {
+        GlobalData {
+            server_data: Mutex::new(HashMap::new()),
+            server_enet: OnceLock::new(),
+            client_enet: OnceLock::new(),
+            session: Mutex::new(session::Session::default()),
+            pings: Mutex::new(ping::Pings::default()),
+            metrics: Mutex::new(metrics::Metrics::default()),
+            world: Mutex::new(world::World::default()),
+            items: OnceLock::new(),
+            capture: Mutex::new(capture::Capture::default()),
+            rules: Mutex::new(rules::Rules::default()),
+            scripts: Mutex::new(scripting::Scripts::new()),
+            #[cfg(feature = "plugins")]
+            plugins: Mutex::new(plugins::Plugins::default()),
+        }
+    })
+}
@@ -0,0 +1,57 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::fs::File;
+use std::io;
+use std::sync::{Mutex, OnceLock};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::EnvFilter;
+use crate::types::config::LoggingConfig;
+
+static HEX_DUMP: OnceLock<HashSet<String>> = OnceLock::new();
+
+/// Installs the global subscriber, writing to `file` instead of stdout when given. Records from
+/// the `log` macros are forwarded to it.
+pub fn init(config: &LoggingConfig, file: Option<File>) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(config.directives()));
+    let ansi = file.is_none();
+    let writer = match file {
+        Some(file) => BoxMakeWriter::new(Mutex::new(file)),
+        None => BoxMakeWriter::new(io::stdout),
+    };
+    let builder = tracing_subscriber::fmt().with_env_filter(filter).with_writer(writer).with_ansi(ansi);
+    if config.json {
+        builder.json().with_current_span(true).with_span_list(false).init();
+    } else {
+        builder.init();
+    }
+    let _ = HEX_DUMP.set(config.hex_dump.iter().cloned().collect());
+}
+
+/// Whether a packet known by any of `names` was chosen for hex dumping.
+pub fn should_dump(names: &[&str]) -> bool {
+    HEX_DUMP.get().is_some_and(|types| names.iter().any(|name| types.contains(*name)))
+}
+
+/// Formats `data` as offset, hex and ASCII columns, 16 bytes per line.
+pub fn hex_dump(data: &[u8]) -> String {
+    let mut out = String::new();
+    for (line, chunk) in data.chunks(16).enumerate() {
+        if line > 0 {
+            out.push('\n');
+        }
+        let _ = write!(out, "{:08x} ", line * 16);
+        for i in 0..16 {
+            match chunk.get(i) {
+                Some(byte) => { let _ = write!(out, " {:02x}", byte); }
+                None => out.push_str("   "),
+            }
+            if i == 7 {
+                out.push(' ');
+            }
+        }
+        out.push_str("  |");
+        out.extend(chunk.iter().map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' }));
+        out.push('|');
+    }
+    out
+}
@@ -1,76 +1,92 @@
-mod resolver;
-mod utils;
-mod enet;
-mod types;
-mod packet_handler;
-mod variant_handler;
-
 use std::{env, thread};
-use std::collections::HashMap;
-use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::fs::File;
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
-use axum::http::{HeaderMap, Response, StatusCode, Uri};
-use axum::response::{Html, IntoResponse};
+use std::time::Duration;
+use axum::http::HeaderMap;
+use axum::response::Html;
 use axum::{Form, Router};
 use axum::routing::post;
 use axum_server::tls_rustls::RustlsConfig;
-use log::{error, info};
-use serde_json::Value;
-use serde::Deserialize;
-use std::sync::{Mutex, OnceLock};
-use std::time::Duration;
-use rusty_enet;
+use log::{error, info, warn};
 use std::str::FromStr;
-use crate::utils::text_parse::{map_to_string, parse_and_store_as_map};
-
-struct GlobalData {
-    server_data: Mutex<HashMap<String, String>>,
-    server_enet_host: Mutex<Option<rusty_enet::Host<UdpSocket>>>,
-    server_peer_id: Mutex<Option<rusty_enet::PeerID>>,
-    client_enet_host: Mutex<Option<rusty_enet::Host<UdpSocket>>>,
-    client_peer_id: Mutex<Option<rusty_enet::PeerID>>,
-}
-
-fn global() -> &'static GlobalData {
-    static GLOBAL: OnceLock<GlobalData> = OnceLock::new();
-    GLOBAL.get_or_init(|| {
-        GlobalData {
-            server_data: Mutex::new(HashMap::new()),
-            server_enet_host: Mutex::new(None),
-            server_peer_id: Mutex::new(None),
-            client_enet_host: Mutex::new(None),
-            client_peer_id: Mutex::new(None),
-        }
-    })
-}
+use growtopia_proxy::{capture, dashboard, enet, global, headless, logging, resolver, session, utils};
+use growtopia_proxy::types::item_database::ItemDatabase;
+use growtopia_proxy::utils::text_parse::{map_to_string, parse_and_store_as_map};
 
 fn main() {
-    env::set_var("RUST_LOG", env::var("RUST_LOG").unwrap_or_else(|_| "growtopia_proxy".to_string()));
-
-    env_logger::init();
+    let tui_requested = env::args().any(|arg| arg == "--tui");
+    let tui = tui_requested && cfg!(feature = "tui");
     utils::config::init();
+    let log_file = tui.then(|| File::create("proxy.log").expect("Failed to create log file"));
+    logging::init(&utils::config::get_logging_config(), log_file);
+    if tui_requested && !tui {
+        warn!("Built without the tui feature, ignoring --tui");
+    }
     rustls::crypto::ring::default_provider().install_default().expect("Failed to install rustls crypto provider");
     info!("Growtopia Proxy started");
+    load_items();
+    capture::with(|capture| capture.enabled = tui || utils::config::get_capture_config().enabled);
 
-    thread::spawn(|| {
-        enet::server::setup();
-    });
+    let threads = [
+        thread::spawn(|| {
+            enet::server::setup();
+        }),
+        thread::spawn(|| {
+            enet::client::setup();
+        }),
+        thread::spawn(|| {
+            setup_webserver();
+        }),
+    ];
 
-    thread::spawn(|| {
-        enet::client::setup();
-    });
+    if utils::config::get_headless_config().start {
+        thread::spawn(start_headless);
+    }
 
-    thread::spawn(|| {
-        setup_webserver();
-    });
+    #[cfg(feature = "tui")]
+    if tui {
+        if let Err(e) = growtopia_proxy::tui::run() {
+            error!("Terminal UI failed: {}", e);
+        }
+        std::process::exit(0);
+    }
+
+    for thread in threads {
+        let _ = thread.join();
+    }
+}
+
+fn start_headless() {
+    // The session connects through the ENet client, which registers itself once running.
+    while global().client_enet.get().is_none() {
+        thread::sleep(Duration::from_millis(100));
+    }
+    if let Err(e) = headless::start() {
+        error!("Failed to start the headless session: {}", e);
+    }
+}
 
-    loop {}
+fn load_items() {
+    let path = utils::config::get_items_dat();
+    match ItemDatabase::load(&path) {
+        Ok(items) => {
+            info!("Loaded {} items from {}", items.items.len(), path);
+            let _ = global().items.set(items);
+        }
+        Err(e) => warn!("Pathfinding is unavailable without {}: {}", path, e),
+    }
 }
 
 #[tokio::main]
 async fn setup_webserver() {
     info!("Running webserver");
     let port = utils::config::get_web_server_port();
+    let dashboard_config = utils::config::get_dashboard_config();
+    let ip = dashboard_config.bind.parse::<IpAddr>().unwrap_or_else(|e| {
+        error!("Invalid dashboard.bind {:?}, listening on 127.0.0.1: {}", dashboard_config.bind, e);
+        IpAddr::from([127, 0, 0, 1])
+    });
     let config = RustlsConfig::from_pem_file(
         PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .join("certs")
@@ -79,9 +95,14 @@ async fn setup_webserver() {
             .join("certs")
             .join("key.pem"),
     ).await.unwrap();
-    let app = Router::new()
+    let mut app = Router::new()
         .route("/growtopia/server_data.php", post(server_data));
-    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    if ip.is_loopback() || !dashboard_config.token.is_empty() {
+        app = app.merge(dashboard::router(dashboard_config.token));
+    } else {
+        warn!("Dashboard disabled: set dashboard.token to serve it on {}", ip);
+    }
+    let addr = SocketAddr::from((ip, port));
     axum_server::bind_rustls(addr, config)
         .serve(app.into_make_service())
         .await
@@ -96,16 +117,13 @@ async fn server_data(header_map: HeaderMap, Form(input): Form<resolver::ServerDa
     let mut data = global().server_data.lock().unwrap();
     let mut parsed = parse_and_store_as_map(&server_data);
     let address = format!("{}:{}", parsed["server"], parsed["port"]);
-    info!("ENet client connecting to server: {}", address);
-    if let Some(mut host) = global().client_enet_host.lock().unwrap().as_mut() {
-        let address = SocketAddr::from_str(&address).unwrap();
-        match host.connect(address, 2, 0) {
-            Ok(..) => {
-                info!("ENet client connected to server");
-            }
-            Err(err) => {
-                error!("ENet client unable to connect to server: {}", err);
-            }
+    if session::is_headless() {
+        info!("Game client attaching to the headless session");
+    } else {
+        info!("ENet client connecting to server: {}", address);
+        match SocketAddr::from_str(&address) {
+            Ok(address) => session::connect(address),
+            Err(err) => error!("ENet client unable to connect to server: {}", err),
         }
     }
     *data = parsed.clone();
@@ -113,4 +131,5 @@ async fn server_data(header_map: HeaderMap, Form(input): Form<resolver::ServerDa
     parsed.insert("server".to_string(), "127.0.0.1".to_string());
     parsed.insert("port".to_string(), port.to_string());
     Html(map_to_string(&parsed))
-}
\ No newline at end of file
+}
+
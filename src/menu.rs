@@ -0,0 +1,109 @@
+//! The proxy's in-game settings menu, opened with `/proxy` or, when enabled, by wrenching yourself.
+//!
+//! It lists the built-in hooks and the configured rules as checkboxes. Its `dialog_return` is
+//! saved to `config.json` and never reaches the server.
+
+use std::collections::HashSet;
+use log::info;
+use rusty_enet::Packet;
+use crate::dialog::{Dialog, DialogReturn};
+use crate::packet_handler::resend_packet;
+use crate::types::config::{Hooks, Rule};
+use crate::types::epacket_type::EPacketType;
+use crate::utils::text_parse;
+use crate::{utils, variant_handler, world};
+
+pub const COMMAND: &str = "/proxy";
+pub const DIALOG_NAME: &str = "proxy_settings";
+
+/// The hooks shown in the menu, by checkbox id.
+fn hook_options(hooks: &mut Hooks) -> [(&'static str, &'static str, &mut bool); 3] {
+    [
+        ("spawn_mod_state", "Set mod state on spawn", &mut hooks.spawn_mod_state),
+        ("block_app_integrity_fail", "Block app integrity fail", &mut hooks.block_app_integrity_fail),
+        ("wrench_menu", "Open this menu by wrenching yourself", &mut hooks.wrench_menu),
+    ]
+}
+
+/// The checkbox ids of `rules`, from each rule's name or, without one, its expression. Replies are
+/// matched by id, so rules added, removed or reordered while the menu is open keep their own
+/// state. Rules whose names only differ in punctuation get `_2`, `_3`... in order.
+pub fn rule_ids(rules: &[Rule]) -> Vec<String> {
+    let mut used = HashSet::new();
+    rules
+        .iter()
+        .map(|rule| {
+            let key = if rule.name.is_empty() { &rule.expression } else { &rule.name };
+            let key: String = key.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+            let base = format!("rule_{}", key);
+            let (mut id, mut n) = (base.clone(), 1);
+            while used.contains(&id) {
+                n += 1;
+                id = format!("{}_{}", base, n);
+            }
+            used.insert(id.clone());
+            id
+        })
+        .collect()
+}
+
+/// The menu showing `hooks` and `rules`.
+pub fn dialog(hooks: &Hooks, rules: &[Rule]) -> Dialog {
+    let mut hooks = hooks.clone();
+    let mut dialog = Dialog::new().label("`wProxy settings``").spacer().textbox("Hooks");
+    for (id, label, enabled) in hook_options(&mut hooks) {
+        dialog = dialog.checkbox(id, label, *enabled);
+    }
+    if !rules.is_empty() {
+        dialog = dialog.spacer().textbox("Rules");
+        for (rule, id) in rules.iter().zip(rule_ids(rules)) {
+            let label = if rule.name.is_empty() { rule.expression.as_str() } else { rule.name.as_str() };
+            dialog = dialog.checkbox(&id, label, rule.enabled);
+        }
+    }
+    dialog.end(DIALOG_NAME, "Cancel", "Save")
+}
+
+/// Sets `hooks` and the `enabled` flag of `rules` from the checkboxes of a menu reply. Rules
+/// without a checkbox in the reply are left alone.
+pub fn apply(reply: &DialogReturn, hooks: &mut Hooks, rules: &mut [Rule]) {
+    for (id, _, enabled) in hook_options(hooks) {
+        *enabled = reply.checked(id);
+    }
+    for (id, rule) in rule_ids(rules).into_iter().zip(rules) {
+        if reply.get(&id).is_some() {
+            rule.enabled = reply.checked(&id);
+        }
+    }
+}
+
+fn opens_menu(message: &str) -> bool {
+    match text_parse::get_field(message, "action") {
+        Some("input") => text_parse::get_field(message, "text").is_some_and(|text| text.eq_ignore_ascii_case(COMMAND)),
+        Some("wrench") if utils::config::get_hooks().wrench_menu => {
+            let local_net_id = world::with(|world| world.local_net_id);
+            text_parse::get_field(message, "netid").and_then(|net_id| net_id.parse().ok()).is_some_and(|net_id: u32| Some(net_id) == local_net_id)
+        }
+        _ => false,
+    }
+}
+
+/// Handles a text packet from the game client. Returns true when it was meant for the proxy and
+/// must not be forwarded.
+pub fn handle(message: &str) -> bool {
+    if opens_menu(message) {
+        let data = variant_handler::dialog_request(&dialog(&utils::config::get_hooks(), &utils::config::get_rules()));
+        resend_packet(&Packet::reliable(&data), &EPacketType::NetMessageGamePacket, 0, true);
+        return true;
+    }
+    match DialogReturn::parse(message) {
+        Some(reply) if reply.name == DIALOG_NAME => {
+            utils::config::update(|config| apply(&reply, &mut config.hooks, &mut config.rules));
+            info!("Proxy settings changed in game");
+            let data = variant_handler::console_message("`2Proxy settings saved.``");
+            resend_packet(&Packet::reliable(&data), &EPacketType::NetMessageGamePacket, 0, true);
+            true
+        }
+        _ => false,
+    }
+}
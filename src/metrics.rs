@@ -0,0 +1,242 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+use crate::global;
+
+/// Function names counted under their own `function` label. Any other name comes from the
+/// network and is counted as `other`, so a server or client can't add labels without bound.
+pub const KNOWN_FUNCTIONS: &[&str] = &[
+    "OnAddNotification",
+    "OnConsoleMessage",
+    "OnCountryState",
+    "OnDialogRequest",
+    "OnFailedToEnterWorld",
+    "OnInvis",
+    "OnNameChanged",
+    "OnParticleEffect",
+    "OnPlayPositioned",
+    "OnRemove",
+    "OnRequestWorldSelectMenu",
+    "OnSendToServer",
+    "OnSetBux",
+    "OnSetClothing",
+    "OnSetCurrentWeather",
+    "OnSetFreezeState",
+    "OnSetPos",
+    "OnSpawn",
+    "OnStoreRequest",
+    "OnTalkBubble",
+    "OnTextOverlay",
+    "OnZoomCamera",
+    "SetHasGrowID",
+];
+
+/// Which side a packet came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Direction {
+    FromClient,
+    FromServer,
+}
+
+impl Direction {
+    /// Direction of a packet received by the host `packet_handler` calls `is_client`.
+    pub fn from_is_client(is_client: bool) -> Self {
+        if is_client {
+            Direction::FromServer
+        } else {
+            Direction::FromClient
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::FromClient => "from_client",
+            Direction::FromServer => "from_server",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Counters {
+    pub packets: BTreeMap<Direction, u64>,
+    pub bytes: BTreeMap<Direction, u64>,
+    pub packet_types: BTreeMap<(Direction, String), u64>,
+    pub tank_types: BTreeMap<(Direction, String), u64>,
+    pub functions: BTreeMap<(Direction, String), u64>,
+    pub decode_errors: BTreeMap<Direction, u64>,
+}
+
+/// Round-trip time and packet loss of an ENet peer, sampled by the host that owns it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PeerStats {
+    pub round_trip_time: Duration,
+    /// Fraction of reliable packets lost.
+    pub packet_loss: f64,
+}
+
+/// The server's pings in the current session and how long the game client took to answer them.
+#[derive(Debug, Clone, Default)]
+pub struct PingStats {
+    pub requests: u64,
+    pub client_replies: u64,
+    /// Pings the proxy answered because the game client couldn't.
+    pub proxy_replies: u64,
+    /// Time from forwarding a ping to the game client until its reply.
+    pub last_round_trip: Option<Duration>,
+    pub min_round_trip: Option<Duration>,
+    pub max_round_trip: Option<Duration>,
+    total_round_trip: Duration,
+}
+
+impl PingStats {
+    pub fn record_round_trip(&mut self, round_trip: Duration) {
+        self.client_replies += 1;
+        self.last_round_trip = Some(round_trip);
+        self.min_round_trip = Some(self.min_round_trip.map_or(round_trip, |min| min.min(round_trip)));
+        self.max_round_trip = Some(self.max_round_trip.map_or(round_trip, |max| max.max(round_trip)));
+        self.total_round_trip += round_trip;
+    }
+
+    pub fn average_round_trip(&self) -> Option<Duration> {
+        (self.client_replies > 0).then(|| self.total_round_trip / self.client_replies as u32)
+    }
+}
+
+/// Counters since the proxy started, and since the current session connected.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    pub global: Counters,
+    pub session: Counters,
+    /// Keyed by the direction of the traffic the peer sends us.
+    pub peers: BTreeMap<Direction, PeerStats>,
+    pub ping: PingStats,
+}
+
+impl Metrics {
+    fn each(&mut self, mut f: impl FnMut(&mut Counters)) {
+        f(&mut self.global);
+        f(&mut self.session);
+    }
+
+    pub fn record_packet(&mut self, direction: Direction, size: usize, packet_type: &str) {
+        self.each(|counters| {
+            *counters.packets.entry(direction).or_default() += 1;
+            *counters.bytes.entry(direction).or_default() += size as u64;
+            *counters.packet_types.entry((direction, packet_type.to_string())).or_default() += 1;
+        });
+    }
+
+    pub fn record_tank(&mut self, direction: Direction, tank_type: &str) {
+        self.each(|counters| *counters.tank_types.entry((direction, tank_type.to_string())).or_default() += 1);
+    }
+
+    /// Counts a function call, as `other` unless it is one of [`KNOWN_FUNCTIONS`].
+    pub fn record_function(&mut self, direction: Direction, function: &str) {
+        let function = if KNOWN_FUNCTIONS.contains(&function) { function } else { "other" };
+        self.each(|counters| *counters.functions.entry((direction, function.to_string())).or_default() += 1);
+    }
+
+    pub fn record_decode_error(&mut self, direction: Direction) {
+        self.each(|counters| *counters.decode_errors.entry(direction).or_default() += 1);
+    }
+
+    pub fn reset_session(&mut self) {
+        self.session = Counters::default();
+        self.peers.clear();
+        self.ping = PingStats::default();
+    }
+
+    /// Renders every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let scopes = [("global", &self.global), ("session", &self.session)];
+
+        header(&mut out, "packets_total", "counter", "Packets received by the proxy.");
+        for (scope, counters) in scopes {
+            for (direction, value) in &counters.packets {
+                sample(&mut out, "packets_total", &[("scope", scope), ("direction", direction.as_str())], *value as f64);
+            }
+        }
+        header(&mut out, "bytes_total", "counter", "Bytes received by the proxy.");
+        for (scope, counters) in scopes {
+            for (direction, value) in &counters.bytes {
+                sample(&mut out, "bytes_total", &[("scope", scope), ("direction", direction.as_str())], *value as f64);
+            }
+        }
+        header(&mut out, "packet_types_total", "counter", "Packets received by message type.");
+        for (scope, counters) in scopes {
+            for ((direction, packet_type), value) in &counters.packet_types {
+                sample(&mut out, "packet_types_total", &[("scope", scope), ("direction", direction.as_str()), ("type", packet_type)], *value as f64);
+            }
+        }
+        header(&mut out, "tank_types_total", "counter", "Tank packets received by tank packet type.");
+        for (scope, counters) in scopes {
+            for ((direction, tank_type), value) in &counters.tank_types {
+                sample(&mut out, "tank_types_total", &[("scope", scope), ("direction", direction.as_str()), ("type", tank_type)], *value as f64);
+            }
+        }
+        header(&mut out, "call_functions_total", "counter", "CallFunction packets received by function name.");
+        for (scope, counters) in scopes {
+            for ((direction, function), value) in &counters.functions {
+                sample(&mut out, "call_functions_total", &[("scope", scope), ("direction", direction.as_str()), ("function", function)], *value as f64);
+            }
+        }
+        header(&mut out, "decode_errors_total", "counter", "Packets that failed to decode and were forwarded as-is.");
+        for (scope, counters) in scopes {
+            for (direction, value) in &counters.decode_errors {
+                sample(&mut out, "decode_errors_total", &[("scope", scope), ("direction", direction.as_str())], *value as f64);
+            }
+        }
+        header(&mut out, "enet_round_trip_seconds", "gauge", "Round-trip time of the connected ENet peers.");
+        for (direction, stats) in &self.peers {
+            sample(&mut out, "enet_round_trip_seconds", &[("direction", direction.as_str())], stats.round_trip_time.as_secs_f64());
+        }
+        header(&mut out, "enet_packet_loss_ratio", "gauge", "Reliable packet loss of the connected ENet peers.");
+        for (direction, stats) in &self.peers {
+            sample(&mut out, "enet_packet_loss_ratio", &[("direction", direction.as_str())], stats.packet_loss);
+        }
+        header(&mut out, "ping_requests_total", "counter", "Ping requests from the server this session.");
+        sample(&mut out, "ping_requests_total", &[], self.ping.requests as f64);
+        header(&mut out, "ping_replies_total", "counter", "Ping replies sent to the server this session, by who answered.");
+        sample(&mut out, "ping_replies_total", &[("by", "client")], self.ping.client_replies as f64);
+        sample(&mut out, "ping_replies_total", &[("by", "proxy")], self.ping.proxy_replies as f64);
+        header(&mut out, "ping_round_trip_seconds", "gauge", "Time the game client took to answer the server's pings this session.");
+        let round_trips = [("last", self.ping.last_round_trip), ("min", self.ping.min_round_trip), ("max", self.ping.max_round_trip), ("avg", self.ping.average_round_trip())];
+        for (stat, value) in round_trips {
+            if let Some(value) = value {
+                sample(&mut out, "ping_round_trip_seconds", &[("stat", stat)], value.as_secs_f64());
+            }
+        }
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP growtopia_proxy_{} {}", name, help);
+    let _ = writeln!(out, "# TYPE growtopia_proxy_{} {}", name, kind);
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: f64) {
+    let labels = labels
+        .iter()
+        .map(|(key, value)| format!("{}=\"{}\"", key, escape(value)))
+        .collect::<Vec<String>>()
+        .join(",");
+    if labels.is_empty() {
+        let _ = writeln!(out, "growtopia_proxy_{} {}", name, value);
+    } else {
+        let _ = writeln!(out, "growtopia_proxy_{}{{{}}} {}", name, labels, value);
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+pub fn with<T>(f: impl FnOnce(&mut Metrics) -> T) -> T {
+    f(&mut global().metrics.lock().unwrap())
+}
+
+pub fn render() -> String {
+    global().metrics.lock().unwrap().render()
+}
@@ -0,0 +1,170 @@
+//! Synthesised movement: `NetGamePacketState` sequences that walk the local player along a path.
+//!
+//! Each state moves the player at most `speed * interval` pixels towards the next tile, with the
+//! velocity and `WALK`, `STANDING`, `JUMP` and `FACING_LEFT` flags a real client would send for
+//! that move. The states go to the server and are echoed to the game client, and an `OnSetPos`
+//! at the end, or when the walk is stopped, puts the client's own player where the server thinks
+//! it is.
+
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use log::info;
+use rusty_enet::Packet;
+use crate::packet_handler::resend_packet;
+use crate::types::epacket_type::EPacketType;
+use crate::types::packet_flag::PacketFlag;
+use crate::types::tank_view::{State, TankView};
+use crate::types::vector2::Vector2;
+use crate::utils::variant::{Variant, VariantList};
+use crate::{variant_handler, world};
+
+/// Width and height of a tile in pixels.
+pub const TILE_SIZE: f32 = 32.0;
+
+/// The walk in progress. Walk threads send under this lock, so nothing goes out for a walk
+/// after [`stop`] or a newer [`walk`] returns.
+static WALKING: Mutex<Walking> = Mutex::new(Walking { generation: 0, stopped: false });
+
+struct Walking {
+    /// Bumped by every walk and by [`stop`]; a walk ends once it no longer matches.
+    generation: u64,
+    /// The last bump came from [`stop`] rather than a newer walk.
+    stopped: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MovementConfig {
+    /// Walking speed in pixels per second.
+    pub speed: f32,
+    /// Time between two states.
+    pub interval: Duration,
+}
+
+impl Default for MovementConfig {
+    fn default() -> Self {
+        Self { speed: 250.0, interval: Duration::from_millis(100) }
+    }
+}
+
+/// A state to send `delay` after the previous one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub delay: Duration,
+    pub state: State,
+}
+
+/// Top left corner of a tile in pixels.
+pub fn tile_position((x, y): (u32, u32)) -> Vector2 {
+    Vector2 { x: x as f32 * TILE_SIZE, y: y as f32 * TILE_SIZE }
+}
+
+fn state(net_id: u32, flags: PacketFlag, position: Vector2, velocity: Vector2) -> State {
+    State { net_id, flags, position, velocity, tile_x: -1, tile_y: -1 }
+}
+
+/// The states that walk player `net_id` from `from` through the tiles of `path`, ending with
+/// the player standing still on the last one.
+pub fn plan(net_id: u32, from: Vector2, path: &[(u32, u32)], config: &MovementConfig) -> Vec<Step> {
+    let step_length = config.speed * config.interval.as_secs_f32();
+    let mut steps = Vec::new();
+    let mut position = from;
+    let mut facing_left = false;
+    for target in path.iter().map(|&tile| tile_position(tile)) {
+        let (dx, dy) = (target.x - position.x, target.y - position.y);
+        let distance = (dx * dx + dy * dy).sqrt();
+        if distance == 0.0 {
+            continue;
+        }
+        if dx != 0.0 {
+            facing_left = dx < 0.0;
+        }
+        let velocity = Vector2 { x: dx / distance * config.speed, y: dy / distance * config.speed };
+        let flags = PacketFlag::empty()
+            .with(PacketFlag::WALK, dx != 0.0)
+            .with(PacketFlag::STANDING, dy == 0.0)
+            .with(PacketFlag::JUMP, dy < 0.0)
+            .with(PacketFlag::FACING_LEFT, facing_left);
+        let count = (distance / step_length).ceil().max(1.0) as u32;
+        let start = position;
+        for index in 1..=count {
+            let progress = index as f32 / count as f32;
+            position = Vector2 { x: start.x + dx * progress, y: start.y + dy * progress };
+            steps.push(Step { delay: config.interval, state: state(net_id, flags, position, velocity) });
+        }
+        position = target;
+    }
+    if !steps.is_empty() {
+        let flags = PacketFlag::STANDING.with(PacketFlag::FACING_LEFT, facing_left);
+        steps.push(Step { delay: config.interval, state: state(net_id, flags, position, Vector2::default()) });
+    }
+    steps
+}
+
+fn send(data: &[u8], to_client: bool) {
+    resend_packet(&Packet::reliable(data), &EPacketType::NetMessageGamePacket, 0, to_client);
+}
+
+/// Moves the game client's own player to `position`.
+fn set_position(net_id: u32, position: Vector2) {
+    let mut variant = VariantList::new();
+    variant.push(Variant::String("OnSetPos".to_string()));
+    variant.push(Variant::Vec2((position.x, position.y)));
+    send(&variant_handler::call_function(&variant, net_id), true);
+}
+
+/// Walks the local player along `path` on a background thread, replacing any walk in progress.
+/// A walk that is stopped puts the client's player where the last state sent to the server left
+/// it; a replaced one leaves that to the walk replacing it. Returns false when the local player
+/// isn't known or the path goes nowhere.
+pub fn walk(path: &[(u32, u32)], config: MovementConfig) -> bool {
+    let Some((net_id, position)) = world::with(|world| world.local_player().map(|player| (player.net_id, player.position))) else {
+        return false;
+    };
+    let steps = plan(net_id, position, path, &config);
+    if steps.is_empty() {
+        return false;
+    }
+    let generation = {
+        let mut walking = WALKING.lock().unwrap();
+        walking.generation += 1;
+        walking.stopped = false;
+        walking.generation
+    };
+    info!("Walking {} tiles in {} states", path.len(), steps.len());
+    thread::spawn(move || {
+        let mut sent = None;
+        for step in steps {
+            thread::sleep(step.delay);
+            let walking = WALKING.lock().unwrap();
+            if walking.generation != generation {
+                if walking.stopped && walking.generation == generation + 1 {
+                    if let Some(position) = sent {
+                        set_position(net_id, position);
+                    }
+                }
+                return;
+            }
+            sent = Some(step.state.position);
+            let mut tank_packet = step.state.to_tank_packet();
+            world::with(|world| world.apply_tank(&tank_packet));
+            let data = tank_packet.to_message(&[], &[]);
+            send(&data, false);
+            send(&data, true);
+        }
+        let walking = WALKING.lock().unwrap();
+        if walking.generation == generation {
+            if let Some(position) = sent {
+                set_position(net_id, position);
+            }
+        }
+    });
+    true
+}
+
+/// Stops the walk in progress, leaving the player where the last state put it.
+pub fn stop() {
+    let mut walking = WALKING.lock().unwrap();
+    walking.generation += 1;
+    walking.stopped = true;
+}
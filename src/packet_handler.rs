@@ -1,138 +1,305 @@
-use std::net::SocketAddr;
+use std::fmt;
 use byteorder::{ByteOrder, LittleEndian};
-use log::{error, info, warn};
+use tracing::{debug, error, field, info, info_span, warn};
 use rusty_enet::Packet;
+use crate::enet::Command;
 use crate::types::epacket_type::EPacketType;
 use crate::types::etank_packet_type::ETankPacketType;
-use crate::types::tank_packet::TankPacket;
-use crate::{global, variant_handler};
+use crate::types::tank_packet::{TankPacket, TankPacketError, TANK_PACKET_SIZE};
+use crate::utils::variant::VariantList;
+use crate::metrics::{self, Direction};
+use crate::filter::FilterInput;
+use crate::rules::{self, Verdict};
+use crate::{capture, global, logging, menu, ping, scripting, session, snapshot, utils, variant_handler, world};
 
-pub fn handle(packet: &mut Packet, is_client: bool) {
+#[derive(Debug)]
+pub enum DecodeError {
+    TooShort(usize),
+    Tank(TankPacketError),
+    Variant(std::io::Error),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::TooShort(len) => write!(f, "packet too short ({} bytes)", len),
+            DecodeError::Tank(err) => write!(f, "invalid tank packet: {}", err),
+            DecodeError::Variant(err) => write!(f, "invalid variant list: {}", err),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum GamePacket {
+    Tank {
+        tank_packet: TankPacket,
+        variants: Option<VariantList>,
+        /// Bytes after the tank packet's extended data.
+        trailing: Vec<u8>,
+    },
+    Text(String),
+    Raw,
+}
+
+pub fn decode(data: &[u8]) -> Result<(EPacketType, GamePacket), DecodeError> {
+    if data.len() < 4 {
+        return Err(DecodeError::TooShort(data.len()));
+    }
+    let packet_type = EPacketType::from(LittleEndian::read_u32(&data[0..4]));
+
+    let packet = match packet_type {
+        EPacketType::NetMessageGamePacket => {
+            let (tank_packet, extended_data, trailing) = TankPacket::decode(&data[4..]).map_err(DecodeError::Tank)?;
+            let variants = match tank_packet.r#type {
+                ETankPacketType::NetGamePacketCallFunction => {
+                    Some(VariantList::deserialize(extended_data).map_err(DecodeError::Variant)?)
+                }
+                _ => None,
+            };
+            GamePacket::Tank { tank_packet, variants, trailing: trailing.to_vec() }
+        }
+        EPacketType::NetMessageGameMessage
+        | EPacketType::NetMessageGenericText
+        | EPacketType::NetMessageTrack => GamePacket::Text(String::from_utf8_lossy(&data[4..]).into_owned()),
+        _ => GamePacket::Raw,
+    };
+
+    Ok((packet_type, packet))
+}
+
+/// Re-encodes a decoded packet that was received as `original`. Tank packets without variants
+/// keep their original extended data. Raw packets return None.
+pub fn encode(packet_type: EPacketType, packet: &mut GamePacket, original: &[u8]) -> Option<Vec<u8>> {
+    match packet {
+        GamePacket::Tank { tank_packet, variants: Some(variants), trailing } => Some(tank_packet.to_message(&variants.serialize(), trailing)),
+        GamePacket::Tank { tank_packet, variants: None, .. } => {
+            let mut data = original.to_vec();
+            data.get_mut(4..4 + TANK_PACKET_SIZE)?.copy_from_slice(&tank_packet.encode());
+            Some(data)
+        }
+        GamePacket::Text(text) => {
+            let mut data = (packet_type as u32).to_le_bytes().to_vec();
+            data.extend_from_slice(text.as_bytes());
+            Some(data)
+        }
+        _ => None,
+    }
+}
+
+pub fn handle(packet: &mut Packet, channel_id: u8, is_client: bool) {
     let data = packet.data();
-    let packet_id = LittleEndian::read_u32(&data[0..4]);
-    let packet_type = EPacketType::from(packet_id);
-    info!("{} Received {:?} packet", if is_client { "Client" } else { "Server" }, packet_type);
+    let direction = Direction::from_is_client(is_client);
+    let session_id = session::id();
+    let span = info_span!(
+        "packet",
+        session = session_id,
+        direction = direction.as_str(),
+        packet_type = field::Empty,
+        tank_type = field::Empty,
+        function = field::Empty,
+        size = data.len(),
+        channel_id,
+    );
+    let _entered = span.enter();
+    let (packet_type, mut decoded) = match decode(data) {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            let packet_type = if data.len() >= 4 { EPacketType::from(LittleEndian::read_u32(&data[0..4])) } else { EPacketType::NetMessageUnknown };
+            let packet_type_name = format!("{:?}", packet_type);
+            span.record("packet_type", packet_type_name.as_str());
+            error!(error = %e, "Failed to decode packet");
+            capture::with(|capture| capture.push(session_id, direction, channel_id, data, format!("{} (undecodable: {})", packet_type_name, e)));
+            metrics::with(|metrics| {
+                metrics.record_packet(direction, data.len(), &packet_type_name);
+                metrics.record_decode_error(direction);
+            });
+            if logging::should_dump(&[&packet_type_name]) {
+                info!("Payload\n{}", logging::hex_dump(data));
+            }
+            resend_packet(packet, &packet_type, channel_id, is_client);
+            return;
+        }
+    };
 
-    match packet_type {
-        EPacketType::NetMessageGamePacket => match bincode::deserialize::<TankPacket>(&data[4..]) {
-            Ok(mut tank_packet) => {
-                info!("{} Received tank: {:?}", if is_client { "Client" } else { "Server" }, tank_packet.r#type);
-                match tank_packet.r#type {
-                    ETankPacketType::NetGamePacketCallFunction => {
-                        let packet = variant_handler::handle(&data[60..], &mut tank_packet, &data[0..4]);
-                        if let Some(packet) = packet {
-                            resend_packet(&packet, &packet_type, is_client);
+    let packet_type_name = format!("{:?}", packet_type);
+    let (tank_type_name, function_name) = match &decoded {
+        GamePacket::Tank { tank_packet, variants, .. } => (
+            Some(format!("{:?}", tank_packet.r#type)),
+            variants.as_ref().and_then(|variants| variants.get(0)).map(|function| function.as_string()),
+        ),
+        _ => (None, None),
+    };
+    span.record("packet_type", packet_type_name.as_str());
+    if let Some(tank_type_name) = &tank_type_name {
+        span.record("tank_type", tank_type_name.as_str());
+    }
+    if let Some(function_name) = &function_name {
+        span.record("function", function_name.as_str());
+    }
+    metrics::with(|metrics| {
+        metrics.record_packet(direction, data.len(), &packet_type_name);
+        if let Some(tank_type_name) = &tank_type_name {
+            metrics.record_tank(direction, tank_type_name);
+        }
+        if let Some(function_name) = &function_name {
+            metrics.record_function(direction, function_name);
+        }
+    });
+    let names: Vec<&str> = [Some(packet_type_name.as_str()), tank_type_name.as_deref(), function_name.as_deref()].into_iter().flatten().collect();
+    capture::with(|capture| capture.push(session_id, direction, channel_id, data, names.join(" ")));
+    if logging::should_dump(&names) {
+        info!("Payload\n{}", logging::hex_dump(data));
+    }
+    world::observe(&decoded, data, is_client);
+
+    let input = FilterInput { direction, channel_id, size: data.len(), packet_type, packet: None };
+    let mut modified = match rules::apply(&input, &mut decoded) {
+        Verdict::Drop => return,
+        verdict => verdict == Verdict::Modified,
+    };
+    match scripting::run(direction, packet_type, &mut decoded) {
+        Verdict::Drop => {
+            info!("Dropped by a script");
+            return;
+        }
+        verdict => modified |= verdict == Verdict::Modified,
+    }
+    if modified {
+        if let Some(rewritten) = encode(packet_type, &mut decoded, data) {
+            *packet = Packet::new(&rewritten, packet.kind());
+        }
+    }
+    #[cfg(feature = "plugins")]
+    let (packet_type, decoded) = match run_plugins(direction, packet, packet_type, decoded) {
+        Some(decoded) => decoded,
+        None => return,
+    };
+
+    match (&packet_type, decoded) {
+        (EPacketType::NetMessageGamePacket, GamePacket::Tank { mut tank_packet, variants, trailing }) => {
+            match tank_packet.r#type {
+                ETankPacketType::NetGamePacketCallFunction => {
+                    if let Some(mut variants) = variants {
+                        let rewritten = variant_handler::handle(&mut variants, &mut tank_packet, &trailing);
+                        if let Some(rewritten) = rewritten {
+                            debug!(rewritten_size = rewritten.len(), "Rewrote function call");
+                            let rewritten = Packet::new(&rewritten, packet.kind());
+                            resend_packet(&rewritten, &packet_type, channel_id, is_client);
                             return;
                         }
-                    },
-                    ETankPacketType::NetGamePacketDisconnect => {
-                        // Disconnect client
-                        let mut client_host = global().client_enet_host.lock().unwrap();
-                        let peer_id = global().client_peer_id.lock().unwrap();
-                        if let Some(client_host) = &mut *client_host {
-                            if let Some(peer_id) = &*peer_id {
-                                let peer = client_host.peer_mut(*peer_id);
-                                peer.disconnect_now(0);
-                            }
-                            let (server, port) = {
-                                let server_data = global().server_data.lock().unwrap();
-                                (server_data.get("server").unwrap().clone(), server_data.get("port").unwrap().clone())
-                            };
-                            let address = SocketAddr::new(server.parse().unwrap(), port.parse().unwrap());
-                            match client_host.connect(address, 2, 0) {
-                                Ok(..) => {
-                                    info!("Client Re-Connected to server");
-                                }
-                                Err(e) => {
-                                    error!("Client Failed to connect to server: {:?}", e);
-                                }
-                            }
-                            client_host.flush();
-                        }
-
-                        // Disconnect server
-                        disconnect(false);
-                        return;
                     }
-                    ETankPacketType::NetGamePacketAppIntegrityFail => {
-                        warn!("{} App integrity fail packet blocked", if is_client { "Client" } else { "Server" });
-                        return;
-                    }
-                    _ => {}
+                },
+                // Move the upstream to the sub-server and have the client reconnect to us
+                ETankPacketType::NetGamePacketDisconnect if !is_client && session::begin_reconnect() => {
+                    disconnect(false);
+                    return;
                 }
-            }
-            Err(e) => {
-                error!("{} Failed to deserialize tank packet: {:?}", if is_client { "Client" } else { "Server" }, e);
+                ETankPacketType::NetGamePacketPingRequest if is_client && ping::handle_request(&tank_packet) => return,
+                ETankPacketType::NetGamePacketPingReply if !is_client && ping::handle_reply(&tank_packet) => return,
+                ETankPacketType::NetGamePacketAppIntegrityFail if utils::config::get_hooks().block_app_integrity_fail => {
+                    warn!("App integrity fail packet blocked");
+                    return;
+                }
+                _ => {}
             }
         },
-        EPacketType::NetMessageGameMessage => {
-            let message = String::from_utf8_lossy(&data);
-            info!("{} Received message: {}", if is_client { "Client" } else { "Server" }, message);
+        (EPacketType::NetMessageGameMessage, GamePacket::Text(message)) => {
+            debug!(text = %message);
             if message.contains("action|quit") && !message.contains("exit") {
+                if session::detach() {
+                    disconnect(false);
+                    return;
+                }
+                session::reset();
                 disconnect(true);
                 disconnect(false);
                 return;
             }
         }
-        EPacketType::NetMessageGenericText => {
-            let message = String::from_utf8_lossy(&data);
-            info!("{} Received generic text: {}", if is_client { "Client" } else { "Server" }, message);
+        (EPacketType::NetMessageGenericText, GamePacket::Text(message)) if !is_client && (menu::handle(&message) || snapshot::handle(&message)) => {
+            debug!(text = %message, "Handled by the proxy menu");
+            return;
+        }
+        (EPacketType::NetMessageGenericText, GamePacket::Text(message)) => {
+            debug!(text = %message);
+            if !is_client && (message.contains("tankIDName|") || message.contains("requestedName|")) {
+                if let Some(login) = session::attach_login(message.trim_end_matches('\0')) {
+                    info!("Logging the attached game client in with the session's credentials");
+                    let mut data = (EPacketType::NetMessageGenericText as u32).to_le_bytes().to_vec();
+                    data.extend_from_slice(login.as_bytes());
+                    *packet = Packet::new(&data, packet.kind());
+                }
+                session::remember_login(channel_id, packet.clone());
+            }
         },
-        EPacketType::NetMessageTrack => {
-            let message = String::from_utf8_lossy(&data);
-            info!("{} Received track: {}", if is_client { "Client" } else { "Server" }, message);
+        (EPacketType::NetMessageServerHello, _) if is_client && (session::resume_login() || session::headless_login()) => {
+            return;
+        }
+        (EPacketType::NetMessageTrack, GamePacket::Text(message)) => {
+            debug!(text = %message);
         }
         _ => {}
     }
-    resend_packet(packet, &packet_type, is_client);
+    resend_packet(packet, &packet_type, channel_id, is_client);
+}
+
+/// Runs the WASM plugins on the packet about to be forwarded, decoding it again when they
+/// replaced it. Returns None when a plugin dropped it.
+#[cfg(feature = "plugins")]
+fn run_plugins(direction: Direction, packet: &mut Packet, packet_type: EPacketType, decoded: GamePacket) -> Option<(EPacketType, GamePacket)> {
+    let mut data = packet.data().to_vec();
+    match crate::plugins::run(direction, &mut data) {
+        Verdict::Drop => None,
+        Verdict::Modified => match decode(&data) {
+            Ok(replaced) => {
+                *packet = Packet::new(&data, packet.kind());
+                Some(replaced)
+            }
+            Err(e) => {
+                warn!(error = %e, "Ignoring a plugin's undecodable replacement");
+                Some((packet_type, decoded))
+            }
+        },
+        Verdict::Pass => Some((packet_type, decoded)),
+    }
 }
 
-pub fn resend_packet(packet: &Packet, packet_type: &EPacketType, is_client: bool) {
-    let (host_lock, peer_id_lock) = if is_client {
-        (&global().server_enet_host, &global().server_peer_id)
+/// Forwards `packet` to the other side on `channel_id`, keeping its reliability.
+pub fn resend_packet(packet: &Packet, packet_type: &EPacketType, channel_id: u8, is_client: bool) {
+    if is_client && session::is_headless() {
+        debug!(?packet_type, "No game client attached, not forwarding");
+        return;
+    }
+    let to = if is_client { "client" } else { "server" };
+    let (channel_id, packet) = if is_client {
+        (channel_id, packet.clone())
     } else {
-        (&global().client_enet_host, &global().client_peer_id)
+        match session::queue_upstream(channel_id, packet.clone()) {
+            Some(send) => send,
+            None => {
+                info!(?packet_type, "Queued until the server connects");
+                return;
+            }
+        }
     };
-
-    if let Ok(peer_id) = peer_id_lock.lock() {
-        if let Some(peer_id) = *peer_id {
-            if let Ok(mut host) = host_lock.lock() {
-                if let Some(host) = host.as_mut() {
-                    let peer = host.peer_mut(peer_id);
-                    if let Err(err) = peer.send(0, &packet) {
-                        error!("{} Failed sending packet: {}",  if is_client { "Client" } else { "Server" }, err);
-                    } else {
-                        info!("{} Sent {:?} packet", if is_client { "Client" } else { "Server" }, packet_type);
-                    }
-                } else {
-                    error!("{} Failed to send packet: Host is None", if is_client { "Client" } else { "Server" });
-                }
+    let host = if is_client { &global().server_enet } else { &global().client_enet };
+    match host.get() {
+        Some(host) => {
+            if host.send(Command::Send { channel_id, packet }) {
+                info!(?packet_type, "Forwarded to {}", to);
             } else {
-                error!("{} Failed to send packet: Host lock failed", if is_client { "Client" } else { "Server" });
+                error!("Failed to forward packet to {}: Host stopped", to);
             }
-        } else {
-            error!("{} Failed to send packet: Peer ID is None", if is_client { "Client" } else { "Server" });
         }
-    } else {
-        error!("{} Failed to send packet: Peer ID lock failed", if is_client { "Client" } else { "Server" });
+        None => {
+            error!("Failed to forward packet to {}: Host is None", to);
+        }
     }
 }
 
 pub fn disconnect(is_client: bool) {
-    let (host_lock, peer_id_lock) = if is_client {
-        (&global().client_enet_host, &global().client_peer_id)
-    } else {
-        (&global().server_enet_host, &global().server_peer_id)
-    };
-
-    let mut host = host_lock.lock().unwrap();
-    let mut peer_id = peer_id_lock.lock().unwrap();
-    if let Some(host) = &mut *host {
-        if let Some(peer_id) = &*peer_id {
-            let peer = host.peer_mut(*peer_id);
-            peer.disconnect_now(0);
-        }
-        host.flush();
+    let host = if is_client { &global().client_enet } else { &global().server_enet };
+    if let Some(host) = host.get() {
+        host.send(Command::Disconnect);
     }
-    peer_id.take();
-}
\ No newline at end of file
+}
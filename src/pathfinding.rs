@@ -0,0 +1,140 @@
+//! A* search for a walkable path between two tiles of the current world.
+//!
+//! Moves are one tile up, down, left or right. Whether a tile can be entered comes from its
+//! foreground's collision type in the item database: solid blocks never, platforms from anywhere
+//! but above, switchable blocks depending on their state and entrances only with access to the
+//! lock they are under. Gravity and jump height are not modelled.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use crate::global;
+use crate::types::item_database::{CollisionType, ItemDatabase};
+use crate::types::world_data::{Tile, TileExtra, TileFlags};
+use crate::world::{self, World};
+
+/// Locks whose area is the whole world, for tiles outside any smaller lock.
+pub const WORLD_LOCK_ITEM_IDS: [u16; 3] = [242, 1796, 7188];
+
+pub struct Pathfinder<'a> {
+    world: &'a World,
+    items: &'a ItemDatabase,
+    /// The player whose access decides which entrances open. None for a player with no access.
+    user_id: Option<u32>,
+}
+
+impl<'a> Pathfinder<'a> {
+    /// A pathfinder for the local player.
+    pub fn new(world: &'a World, items: &'a ItemDatabase) -> Self {
+        let user_id = world.local_player().map(|player| player.user_id);
+        Self { world, items, user_id }
+    }
+
+    pub fn with_user_id(mut self, user_id: Option<u32>) -> Self {
+        self.user_id = user_id;
+        self
+    }
+
+    fn lock_of(&self, tile: &Tile) -> Option<&TileExtra> {
+        let lock = match tile.lock_index {
+            Some(index) => self.world.tiles.get(index as usize),
+            None => self.world.tiles.iter().find(|tile| WORLD_LOCK_ITEM_IDS.contains(&tile.foreground)),
+        };
+        lock.and_then(|lock| lock.extra.as_ref()).filter(|extra| matches!(extra, TileExtra::Lock { .. }))
+    }
+
+    /// Whether the player may pass an entrance: it is public, unlocked, or the player owns or
+    /// has access to its lock.
+    pub fn has_access(&self, tile: &Tile) -> bool {
+        if tile.flags.contains(TileFlags::PUBLIC) {
+            return true;
+        }
+        match self.lock_of(tile) {
+            Some(TileExtra::Lock { owner_uid, access_uids, .. }) => {
+                self.user_id.is_some_and(|user_id| user_id == *owner_uid || access_uids.contains(&user_id))
+            }
+            _ => true,
+        }
+    }
+
+    /// Whether the tile at `to` can be entered from the neighbouring tile at `from`. Tiles whose
+    /// item is missing from the database count as solid.
+    pub fn can_enter(&self, from: (u32, u32), to: (u32, u32)) -> bool {
+        let Some(tile) = self.world.tile(to.0 as i32, to.1 as i32) else {
+            return false;
+        };
+        if tile.foreground == 0 {
+            return true;
+        }
+        let Some(item) = self.items.get(tile.foreground) else {
+            return false;
+        };
+        match item.collision_type {
+            CollisionType::None | CollisionType::JumpDown => true,
+            CollisionType::JumpThrough | CollisionType::Cloud => to.1 <= from.1,
+            CollisionType::IfOff => tile.flags.contains(TileFlags::ON),
+            CollisionType::IfOn => !tile.flags.contains(TileFlags::ON),
+            CollisionType::OneWay => match to.0.cmp(&from.0) {
+                Ordering::Greater => !tile.flags.contains(TileFlags::FLIPPED_X),
+                Ordering::Less => tile.flags.contains(TileFlags::FLIPPED_X),
+                Ordering::Equal => true,
+            },
+            CollisionType::Gateway
+            | CollisionType::VipEntrance
+            | CollisionType::TeamEntrance
+            | CollisionType::GuildEntrance
+            | CollisionType::FriendEntrance => self.has_access(tile),
+            CollisionType::Solid | CollisionType::Adventure | CollisionType::Other(_) => false,
+        }
+    }
+
+    fn neighbours(&self, (x, y): (u32, u32)) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let (width, height) = (self.world.width, self.world.height);
+        [(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)]
+            .into_iter()
+            .filter(move |&(nx, ny)| nx < width && ny < height)
+            .filter(move |&to| self.can_enter((x, y), to))
+    }
+
+    /// The shortest path from `start` to `target` as tile positions, both included, or None
+    /// when there is none. The start tile itself doesn't need to be walkable.
+    pub fn find_path(&self, start: (u32, u32), target: (u32, u32)) -> Option<Vec<(u32, u32)>> {
+        let (width, height) = (self.world.width, self.world.height);
+        if start.0 >= width || start.1 >= height || target.0 >= width || target.1 >= height {
+            return None;
+        }
+        let index = |(x, y): (u32, u32)| y as usize * width as usize + x as usize;
+        let estimate = |(x, y): (u32, u32)| x.abs_diff(target.0) + y.abs_diff(target.1);
+        let mut cost = vec![u32::MAX; width as usize * height as usize];
+        let mut came_from = vec![None; cost.len()];
+        let mut open = BinaryHeap::new();
+        cost[index(start)] = 0;
+        open.push(Reverse((estimate(start), 0, start)));
+        while let Some(Reverse((_, steps, position))) = open.pop() {
+            if position == target {
+                let mut path = vec![position];
+                while let Some(previous) = came_from[index(*path.last().unwrap())] {
+                    path.push(previous);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if steps > cost[index(position)] {
+                continue;
+            }
+            for next in self.neighbours(position) {
+                if steps + 1 < cost[index(next)] {
+                    cost[index(next)] = steps + 1;
+                    came_from[index(next)] = Some(position);
+                    open.push(Reverse((steps + 1 + estimate(next), steps + 1, next)));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Finds a path for the local player in the current world. None without an item database too.
+pub fn find_path(start: (u32, u32), target: (u32, u32)) -> Option<Vec<(u32, u32)>> {
+    let items = global().items.get()?;
+    world::with(|world| Pathfinder::new(world, items).find_path(start, target))
+}
@@ -0,0 +1,204 @@
+//! Keeps sessions alive while the game client can't answer the server's pings.
+//!
+//! `NetGamePacketPingRequest` is forwarded to the game client as usual. If the client hasn't
+//! replied after `ping.client_timeout_ms`, because it is paused or in the background, the proxy
+//! replies itself, and drops the client's reply when it finally comes. While the session is
+//! resuming or headless the proxy replies straight away. The proxy's replies copy the ranges,
+//! speed and gravity of the client's last reply.
+
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use log::{debug, info, warn};
+use rusty_enet::Packet;
+use crate::packet_handler::resend_packet;
+use crate::types::config::PingConfig;
+use crate::types::epacket_type::EPacketType;
+use crate::types::tank_packet::TankPacket;
+use crate::types::tank_view::{PingReply, PingRequest, TankView};
+use crate::utils::watch::Watch;
+use crate::{global, metrics, session, utils};
+
+/// Unanswered pings kept for the round-trip statistics.
+const MAX_PENDING: usize = 32;
+/// Pings the proxy answered itself that are remembered, to drop the client's late replies.
+const MAX_ANSWERED: usize = 32;
+
+/// Proton's `HashString` over `data`, which the client sends as [`PingReply::hash`] of the
+/// ping's time. The format follows Proton's source and hasn't been checked against a captured
+/// reply; a client reply with a different hash is only logged.
+pub fn proton_hash(data: &[u8]) -> u32 {
+    data.iter().fold(0x55555555u32, |acc, &byte| (acc >> 27).wrapping_add(acc << 5).wrapping_add(byte as u32))
+}
+
+/// A reply to the ping sent at `time`, with the ranges, speed and gravity of `template`.
+pub fn reply_to(time: u32, template: &PingReply) -> PingReply {
+    PingReply { hash: proton_hash(&time.to_le_bytes()), time, ..template.clone() }
+}
+
+/// What to do with a packet after [`Pings`] saw it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PingAction {
+    Forward,
+    /// Don't forward the packet; send this reply to the server instead.
+    Answer(PingReply),
+    /// Don't forward the packet.
+    Drop,
+}
+
+/// Pings waiting for the game client's reply in the current session.
+#[derive(Debug)]
+pub struct Pings {
+    /// Ping times forwarded to the game client, oldest first.
+    pending: VecDeque<(u32, Instant)>,
+    answered: VecDeque<u32>,
+    /// The game client's last reply.
+    template: PingReply,
+    /// The `ping` section of `config.json`, reloaded when the file changes.
+    config: PingConfig,
+    watch: Watch,
+}
+
+impl Default for Pings {
+    fn default() -> Self {
+        Self {
+            pending: VecDeque::new(),
+            answered: VecDeque::new(),
+            template: PingReply { build_range: 64.0, punch_range: 64.0, speed: 1000.0, gravity: 250.0, ..Default::default() },
+            config: PingConfig::default(),
+            watch: Watch::default(),
+        }
+    }
+}
+
+impl Pings {
+    /// The ping config, read again when `config.json` changed. Keeps the current one while the
+    /// file can't be read.
+    fn config(&mut self) -> PingConfig {
+        if self.watch.file_changed(Path::new("config.json")) {
+            match utils::config::read() {
+                Ok(config) => self.config = config.ping,
+                Err(e) => {
+                    warn!("Keeping the current ping config, config.json couldn't be read: {}", e);
+                    self.watch.retry();
+                }
+            }
+        }
+        self.config.clone()
+    }
+
+    fn answer(&mut self, time: u32) -> PingReply {
+        if self.answered.len() >= MAX_ANSWERED {
+            self.answered.pop_front();
+        }
+        self.answered.push_back(time);
+        metrics::with(|metrics| metrics.ping.proxy_replies += 1);
+        reply_to(time, &self.template)
+    }
+
+    /// A ping request from the server. `client_available` is false when no game client can
+    /// answer it, so the proxy does.
+    pub fn request(&mut self, request: &PingRequest, client_available: bool, now: Instant) -> PingAction {
+        metrics::with(|metrics| metrics.ping.requests += 1);
+        if !client_available {
+            return PingAction::Answer(self.answer(request.time));
+        }
+        if self.pending.len() >= MAX_PENDING {
+            self.pending.pop_front();
+        }
+        self.pending.push_back((request.time, now));
+        PingAction::Forward
+    }
+
+    /// A ping reply from the game client.
+    pub fn reply(&mut self, reply: &PingReply, now: Instant) -> PingAction {
+        if let Some(index) = self.answered.iter().position(|&time| time == reply.time) {
+            self.answered.remove(index);
+            debug!("Dropping the client's late reply to ping {}", reply.time);
+            return PingAction::Drop;
+        }
+        if reply.hash != proton_hash(&reply.time.to_le_bytes()) {
+            debug!("Client ping reply hash {:#x} differs from the computed one", reply.hash);
+        }
+        self.template = reply.clone();
+        if let Some(index) = self.pending.iter().position(|&(time, _)| time == reply.time) {
+            let (_, forwarded_at) = self.pending.remove(index).unwrap();
+            metrics::with(|metrics| metrics.ping.record_round_trip(now.duration_since(forwarded_at)));
+        }
+        PingAction::Forward
+    }
+
+    /// Replies to the pings the game client left unanswered for `timeout`.
+    pub fn expire(&mut self, timeout: Duration, now: Instant) -> Vec<PingReply> {
+        let mut replies = Vec::new();
+        while let Some(&(time, forwarded_at)) = self.pending.front() {
+            if now.duration_since(forwarded_at) < timeout {
+                break;
+            }
+            self.pending.pop_front();
+            replies.push(self.answer(time));
+        }
+        replies
+    }
+
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.answered.clear();
+    }
+}
+
+fn send(reply: &PingReply) {
+    let data = reply.to_tank_packet().to_message(&[], &[]);
+    resend_packet(&Packet::reliable(&data), &EPacketType::NetMessageGamePacket, 0, false);
+}
+
+fn act(action: PingAction) -> bool {
+    match action {
+        PingAction::Forward => false,
+        PingAction::Answer(reply) => {
+            info!("Answered ping {} for the game client", reply.time);
+            send(&reply);
+            true
+        }
+        PingAction::Drop => true,
+    }
+}
+
+/// Handles a ping request from the server. Returns true when the proxy answered it and it must
+/// not be forwarded.
+pub fn handle_request(tank_packet: &TankPacket) -> bool {
+    let Some(request) = tank_packet.view::<PingRequest>() else {
+        return false;
+    };
+    let config = global().pings.lock().unwrap().config();
+    let client_available = !config.answer || !(session::is_resuming() || session::is_headless());
+    let action = global().pings.lock().unwrap().request(&request, client_available, Instant::now());
+    act(action)
+}
+
+/// Handles a ping reply from the game client. Returns true when it must not be forwarded.
+pub fn handle_reply(tank_packet: &TankPacket) -> bool {
+    let Some(reply) = tank_packet.view::<PingReply>() else {
+        return false;
+    };
+    let action = global().pings.lock().unwrap().reply(&reply, Instant::now());
+    act(action)
+}
+
+/// Answers pings the game client is taking too long with. Called periodically by the upstream host.
+pub fn check_timeout() {
+    let mut pings = global().pings.lock().unwrap();
+    if pings.pending.is_empty() {
+        return;
+    }
+    let config = pings.config();
+    if !config.answer {
+        return;
+    }
+    let replies = pings.expire(Duration::from_millis(config.client_timeout_ms), Instant::now());
+    drop(pings);
+    for reply in replies {
+        info!("Game client didn't answer ping {}, answering for it", reply.time);
+        send(&reply);
+    }
+}
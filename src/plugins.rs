@@ -0,0 +1,275 @@
+//! Sandboxed WebAssembly plugins from the `plugins` directory, reloaded when they change.
+//!
+//! Each `.wasm` file is instantiated in its own store with the limits from the `plugins` section
+//! of `config.json`. A plugin that traps, runs out of fuel or breaks the ABI is disabled until
+//! its file changes; the packet it was handling is forwarded as if it had passed.
+//!
+//! ABI version 1. Integers are `i32`, pointers are offsets into the plugin's exported `memory`.
+//!
+//! Exports:
+//! - `proxy_abi_version() -> i32` must return 1.
+//! - `proxy_alloc(len) -> ptr` returns a buffer for the proxy to write a packet into. The buffer
+//!   belongs to the plugin afterwards.
+//! - `proxy_on_packet(direction, ptr, len) -> verdict` is called for every packet about to be
+//!   forwarded. `direction` is 0 when it came from the game client and 1 when it came from the
+//!   server. The bytes are the game message: a little-endian `u32` message type followed by the
+//!   text or tank packet, after rules and scripts ran. The verdict is 0 to pass, 1 to drop and
+//!   2 to forward the packet given to `set_packet` instead.
+//! - `proxy_init()` is optional and called once after the plugin loads.
+//!
+//! Imports from the `proxy` module:
+//! - `set_packet(ptr, len)` sets the replacement for a verdict of 2.
+//! - `inject(to, ptr, len)` sends a game message of its own, to the game client when `to` is 0
+//!   and to the server when it is 1. Injected packets are sent even when the packet is dropped.
+//! - `log(ptr, len)` logs a UTF-8 message.
+
+use std::path::Path;
+use byteorder::{ByteOrder, LittleEndian};
+use log::{info, warn};
+use rusty_enet::Packet;
+use wasmtime::{Caller, Engine, Error, Extern, Instance, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc};
+use crate::metrics::Direction;
+use crate::packet_handler::resend_packet;
+use crate::rules::Verdict;
+use crate::types::config::PluginConfig;
+use crate::types::epacket_type::EPacketType;
+use crate::utils::watch::{self, Watch};
+use crate::{global, utils};
+
+pub const PLUGINS_DIR: &str = "plugins";
+pub const ABI_VERSION: i32 = 1;
+
+const VERDICT_PASS: i32 = 0;
+const VERDICT_DROP: i32 = 1;
+const VERDICT_MODIFY: i32 = 2;
+
+/// A packet a plugin asked to send.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Injection {
+    pub to_client: bool,
+    pub data: Vec<u8>,
+}
+
+struct HostState {
+    name: String,
+    limits: StoreLimits,
+    max_injections: usize,
+    replacement: Option<Vec<u8>>,
+    injected: Vec<Injection>,
+}
+
+struct Plugin {
+    name: String,
+    store: Store<HostState>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    on_packet: TypedFunc<(i32, i32, i32), i32>,
+    fuel: u64,
+    /// Set when the plugin failed; it is skipped until reloaded.
+    failed: bool,
+}
+
+impl Plugin {
+    /// Hands one packet to the plugin. Returns its verdict and, for `Modified`, the replacement.
+    fn call(&mut self, direction: Direction, data: &[u8]) -> Result<(Verdict, Option<Vec<u8>>), Error> {
+        self.store.set_fuel(self.fuel)?;
+        let state = self.store.data_mut();
+        state.replacement = None;
+        state.injected.clear();
+        let len = i32::try_from(data.len())?;
+        let ptr = self.alloc.call(&mut self.store, len)?;
+        self.memory.write(&mut self.store, ptr as u32 as usize, data)?;
+        let direction = match direction {
+            Direction::FromClient => 0,
+            Direction::FromServer => 1,
+        };
+        match self.on_packet.call(&mut self.store, (direction, ptr, len))? {
+            VERDICT_PASS => Ok((Verdict::Pass, None)),
+            VERDICT_DROP => Ok((Verdict::Drop, None)),
+            VERDICT_MODIFY => match self.store.data_mut().replacement.take() {
+                Some(replacement) => Ok((Verdict::Modified, Some(replacement))),
+                None => Err(Error::msg("returned modify without calling set_packet")),
+            },
+            verdict => Err(Error::msg(format!("returned unknown verdict {}", verdict))),
+        }
+    }
+}
+
+/// The loaded plugins and the engine they run in.
+pub struct Plugins {
+    engine: Engine,
+    config: PluginConfig,
+    plugins: Vec<Plugin>,
+    /// Packets injected during the last `run`.
+    injected: Vec<Injection>,
+    watch: Watch,
+}
+
+impl Default for Plugins {
+    fn default() -> Self {
+        Self::new(PluginConfig::default())
+    }
+}
+
+impl Plugins {
+    pub fn new(config: PluginConfig) -> Self {
+        let mut engine_config = wasmtime::Config::new();
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config).expect("Failed to create the plugin engine");
+        Self { engine, config, plugins: Vec::new(), injected: Vec::new(), watch: Watch::default() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.plugins.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    /// Compiles and instantiates one plugin with the limits configured for its file name.
+    pub fn load_file(&mut self, path: &Path) -> Result<(), String> {
+        let name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        let limits = self.config.limits_for(&name).clone();
+        let module = Module::from_file(&self.engine, path).map_err(|e| e.to_string())?;
+        let state = HostState {
+            name: name.clone(),
+            limits: StoreLimitsBuilder::new().memory_size(limits.max_memory).instances(1).build(),
+            max_injections: limits.max_injections,
+            replacement: None,
+            injected: Vec::new(),
+        };
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(limits.fuel).map_err(|e| e.to_string())?;
+        let instance = link(&self.engine)
+            .and_then(|linker| linker.instantiate(&mut store, &module))
+            .map_err(|e| format!("{:#}", e))?;
+        let plugin = instantiate(name, store, instance, limits.fuel).map_err(|e| format!("{:#}", e))?;
+        self.plugins.push(plugin);
+        Ok(())
+    }
+
+    /// Replaces the loaded plugins with the `.wasm` files in `dir`, in file name order.
+    pub fn load_dir(&mut self, dir: &Path) {
+        self.plugins.clear();
+        for (path, _) in watch::files(dir, "wasm") {
+            match self.load_file(&path) {
+                Ok(()) => info!("Loaded plugin {}", path.display()),
+                Err(e) => warn!("Failed to load plugin {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    fn reload_if_changed(&mut self) {
+        if self.watch.dir_changed(Path::new(PLUGINS_DIR), "wasm") {
+            match utils::config::read() {
+                Ok(config) => self.config = config.plugins,
+                Err(e) => warn!("Keeping the current plugin limits, config.json couldn't be read: {}", e),
+            }
+            self.load_dir(Path::new(PLUGINS_DIR));
+        }
+    }
+
+    /// Runs every working plugin on a game message, in order. A drop stops at once; later
+    /// plugins see earlier replacements, which are written back into `data`.
+    pub fn run(&mut self, direction: Direction, data: &mut Vec<u8>) -> Verdict {
+        let mut verdict = Verdict::Pass;
+        for plugin in self.plugins.iter_mut().filter(|plugin| !plugin.failed) {
+            let result = plugin.call(direction, data);
+            let state = plugin.store.data_mut();
+            match result {
+                Ok((result, replacement)) => {
+                    self.injected.append(&mut state.injected);
+                    if let Some(replacement) = replacement {
+                        info!("Plugin {} modified the packet", plugin.name);
+                        *data = replacement;
+                        verdict = Verdict::Modified;
+                    }
+                    if result == Verdict::Drop {
+                        info!("Plugin {} dropped the packet", plugin.name);
+                        return Verdict::Drop;
+                    }
+                }
+                Err(e) => {
+                    state.injected.clear();
+                    warn!("Plugin {} failed and was disabled: {:#}", plugin.name, e);
+                    plugin.failed = true;
+                }
+            }
+        }
+        verdict
+    }
+
+    /// Packets the plugins injected since the last call.
+    pub fn take_injected(&mut self) -> Vec<Injection> {
+        std::mem::take(&mut self.injected)
+    }
+}
+
+fn link(engine: &Engine) -> Result<Linker<HostState>, Error> {
+    let mut linker = Linker::new(engine);
+    linker.func_wrap("proxy", "set_packet", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+        let data = read(&mut caller, ptr, len)?;
+        caller.data_mut().replacement = Some(data);
+        Ok(())
+    })?;
+    linker.func_wrap("proxy", "inject", |mut caller: Caller<'_, HostState>, to: i32, ptr: i32, len: i32| {
+        let data = read(&mut caller, ptr, len)?;
+        if data.len() < 4 {
+            return Err(Error::msg(format!("injected packet too short ({} bytes)", data.len())));
+        }
+        let state = caller.data_mut();
+        if state.injected.len() >= state.max_injections {
+            return Err(Error::msg(format!("injected more than {} packets", state.max_injections)));
+        }
+        state.injected.push(Injection { to_client: to == 0, data });
+        Ok(())
+    })?;
+    linker.func_wrap("proxy", "log", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+        let data = read(&mut caller, ptr, len)?;
+        info!("[plugin {}] {}", caller.data().name, String::from_utf8_lossy(&data));
+        Ok(())
+    })?;
+    Ok(linker)
+}
+
+/// Looks up the ABI exports of a new instance and runs its `proxy_init`.
+fn instantiate(name: String, mut store: Store<HostState>, instance: Instance, fuel: u64) -> Result<Plugin, Error> {
+    let version = instance.get_typed_func::<(), i32>(&mut store, "proxy_abi_version")?.call(&mut store, ())?;
+    if version != ABI_VERSION {
+        return Err(Error::msg(format!("ABI version {} is not supported, expected {}", version, ABI_VERSION)));
+    }
+    let memory = instance.get_memory(&mut store, "memory").ok_or_else(|| Error::msg("no exported memory"))?;
+    let alloc = instance.get_typed_func::<i32, i32>(&mut store, "proxy_alloc")?;
+    let on_packet = instance.get_typed_func::<(i32, i32, i32), i32>(&mut store, "proxy_on_packet")?;
+    if let Ok(init) = instance.get_typed_func::<(), ()>(&mut store, "proxy_init") {
+        init.call(&mut store, ())?;
+    }
+    Ok(Plugin { name, store, memory, alloc, on_packet, fuel, failed: false })
+}
+
+/// Copies `len` bytes at `ptr` out of the calling plugin's memory.
+fn read(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> Result<Vec<u8>, Error> {
+    let Some(Extern::Memory(memory)) = caller.get_export("memory") else {
+        return Err(Error::msg("no exported memory"));
+    };
+    let start = ptr as u32 as usize;
+    let end = start.checked_add(len as u32 as usize).ok_or_else(|| Error::msg("buffer out of bounds"))?;
+    memory.data(&caller).get(start..end).map(<[u8]>::to_vec).ok_or_else(|| Error::msg("buffer out of bounds"))
+}
+
+/// Runs the plugins in `PLUGINS_DIR` on a game message and sends what they injected.
+pub fn run(direction: Direction, data: &mut Vec<u8>) -> Verdict {
+    let (verdict, injected) = {
+        let mut plugins = global().plugins.lock().unwrap();
+        plugins.reload_if_changed();
+        let verdict = plugins.run(direction, data);
+        (verdict, plugins.take_injected())
+    };
+    for injection in injected {
+        let packet_type = EPacketType::from(LittleEndian::read_u32(&injection.data[0..4]));
+        resend_packet(&Packet::reliable(&injection.data), &packet_type, 0, injection.to_client);
+    }
+    verdict
+}
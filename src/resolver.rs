@@ -35,9 +35,9 @@ pub fn resolve_ip(domain: &str) -> Option<String> {
 
 #[derive(Deserialize, Debug)]
 pub struct ServerDataInput {
-    version: String,
-    platform: String,
-    protocol: String,
+    pub version: String,
+    pub platform: String,
+    pub protocol: String,
 }
 
 pub fn resolve_server_data(ip: &str, input: ServerDataInput) -> Option<String> {
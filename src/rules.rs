@@ -0,0 +1,186 @@
+use std::path::Path;
+use log::{info, warn};
+use serde_json::Value;
+use crate::filter::{Filter, FilterInput};
+use crate::global;
+use crate::packet_handler::GamePacket;
+use crate::types::config::{Rule, RuleAction};
+use crate::utils::{self, text_parse};
+use crate::utils::watch::Watch;
+use crate::utils::variant::{Variant, VariantList};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    Drop,
+    /// The packet was changed and must be re-encoded.
+    Modified,
+}
+
+#[derive(Debug)]
+struct CompiledRule {
+    name: String,
+    filter: Filter,
+    action: RuleAction,
+}
+
+/// The enabled rules from config, with their expressions parsed.
+#[derive(Debug, Default)]
+pub struct Rules {
+    rules: Vec<CompiledRule>,
+    watch: Watch,
+}
+
+impl Rules {
+    /// Compiles `rules`, skipping disabled ones and logging the ones that don't parse.
+    pub fn new(rules: &[Rule]) -> Self {
+        let rules = rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| rule.enabled)
+            .filter_map(|(index, rule)| {
+                let name = if rule.name.is_empty() { format!("#{}", index) } else { rule.name.clone() };
+                match Filter::parse(&rule.expression) {
+                    Ok(filter) => Some(CompiledRule { name, filter, action: rule.action.clone() }),
+                    Err(e) => {
+                        warn!("Rule {} has an invalid match expression: {}", name, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        Self { rules, watch: Watch::default() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Runs every matching rule in order. A drop stops at once; later rules see earlier changes.
+    pub fn apply(&self, input: &FilterInput, packet: &mut GamePacket) -> Verdict {
+        let mut verdict = Verdict::Pass;
+        for rule in &self.rules {
+            let input = FilterInput { packet: Some(&*packet), ..*input };
+            if !rule.filter.matches(&input) {
+                continue;
+            }
+            match &rule.action {
+                RuleAction::Drop => {
+                    info!("Rule {} dropped the packet", rule.name);
+                    return Verdict::Drop;
+                }
+                action => {
+                    if modify(action, packet) {
+                        info!("Rule {} modified the packet", rule.name);
+                        verdict = Verdict::Modified;
+                    }
+                }
+            }
+        }
+        verdict
+    }
+
+    /// Recompiles the rules when `config.json` changed. Keeps the current rules while the file
+    /// can't be read.
+    fn reload_if_changed(&mut self) {
+        if !self.watch.file_changed(Path::new("config.json")) {
+            return;
+        }
+        match utils::config::read() {
+            Ok(config) => {
+                self.rules = Rules::new(&config.rules).rules;
+                info!("Loaded {} packet rules", self.rules.len());
+            }
+            Err(e) => {
+                warn!("Keeping the current packet rules, config.json couldn't be read: {}", e);
+                self.watch.retry();
+            }
+        }
+    }
+}
+
+/// Applies a non-drop action. Returns whether anything changed.
+fn modify(action: &RuleAction, packet: &mut GamePacket) -> bool {
+    match (action, packet) {
+        (RuleAction::Replace { find, replace, .. }, GamePacket::Text(text)) => {
+            let replaced = text.replace(find.as_str(), replace);
+            let changed = replaced != *text;
+            *text = replaced;
+            changed
+        }
+        (RuleAction::Replace { find, replace, variant }, GamePacket::Tank { variants: Some(variants), .. }) => {
+            let indices = match variant {
+                Some(index) => *index..*index + 1,
+                None => 0..variants.len(),
+            };
+            let mut changed = false;
+            for index in indices {
+                if let Some(Variant::String(value)) = variants.get(index) {
+                    let replaced = value.replace(find.as_str(), replace);
+                    if replaced != *value {
+                        variants.set(index, Variant::String(replaced));
+                        changed = true;
+                    }
+                }
+            }
+            changed
+        }
+        (RuleAction::SetField { key, value, .. }, GamePacket::Text(text)) => {
+            let updated = text_parse::set_field(text, key, value);
+            let changed = updated != *text;
+            *text = updated;
+            changed
+        }
+        (RuleAction::SetField { key, value, variant }, GamePacket::Tank { variants: Some(variants), .. }) => {
+            let index = variant.unwrap_or(1);
+            match variants.get(index) {
+                Some(Variant::String(message)) => {
+                    let updated = text_parse::set_field(message, key, value);
+                    let changed = updated != *message;
+                    variants.set(index, Variant::String(updated));
+                    changed
+                }
+                _ => false,
+            }
+        }
+        (RuleAction::SetVariant { index, value }, GamePacket::Tank { variants: Some(variants), .. }) if *index < variants.len() => {
+            match variant_from_json(variants, *index, value) {
+                Some(variant) if variants.get(*index) != Some(&variant) => {
+                    variants.set(*index, variant);
+                    true
+                }
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Converts a config value into a variant, keeping the numeric type already at `index`.
+fn variant_from_json(variants: &VariantList, index: usize, value: &Value) -> Option<Variant> {
+    let variant = match value {
+        Value::String(value) => Variant::String(value.clone()),
+        Value::Number(number) => match variants.get(index) {
+            Some(Variant::Unsigned(_)) => Variant::Unsigned(number.as_u64()? as u32),
+            Some(Variant::Float(_)) => Variant::Float(number.as_f64()? as f32),
+            Some(Variant::Signed(_)) => Variant::Signed(number.as_i64()? as i32),
+            _ => match number.as_i64() {
+                Some(value) => Variant::Signed(value as i32),
+                None => Variant::Float(number.as_f64()? as f32),
+            },
+        },
+        _ => return None,
+    };
+    Some(variant)
+}
+
+/// Applies the configured rules to a decoded packet.
+pub fn apply(input: &FilterInput, packet: &mut GamePacket) -> Verdict {
+    let mut rules = global().rules.lock().unwrap();
+    rules.reload_if_changed();
+    rules.apply(input, packet)
+}
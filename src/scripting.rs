@@ -0,0 +1,472 @@
+//! Rhai scripts from the `scripts` directory, reloaded when they change.
+//!
+//! Scripts register handlers when they load:
+//!
+//! ```rhai
+//! on_variant("OnConsoleMessage", |vars| {
+//!     let message = vars[1];
+//!     message.replace("Growtopia", "Proxy");
+//!     vars[1] = message;
+//! });
+//! on_tank("TileChangeRequest", |tank| tank.int_x != 0);
+//! on_text(|text| {
+//!     if text.get("action") == "input" { console("Sent " + text.get("text")); }
+//! });
+//! ```
+//!
+//! `vars`, `tank` and `text` are live views of the packet; changes are forwarded in place of the
+//! original. A handler returning `false` drops the packet. Every view has a `direction` of
+//! `"client"` or `"server"`, the side that sent the packet. Rhai's string methods such as
+//! `replace` change the string in place and return nothing, so copy a variant out, change it
+//! and assign it back. Assigning a value that isn't a variant, or past the end, is an error.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use log::{info, warn};
+use rhai::{Array, Dynamic, Engine, FnPtr, AST, INT};
+use rusty_enet::Packet;
+use crate::global;
+use crate::metrics::Direction;
+use crate::packet_handler::{resend_packet, GamePacket};
+use crate::rules::Verdict;
+use crate::types::epacket_type::EPacketType;
+use crate::types::etank_packet_type::ETankPacketType;
+use crate::types::packet_flag::PacketFlag;
+use crate::types::tank_packet::TankPacket;
+use crate::utils::text_parse;
+use crate::utils::watch::{self, Watch};
+use crate::utils::variant::{Variant, VariantList};
+use crate::variant_handler;
+
+pub const SCRIPTS_DIR: &str = "scripts";
+/// Operations a single script call may run before it is aborted.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+#[derive(Clone)]
+enum Handler {
+    Variant(String, FnPtr),
+    Tank(ETankPacketType, FnPtr),
+    Text(FnPtr),
+}
+
+struct Script {
+    path: PathBuf,
+    ast: AST,
+    handlers: Vec<Handler>,
+}
+
+/// The loaded scripts and the engine they run in.
+pub struct Scripts {
+    engine: Engine,
+    /// Handlers registered by the script currently being loaded.
+    registering: Arc<Mutex<Vec<Handler>>>,
+    scripts: Vec<Script>,
+    watch: Watch,
+}
+
+impl Default for Scripts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scripts {
+    pub fn new() -> Self {
+        let registering = Arc::new(Mutex::new(Vec::new()));
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.on_print(|message| info!("[script] {}", message));
+        engine.on_debug(|message, source, position| info!("[script] {} {} {}", source.unwrap_or_default(), position, message));
+        register_handlers(&mut engine, &registering);
+        register_variants(&mut engine);
+        register_tank(&mut engine);
+        register_text(&mut engine);
+        register_injection(&mut engine);
+        Self { engine, registering, scripts: Vec::new(), watch: Watch::default() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.scripts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scripts.is_empty()
+    }
+
+    /// Compiles and runs one script, keeping the handlers it registers.
+    pub fn load_file(&mut self, path: &Path) -> Result<(), String> {
+        let ast = self.engine.compile_file(path.to_path_buf()).map_err(|e| e.to_string())?;
+        self.registering.lock().unwrap().clear();
+        let result = self.engine.run_ast(&ast);
+        let handlers = std::mem::take(&mut *self.registering.lock().unwrap());
+        result.map_err(|e| e.to_string())?;
+        self.scripts.push(Script { path: path.to_path_buf(), ast, handlers });
+        Ok(())
+    }
+
+    /// Replaces the loaded scripts with the `.rhai` files in `dir`, in file name order.
+    pub fn load_dir(&mut self, dir: &Path) {
+        self.scripts.clear();
+        for (path, _) in watch::files(dir, "rhai") {
+            match self.load_file(&path) {
+                Ok(()) => info!("Loaded script {}", path.display()),
+                Err(e) => warn!("Failed to load script {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    fn reload_if_changed(&mut self) {
+        if self.watch.dir_changed(Path::new(SCRIPTS_DIR), "rhai") {
+            self.load_dir(Path::new(SCRIPTS_DIR));
+        }
+    }
+
+    /// Runs the handlers registered for a decoded packet, writing their changes back into it.
+    pub fn run(&self, direction: Direction, packet_type: EPacketType, packet: &mut GamePacket) -> Verdict {
+        if self.scripts.is_empty() {
+            return Verdict::Pass;
+        }
+        let direction = match direction {
+            Direction::FromClient => "client",
+            Direction::FromServer => "server",
+        };
+        let mut dropped = false;
+        match packet {
+            GamePacket::Tank { tank_packet, variants, .. } => {
+                let tank = TankHandle { tank: Arc::new(Mutex::new(tank_packet.clone())), direction };
+                let vars = variants.as_ref().map(|variants| VariantsHandle {
+                    list: Arc::new(Mutex::new(variants.clone())),
+                    tank: tank.clone(),
+                    direction,
+                });
+                let function = variants.as_ref().and_then(|variants| variants.get(0)).map(Variant::as_string);
+                for (script, handler) in self.handlers() {
+                    match (handler, &vars, &function) {
+                        (Handler::Variant(name, f), Some(vars), Some(function)) if name == function => {
+                            dropped |= self.call(script, f, Dynamic::from(vars.clone()));
+                        }
+                        (Handler::Tank(r#type, f), _, _) if *r#type == tank_packet.r#type => {
+                            dropped |= self.call(script, f, Dynamic::from(tank.clone()));
+                        }
+                        _ => {}
+                    }
+                }
+                let updated = tank.tank.lock().unwrap().clone();
+                let mut modified = updated != *tank_packet;
+                *tank_packet = updated;
+                if let (Some(variants), Some(vars)) = (variants, vars) {
+                    let updated = vars.list.lock().unwrap().clone();
+                    modified |= updated != *variants;
+                    *variants = updated;
+                }
+                if dropped {
+                    return Verdict::Drop;
+                }
+                if modified {
+                    return Verdict::Modified;
+                }
+            }
+            GamePacket::Text(message) => {
+                let text = TextHandle { text: Arc::new(Mutex::new(message.clone())), packet_type: packet_type as u32 as INT, direction };
+                for (script, handler) in self.handlers() {
+                    if let Handler::Text(f) = handler {
+                        dropped |= self.call(script, f, Dynamic::from(text.clone()));
+                    }
+                }
+                let updated = text.text.lock().unwrap().clone();
+                if dropped {
+                    return Verdict::Drop;
+                }
+                if updated != *message {
+                    *message = updated;
+                    return Verdict::Modified;
+                }
+            }
+            GamePacket::Raw => {}
+        }
+        Verdict::Pass
+    }
+
+    fn handlers(&self) -> impl Iterator<Item = (&Script, &Handler)> {
+        self.scripts.iter().flat_map(|script| script.handlers.iter().map(move |handler| (script, handler)))
+    }
+
+    /// Calls a handler. Returns true when it asked for the packet to be dropped.
+    fn call(&self, script: &Script, f: &FnPtr, arg: Dynamic) -> bool {
+        match f.call::<Dynamic>(&self.engine, &script.ast, (arg,)) {
+            Ok(result) => result.as_bool() == Ok(false),
+            Err(e) => {
+                warn!("Script {} failed: {}", script.path.display(), e);
+                false
+            }
+        }
+    }
+}
+
+/// Runs the scripts in `SCRIPTS_DIR` on a decoded packet.
+pub fn run(direction: Direction, packet_type: EPacketType, packet: &mut GamePacket) -> Verdict {
+    let mut scripts = global().scripts.lock().unwrap();
+    scripts.reload_if_changed();
+    scripts.run(direction, packet_type, packet)
+}
+
+/// A function call's variants. `vars[0]` is the function name.
+#[derive(Clone)]
+pub struct VariantsHandle {
+    list: Arc<Mutex<VariantList>>,
+    tank: TankHandle,
+    direction: &'static str,
+}
+
+#[derive(Clone)]
+pub struct TankHandle {
+    tank: Arc<Mutex<TankPacket>>,
+    direction: &'static str,
+}
+
+#[derive(Clone)]
+pub struct TextHandle {
+    text: Arc<Mutex<String>>,
+    packet_type: INT,
+    direction: &'static str,
+}
+
+fn tank_type_from_name(name: &str) -> Option<ETankPacketType> {
+    (0..=u8::MAX).map(ETankPacketType::from).filter(|r#type| !matches!(r#type, ETankPacketType::Unknown(_))).find(|r#type| {
+        let type_name = format!("{:?}", r#type);
+        type_name == name || type_name.strip_prefix("NetGamePacket") == Some(name)
+    })
+}
+
+fn to_dynamic(variant: &Variant) -> Dynamic {
+    match variant {
+        Variant::Float(value) => Dynamic::from_float(*value as f64),
+        Variant::String(value) => value.clone().into(),
+        Variant::Bytes(value) => Dynamic::from_blob(value.clone()),
+        Variant::Vec2((x, y)) => Dynamic::from_array(vec![(*x as f64).into(), (*y as f64).into()]),
+        Variant::Vec3((x, y, z)) => Dynamic::from_array(vec![(*x as f64).into(), (*y as f64).into(), (*z as f64).into()]),
+        Variant::Vec4((x, y, z, w)) => Dynamic::from_array(vec![(*x as f64).into(), (*y as f64).into(), (*z as f64).into(), (*w as f64).into()]),
+        Variant::Unsigned(value) => (*value as INT).into(),
+        Variant::Signed(value) => (*value as INT).into(),
+        Variant::Unknown { .. } => Dynamic::UNIT,
+    }
+}
+
+/// Converts a script value into a variant, keeping the numeric type of `current`.
+fn from_dynamic(value: Dynamic, current: Option<&Variant>) -> Option<Variant> {
+    let number = |value: &Dynamic| value.as_float().ok().or_else(|| value.as_int().ok().map(|value| value as f64));
+    let variant = if value.is_string() {
+        Variant::String(value.into_string().ok()?)
+    } else if value.is_int() {
+        let value = value.as_int().ok()?;
+        match current {
+            Some(Variant::Unsigned(_)) => Variant::Unsigned(value as u32),
+            Some(Variant::Float(_)) => Variant::Float(value as f32),
+            _ => Variant::Signed(value as i32),
+        }
+    } else if value.is_float() {
+        Variant::Float(value.as_float().ok()? as f32)
+    } else if value.is_blob() {
+        Variant::Bytes(value.into_blob().ok()?)
+    } else if value.is_array() {
+        let values: Vec<f32> = value.into_array().ok()?.iter().map(|value| number(value).map(|value| value as f32)).collect::<Option<_>>()?;
+        match values[..] {
+            [x, y] => Variant::Vec2((x, y)),
+            [x, y, z] => Variant::Vec3((x, y, z)),
+            [x, y, z, w] => Variant::Vec4((x, y, z, w)),
+            _ => return None,
+        }
+    } else {
+        return None;
+    };
+    Some(variant)
+}
+
+fn variants_from_array(values: Array) -> VariantList {
+    let mut variants = VariantList::new();
+    for value in values {
+        if let Some(variant) = from_dynamic(value, None) {
+            variants.push(variant);
+        }
+    }
+    variants
+}
+
+fn register_handlers(engine: &mut Engine, registering: &Arc<Mutex<Vec<Handler>>>) {
+    let handlers = registering.clone();
+    engine.register_fn("on_variant", move |name: &str, f: FnPtr| {
+        handlers.lock().unwrap().push(Handler::Variant(name.to_string(), f));
+    });
+    let handlers = registering.clone();
+    engine.register_fn("on_tank", move |name: &str, f: FnPtr| -> Result<(), Box<rhai::EvalAltResult>> {
+        let r#type = tank_type_from_name(name).ok_or_else(|| format!("unknown tank packet type {:?}", name))?;
+        handlers.lock().unwrap().push(Handler::Tank(r#type, f));
+        Ok(())
+    });
+    let handlers = registering.clone();
+    engine.register_fn("on_text", move |f: FnPtr| {
+        handlers.lock().unwrap().push(Handler::Text(f));
+    });
+}
+
+fn register_variants(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<VariantsHandle>("VariantList")
+        .register_fn("new_variants", |values: Array| {
+            let tank = TankPacket { r#type: ETankPacketType::NetGamePacketCallFunction, net_id: u32::MAX, flags: PacketFlag::EXTENDED, ..Default::default() };
+            VariantsHandle {
+                list: Arc::new(Mutex::new(variants_from_array(values))),
+                tank: TankHandle { tank: Arc::new(Mutex::new(tank)), direction: "server" },
+                direction: "server",
+            }
+        })
+        .register_fn("len", |vars: &mut VariantsHandle| vars.list.lock().unwrap().len() as INT)
+        .register_get("direction", |vars: &mut VariantsHandle| vars.direction.to_string())
+        .register_get("tank", |vars: &mut VariantsHandle| vars.tank.clone())
+        .register_indexer_get(|vars: &mut VariantsHandle, index: INT| {
+            vars.list.lock().unwrap().get(index as usize).map(to_dynamic).unwrap_or(Dynamic::UNIT)
+        })
+        .register_indexer_set(|vars: &mut VariantsHandle, index: INT, value: Dynamic| -> Result<(), Box<rhai::EvalAltResult>> {
+            let mut list = vars.list.lock().unwrap();
+            let len = list.len();
+            let Some(current) = usize::try_from(index).ok().and_then(|index| list.get(index)) else {
+                return Err(format!("variant index {} out of range for {} variants", index, len).into());
+            };
+            let type_name = value.type_name();
+            let variant = from_dynamic(value, Some(current)).ok_or_else(|| format!("a {} can't be a variant", type_name))?;
+            list.set(index as usize, variant);
+            Ok(())
+        })
+        .register_fn("push", |vars: &mut VariantsHandle, value: Dynamic| -> Result<(), Box<rhai::EvalAltResult>> {
+            let type_name = value.type_name();
+            let variant = from_dynamic(value, None).ok_or_else(|| format!("a {} can't be a variant", type_name))?;
+            if !vars.list.lock().unwrap().push(variant) {
+                return Err("can't add variants after one of an unknown type".into());
+            }
+            Ok(())
+        })
+        .register_fn("to_string", |vars: &mut VariantsHandle| {
+            let list = vars.list.lock().unwrap();
+            list.iter().map(|variant| format!("{:?}", variant)).collect::<Vec<String>>().join(", ")
+        });
+}
+
+macro_rules! tank_field {
+    ($engine:expr, $name:literal, $field:ident, int) => {
+        $engine.register_get_set(
+            $name,
+            |tank: &mut TankHandle| tank.tank.lock().unwrap().$field as INT,
+            |tank: &mut TankHandle, value: INT| tank.tank.lock().unwrap().$field = value as _,
+        );
+    };
+    ($engine:expr, $name:literal, $field:ident, float) => {
+        $engine.register_get_set(
+            $name,
+            |tank: &mut TankHandle| tank.tank.lock().unwrap().$field as f64,
+            |tank: &mut TankHandle, value: f64| tank.tank.lock().unwrap().$field = value as f32,
+        );
+    };
+}
+
+fn register_tank(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<TankHandle>("TankPacket")
+        .register_fn("new_tank", |name: &str| -> Result<TankHandle, Box<rhai::EvalAltResult>> {
+            let r#type = tank_type_from_name(name).ok_or_else(|| format!("unknown tank packet type {:?}", name))?;
+            Ok(TankHandle { tank: Arc::new(Mutex::new(TankPacket { r#type, ..Default::default() })), direction: "server" })
+        })
+        .register_get("direction", |tank: &mut TankHandle| tank.direction.to_string())
+        .register_get_set(
+            "type",
+            |tank: &mut TankHandle| format!("{:?}", tank.tank.lock().unwrap().r#type),
+            |tank: &mut TankHandle, name: String| {
+                if let Some(r#type) = tank_type_from_name(&name) {
+                    tank.tank.lock().unwrap().r#type = r#type;
+                }
+            },
+        )
+        .register_get_set(
+            "flags",
+            |tank: &mut TankHandle| tank.tank.lock().unwrap().flags.to_u32() as INT,
+            |tank: &mut TankHandle, value: INT| tank.tank.lock().unwrap().flags = PacketFlag::from_u32(value as u32),
+        )
+        .register_fn("has_flag", |tank: &mut TankHandle, name: &str| {
+            PacketFlag::from_name(&name.to_uppercase()).is_some_and(|flag| tank.tank.lock().unwrap().flags.contains(flag))
+        })
+        .register_fn("set_flag", |tank: &mut TankHandle, name: &str, on: bool| {
+            if let Some(flag) = PacketFlag::from_name(&name.to_uppercase()) {
+                let mut tank = tank.tank.lock().unwrap();
+                tank.flags = tank.flags.with(flag, on);
+            }
+        })
+        .register_fn("to_string", |tank: &mut TankHandle| format!("{:?}", tank.tank.lock().unwrap()));
+    tank_field!(engine, "unk1", unk1, int);
+    tank_field!(engine, "unk2", unk2, int);
+    tank_field!(engine, "unk3", unk3, int);
+    tank_field!(engine, "net_id", net_id, int);
+    tank_field!(engine, "sec_id", sec_id, int);
+    tank_field!(engine, "value", value, int);
+    tank_field!(engine, "int_x", int_x, int);
+    tank_field!(engine, "int_y", int_y, int);
+    tank_field!(engine, "unk6", unk6, float);
+    tank_field!(engine, "vector_x", vector_x, float);
+    tank_field!(engine, "vector_y", vector_y, float);
+    tank_field!(engine, "vector_x2", vector_x2, float);
+    tank_field!(engine, "vector_y2", vector_y2, float);
+    tank_field!(engine, "unk12", unk12, float);
+}
+
+fn register_text(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<TextHandle>("TextPacket")
+        .register_get("direction", |text: &mut TextHandle| text.direction.to_string())
+        .register_get("type", |text: &mut TextHandle| text.packet_type)
+        .register_get_set(
+            "text",
+            |text: &mut TextHandle| text.text.lock().unwrap().clone(),
+            |text: &mut TextHandle, value: String| *text.text.lock().unwrap() = value,
+        )
+        .register_fn("get", |text: &mut TextHandle, key: &str| {
+            text_parse::get_field(&text.text.lock().unwrap(), key).map_or(Dynamic::UNIT, |value| value.to_string().into())
+        })
+        .register_fn("set", |text: &mut TextHandle, key: &str, value: &str| {
+            let mut text = text.text.lock().unwrap();
+            *text = text_parse::set_field(&text, key, value);
+        })
+        .register_fn("to_string", |text: &mut TextHandle| text.text.lock().unwrap().clone());
+}
+
+/// Sends a packet the script built. `to_client` picks the game client rather than the server.
+fn inject(data: &[u8], packet_type: EPacketType, to_client: bool) {
+    resend_packet(&Packet::reliable(data), &packet_type, 0, to_client);
+}
+
+fn text_message(packet_type: INT, text: &str) -> Vec<u8> {
+    let mut data = (packet_type as u32).to_le_bytes().to_vec();
+    data.extend_from_slice(text.as_bytes());
+    data
+}
+
+fn register_injection(engine: &mut Engine) {
+    for (name, to_client) in [("send_to_client", true), ("send_to_server", false)] {
+        engine.register_fn(name, move |tank: TankHandle| {
+            let data = tank.tank.lock().unwrap().to_message(&[], &[]);
+            inject(&data, EPacketType::NetMessageGamePacket, to_client);
+        });
+        engine.register_fn(name, move |vars: VariantsHandle| {
+            let serialized = vars.list.lock().unwrap().serialize();
+            let data = vars.tank.tank.lock().unwrap().to_message(&serialized, &[]);
+            inject(&data, EPacketType::NetMessageGamePacket, to_client);
+        });
+        engine.register_fn(name, move |packet_type: INT, text: &str| {
+            inject(&text_message(packet_type, text), EPacketType::from(packet_type as u32), to_client);
+        });
+    }
+    engine.register_fn("call_client", |values: Array| {
+        let data = variant_handler::call_function(&variants_from_array(values), u32::MAX);
+        inject(&data, EPacketType::NetMessageGamePacket, true);
+    });
+    engine.register_fn("console", |message: &str| {
+        inject(&variant_handler::console_message(message), EPacketType::NetMessageGamePacket, true);
+    });
+}
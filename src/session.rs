@@ -0,0 +1,532 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use log::{info, warn};
+use rusty_enet::Packet;
+use crate::enet::Command;
+use crate::types::epacket_type::EPacketType;
+use crate::utils::text_parse;
+use crate::{global, metrics, packet_handler, utils, variant_handler};
+
+/// How long an upstream connection attempt may take before it is retried.
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Connection attempts made before giving up and kicking the game client.
+const MAX_CONNECT_ATTEMPTS: u32 = 3;
+/// Packets from the game client kept while the upstream is not connected.
+const MAX_QUEUED_PACKETS: usize = 256;
+
+/// Where `OnSendToServer` sends the client, and the credentials it logs in with there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redirect {
+    pub address: SocketAddr,
+    pub token: i32,
+    pub user: i32,
+    pub door_id: String,
+    pub uuid: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionState {
+    Idle,
+    Connecting { address: SocketAddr, since: Instant, attempts: u32 },
+    Connected { address: SocketAddr },
+    /// `OnSendToServer` was forwarded, waiting for the client to leave the current sub-server.
+    Redirecting { from: SocketAddr, redirect: Redirect },
+    Reconnecting { redirect: Redirect, since: Instant, attempts: u32 },
+    /// The server dropped us unexpectedly and the reconnect policy is on. The game client stays
+    /// connected while we reconnect and replay its last login once the server says hello.
+    Resuming { address: SocketAddr, attempts: u32, retry_at: Instant, connected: bool },
+}
+
+impl fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionState::Idle => write!(f, "idle"),
+            ConnectionState::Connecting { address, attempts, .. } => write!(f, "connecting to {} (attempt {})", address, attempts),
+            ConnectionState::Connected { address } => write!(f, "connected to {}", address),
+            ConnectionState::Redirecting { redirect, .. } => write!(f, "redirecting to {}", redirect.address),
+            ConnectionState::Reconnecting { redirect, attempts, .. } => write!(f, "reconnecting to {} (attempt {})", redirect.address, attempts),
+            ConnectionState::Resuming { address, attempts, .. } => write!(f, "resuming {} (attempt {})", address, attempts),
+        }
+    }
+}
+
+/// Upstream connection state for the game client connected to the proxy.
+pub struct Session {
+    /// Increases every time `server_data.php` starts a new session.
+    pub id: u64,
+    pub state: ConnectionState,
+    /// Packets from the game client waiting for the upstream to connect, with their channel.
+    queued: VecDeque<(u8, Packet)>,
+    /// The last login text the game client sent, replayed when resuming.
+    login: Option<(u8, Packet)>,
+    /// No game client is attached: the proxy logs in, answers pings and follows
+    /// `OnSendToServer` itself.
+    headless: bool,
+    /// A game client is attaching to the headless session; its login needs the session's
+    /// credentials.
+    attaching: bool,
+    /// A game client connected while the headless session was still connecting; it attaches
+    /// once the upstream is up.
+    attach_pending: bool,
+    /// The last `OnSendToServer`, with the credentials a login to the current sub-server needs.
+    last_redirect: Option<Redirect>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            state: ConnectionState::Idle,
+            queued: VecDeque::new(),
+            login: None,
+            headless: false,
+            attaching: false,
+            attach_pending: false,
+            last_redirect: None,
+        }
+    }
+}
+
+impl Session {
+    pub fn is_headless(&self) -> bool {
+        self.headless && self.state != ConnectionState::Idle
+    }
+
+    /// Hands the headless session on `address` to the game client, on a connection of its own.
+    fn attach(&mut self, address: SocketAddr) {
+        info!("Game client attached, reconnecting to {} for it", address);
+        self.headless = false;
+        self.attaching = true;
+        self.attach_pending = false;
+        self.queued.clear();
+        send_upstream(Command::Connect(address));
+        self.state = ConnectionState::Connecting { address, since: Instant::now(), attempts: 1 };
+    }
+
+    /// Address the upstream should be connecting to, if an attempt is in progress.
+    fn target(&self) -> Option<SocketAddr> {
+        match &self.state {
+            ConnectionState::Connecting { address, .. } => Some(*address),
+            ConnectionState::Reconnecting { redirect, .. } => Some(redirect.address),
+            ConnectionState::Resuming { address, .. } => Some(*address),
+            _ => None,
+        }
+    }
+}
+
+fn send_upstream(command: Command) {
+    if let Some(client) = global().client_enet.get() {
+        client.send(command);
+    }
+}
+
+fn notify_client(message: &str) {
+    let data = variant_handler::console_message(&format!("`4[Proxy]`` {}", message));
+    packet_handler::resend_packet(&Packet::reliable(&data), &EPacketType::NetMessageGamePacket, 0, true);
+}
+
+/// Starts a fresh upstream connection, as requested by `server_data.php`.
+pub fn connect(address: SocketAddr) {
+    start(address, None);
+}
+
+/// Starts a session without a game client that logs in with the login text `login`. Returns
+/// false, leaving the session alone, unless it is idle.
+pub fn start_headless(address: SocketAddr, login: &str) -> bool {
+    start(address, Some(login))
+}
+
+/// Starts a new session, headless when given its login text. The state, the headless flag and
+/// the login change together, so nothing sees a headless session without its login. A headless
+/// session only replaces an idle one.
+fn start(address: SocketAddr, headless_login: Option<&str>) -> bool {
+    let mut session = global().session.lock().unwrap();
+    if headless_login.is_some() && session.state != ConnectionState::Idle {
+        return false;
+    }
+    session.id += 1;
+    session.state = ConnectionState::Connecting { address, since: Instant::now(), attempts: 1 };
+    session.queued.clear();
+    session.headless = headless_login.is_some();
+    session.attaching = false;
+    session.attach_pending = false;
+    session.last_redirect = None;
+    if let Some(login) = headless_login {
+        session.login = Some((0, text_packet(login)));
+        info!("Session is headless");
+    }
+    metrics::with(|metrics| metrics.reset_session());
+    drop(session);
+    // Never lock the pings while holding the session: answering a ping locks the session to send.
+    global().pings.lock().unwrap().reset();
+    info!("Session connecting to {}", address);
+    send_upstream(Command::Connect(address));
+    true
+}
+
+pub fn id() -> u64 {
+    global().session.lock().unwrap().id
+}
+
+pub fn is_idle() -> bool {
+    global().session.lock().unwrap().state == ConnectionState::Idle
+}
+
+pub fn is_headless() -> bool {
+    global().session.lock().unwrap().is_headless()
+}
+
+/// Whether the proxy is reconnecting to the server on the game client's behalf.
+pub fn is_resuming() -> bool {
+    matches!(global().session.lock().unwrap().state, ConnectionState::Resuming { .. })
+}
+
+/// Records where `OnSendToServer` is sending the client.
+pub fn redirect(redirect: Redirect) {
+    let mut session = global().session.lock().unwrap();
+    let from = match &session.state {
+        ConnectionState::Connected { address } => *address,
+        ConnectionState::Redirecting { from, .. } => *from,
+        state => {
+            warn!("Session redirect to {} while {:?}", redirect.address, state);
+            redirect.address
+        }
+    };
+    info!("Session redirecting from {} to {} (door {:?})", from, redirect.address, redirect.door_id);
+    session.last_redirect = Some(redirect.clone());
+    if session.is_headless() {
+        // Nobody will reconnect for us: log in to the sub-server with the new credentials.
+        if let Some((channel_id, login)) = session.login.take() {
+            session.login = Some((channel_id, text_packet(&apply_redirect(&packet_text(&login), &redirect))));
+        }
+        info!("Session reconnecting to {}", redirect.address);
+        send_upstream(Command::Connect(redirect.address));
+        session.state = ConnectionState::Reconnecting { redirect, since: Instant::now(), attempts: 1 };
+        return;
+    }
+    session.state = ConnectionState::Redirecting { from, redirect };
+}
+
+/// Moves a redirect along once the client leaves the current sub-server.
+///
+/// Returns false when no redirect is pending, leaving the caller to handle the disconnect.
+pub fn begin_reconnect() -> bool {
+    let mut session = global().session.lock().unwrap();
+    let redirect = match &session.state {
+        ConnectionState::Redirecting { redirect, .. } => redirect.clone(),
+        ConnectionState::Reconnecting { .. } => return true,
+        _ => return false,
+    };
+    info!("Session reconnecting to {}", redirect.address);
+    send_upstream(Command::Connect(redirect.address));
+    session.state = ConnectionState::Reconnecting { redirect, since: Instant::now(), attempts: 1 };
+    true
+}
+
+/// Called by the upstream host when its peer connects. Flushes queued client packets.
+pub fn upstream_connected() {
+    let mut session = global().session.lock().unwrap();
+    if let ConnectionState::Resuming { address, connected, retry_at, .. } = &mut session.state {
+        // Queued packets wait for the login replay in `resume_login`.
+        info!("Session reconnected to {}, waiting for hello", address);
+        *connected = true;
+        *retry_at = Instant::now() + CONNECT_TIMEOUT;
+        return;
+    }
+    let address = match &session.state {
+        ConnectionState::Connecting { address, .. } => *address,
+        ConnectionState::Reconnecting { redirect, .. } => redirect.address,
+        state => {
+            warn!("Upstream connected while {:?}", state);
+            return;
+        }
+    };
+    info!("Session connected to {}", address);
+    session.state = ConnectionState::Connected { address };
+    if std::mem::take(&mut session.attach_pending) {
+        // Nobody has logged in yet, so the client can have this connection.
+        info!("Game client attached on {}", address);
+        session.headless = false;
+        session.attaching = true;
+    }
+    for (channel_id, packet) in session.queued.drain(..) {
+        send_upstream(Command::Send { channel_id, packet });
+    }
+}
+
+/// Called by the upstream host when its peer disconnects.
+pub fn upstream_disconnected() {
+    let mut session = global().session.lock().unwrap();
+    match session.state.clone() {
+        ConnectionState::Redirecting { redirect, .. } => {
+            // The old sub-server may hang up before the client does; connect right away.
+            info!("Session reconnecting to {}", redirect.address);
+            send_upstream(Command::Connect(redirect.address));
+            session.state = ConnectionState::Reconnecting { redirect, since: Instant::now(), attempts: 1 };
+        }
+        ConnectionState::Connecting { .. } | ConnectionState::Reconnecting { .. } => {
+            drop(session);
+            retry();
+        }
+        ConnectionState::Connected { address } if session.login.is_some() && utils::config::get_reconnect_policy().enabled => {
+            warn!("Session lost connection to {}, resuming", address);
+            let delay = utils::config::get_reconnect_policy().delay(1);
+            session.state = ConnectionState::Resuming { address, attempts: 0, retry_at: Instant::now() + delay, connected: false };
+            drop(session);
+            notify_client(&format!("Lost connection to the server, reconnecting in {:.1}s...", delay.as_secs_f32()));
+        }
+        ConnectionState::Resuming { address, attempts, .. } => {
+            let delay = utils::config::get_reconnect_policy().delay(attempts + 1);
+            session.state = ConnectionState::Resuming { address, attempts, retry_at: Instant::now() + delay, connected: false };
+        }
+        ConnectionState::Connected { address } => {
+            warn!("Session lost connection to {}", address);
+            session.state = ConnectionState::Idle;
+            session.queued.clear();
+            drop(session);
+            packet_handler::disconnect(false);
+        }
+        ConnectionState::Idle => {}
+    }
+}
+
+/// Called when the game client leaves the proxy. Drops the upstream unless the client is
+/// on its way to another sub-server or the session carries on headless.
+pub fn client_disconnected() {
+    if std::mem::take(&mut global().session.lock().unwrap().attach_pending) {
+        info!("Game client left before attaching, the session carries on headless");
+        return;
+    }
+    if begin_reconnect() || detach() {
+        return;
+    }
+    reset();
+    packet_handler::disconnect(true);
+}
+
+/// Lets the session carry on without the game client when `headless.keep_alive` is on.
+/// Returns false when the session should end instead.
+pub fn detach() -> bool {
+    if !utils::config::get_headless_config().keep_alive {
+        return false;
+    }
+    let mut session = global().session.lock().unwrap();
+    if session.is_headless() {
+        return true;
+    }
+    if !matches!(session.state, ConnectionState::Connected { .. }) || session.login.is_none() {
+        return false;
+    }
+    session.headless = true;
+    session.attaching = false;
+    info!("Game client detached, the session carries on headless");
+    true
+}
+
+/// Called by the downstream host when a game client connects. A client joining a headless
+/// session gets its own connection to the current sub-server, which it logs in to with the
+/// session's credentials. While the session is still connecting, the client takes over that
+/// connection once it is up instead.
+pub fn client_connected() {
+    let mut session = global().session.lock().unwrap();
+    if !session.is_headless() {
+        return;
+    }
+    let ConnectionState::Connected { address } = session.state else {
+        info!("Game client attaching once the session is connected, now {:?}", session.state);
+        session.attach_pending = true;
+        return;
+    };
+    session.attach(address);
+}
+
+/// Gives the login of a game client attaching to a headless session the session's credentials.
+/// Returns None when no client is attaching.
+pub fn attach_login(login: &str) -> Option<String> {
+    let mut session = global().session.lock().unwrap();
+    if !std::mem::take(&mut session.attaching) {
+        return None;
+    }
+    Some(match &session.last_redirect {
+        Some(redirect) => apply_redirect(login, redirect),
+        None => login.to_string(),
+    })
+}
+
+pub fn reset() {
+    let mut session = global().session.lock().unwrap();
+    session.state = ConnectionState::Idle;
+    session.queued.clear();
+    session.login = None;
+    session.headless = false;
+    session.attaching = false;
+    session.attach_pending = false;
+}
+
+/// The login text of the session, as the game client sent it.
+pub fn login_text() -> Option<String> {
+    global().session.lock().unwrap().login.as_ref().map(|(_, login)| packet_text(login))
+}
+
+/// Keeps the game client's login text so a resumed connection can log in again.
+pub fn remember_login(channel_id: u8, packet: Packet) {
+    global().session.lock().unwrap().login = Some((channel_id, packet));
+}
+
+/// Answers the server's hello with the remembered login when resuming.
+///
+/// Returns true when the hello was consumed and must not reach the game client.
+pub fn resume_login() -> bool {
+    let mut session = global().session.lock().unwrap();
+    let address = match &session.state {
+        ConnectionState::Resuming { address, connected: true, .. } => *address,
+        _ => return false,
+    };
+    let Some((channel_id, packet)) = session.login.clone() else {
+        return false;
+    };
+    info!("Session resumed on {}", address);
+    session.state = ConnectionState::Connected { address };
+    send_upstream(Command::Send { channel_id, packet });
+    for (channel_id, packet) in session.queued.drain(..) {
+        send_upstream(Command::Send { channel_id, packet });
+    }
+    if session.attach_pending {
+        // The session logged in again, so the waiting client needs a connection of its own.
+        session.attach(address);
+        return true;
+    }
+    drop(session);
+    notify_client("Reconnected to the server.");
+    true
+}
+
+/// Answers the server's hello with the session's login when no game client is attached.
+///
+/// Returns true when the hello was consumed.
+pub fn headless_login() -> bool {
+    let session = global().session.lock().unwrap();
+    if !session.is_headless() || !matches!(session.state, ConnectionState::Connected { .. }) {
+        return false;
+    }
+    let Some((channel_id, packet)) = session.login.clone() else {
+        return false;
+    };
+    info!("Session logging in without a game client");
+    send_upstream(Command::Send { channel_id, packet });
+    true
+}
+
+/// Sets the credentials of `redirect` in the login text `login`, as a game client does when it
+/// follows `OnSendToServer`.
+pub fn apply_redirect(login: &str, redirect: &Redirect) -> String {
+    let fields = [
+        ("user", redirect.user.to_string()),
+        ("token", redirect.token.to_string()),
+        ("UUIDToken", redirect.uuid.clone()),
+        ("doorID", redirect.door_id.clone()),
+    ];
+    fields.iter().fold(login.to_string(), |login, (key, value)| text_parse::set_field(&login, key, value))
+}
+
+/// The text of a `NetMessageGenericText` packet, without the NUL some clients end it with.
+fn packet_text(packet: &Packet) -> String {
+    let text = String::from_utf8_lossy(packet.data().get(4..).unwrap_or_default()).into_owned();
+    text.trim_end_matches('\0').to_string()
+}
+
+fn text_packet(text: &str) -> Packet {
+    let mut data = (EPacketType::NetMessageGenericText as u32).to_le_bytes().to_vec();
+    data.extend_from_slice(text.as_bytes());
+    Packet::reliable(&data)
+}
+
+/// Sends a packet from the game client upstream, queueing it while the upstream connects.
+///
+/// Returns the packet back when the session isn't connecting, so the caller sends it directly.
+pub fn queue_upstream(channel_id: u8, packet: Packet) -> Option<(u8, Packet)> {
+    let mut session = global().session.lock().unwrap();
+    if session.target().is_none() {
+        return Some((channel_id, packet));
+    }
+    if session.queued.len() >= MAX_QUEUED_PACKETS {
+        warn!("Session queue full, dropping oldest packet");
+        session.queued.pop_front();
+    }
+    session.queued.push_back((channel_id, packet));
+    None
+}
+
+/// Retries or abandons a connection attempt that timed out. Called periodically by the upstream host.
+pub fn check_timeout() {
+    let timed_out = {
+        let session = global().session.lock().unwrap();
+        match &session.state {
+            ConnectionState::Resuming { retry_at, .. } => {
+                if Instant::now() >= *retry_at {
+                    drop(session);
+                    resume_attempt();
+                }
+                return;
+            }
+            ConnectionState::Connecting { since, .. } | ConnectionState::Reconnecting { since, .. } => since.elapsed() >= CONNECT_TIMEOUT,
+            _ => false,
+        }
+    };
+    if timed_out {
+        retry();
+    }
+}
+
+fn retry() {
+    let mut session = global().session.lock().unwrap();
+    let Some(address) = session.target() else {
+        return;
+    };
+    match &mut session.state {
+        ConnectionState::Connecting { since, attempts, .. } | ConnectionState::Reconnecting { since, attempts, .. } if *attempts < MAX_CONNECT_ATTEMPTS => {
+            *attempts += 1;
+            *since = Instant::now();
+            warn!("Session retrying {} (attempt {}/{})", address, attempts, MAX_CONNECT_ATTEMPTS);
+            send_upstream(Command::Connect(address));
+        }
+        _ => {
+            warn!("Session gave up connecting to {}", address);
+            session.state = ConnectionState::Idle;
+            session.queued.clear();
+            drop(session);
+            send_upstream(Command::Disconnect);
+            packet_handler::disconnect(false);
+        }
+    }
+}
+
+fn resume_attempt() {
+    let policy = utils::config::get_reconnect_policy();
+    let mut session = global().session.lock().unwrap();
+    let ConnectionState::Resuming { address, attempts, retry_at, connected } = &mut session.state else {
+        return;
+    };
+    let address = *address;
+    if *attempts >= policy.max_attempts {
+        warn!("Session gave up resuming {}", address);
+        session.state = ConnectionState::Idle;
+        session.queued.clear();
+        drop(session);
+        send_upstream(Command::Disconnect);
+        notify_client("Could not reconnect to the server.");
+        packet_handler::disconnect(false);
+        return;
+    }
+    *attempts += 1;
+    // Give the attempt its connect timeout, then back off before the next one.
+    *retry_at = Instant::now() + CONNECT_TIMEOUT + policy.delay(*attempts + 1);
+    *connected = false;
+    let attempts = *attempts;
+    drop(session);
+    info!("Session resuming {} (attempt {}/{})", address, attempts, policy.max_attempts);
+    send_upstream(Command::Connect(address));
+    notify_client(&format!("Reconnecting (attempt {}/{})...", attempts, policy.max_attempts));
+}
@@ -0,0 +1,309 @@
+//! Snapshots of the tracked world: exported as JSON, drawn as PNG and compared with each other.
+//!
+//! Snapshots are saved to `snapshots.dir` in `config.json` as `<WORLD>-<unix time>.json` with a
+//! `.png` next to it, from the dashboard API or the `/snapshot` chat command.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use log::info;
+use rusty_enet::Packet;
+use serde::{Deserialize, Serialize};
+use crate::packet_handler::resend_packet;
+use crate::types::config::SnapshotConfig;
+use crate::types::epacket_type::EPacketType;
+use crate::types::world_data::{DroppedItem, Tile, TileExtra};
+use crate::utils::text_parse;
+use crate::world::World;
+use crate::{utils, variant_handler, world};
+
+pub const COMMAND: &str = "/snapshot";
+
+/// A lock and the tiles it covers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lock {
+    pub x: u32,
+    pub y: u32,
+    pub item_id: u16,
+    pub owner_uid: Option<u32>,
+    pub access_uids: Vec<u32>,
+    /// Number of tiles in the lock's area.
+    pub tiles: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub name: String,
+    /// Unix time in seconds.
+    pub taken_at: u64,
+    pub width: u32,
+    pub height: u32,
+    /// Row by row from the top left.
+    pub tiles: Vec<Tile>,
+    pub dropped: Vec<DroppedItem>,
+    pub locks: Vec<Lock>,
+}
+
+impl Snapshot {
+    /// Copies the world, or None when no map data has been seen.
+    pub fn take(world: &World) -> Option<Self> {
+        if world.tiles.is_empty() {
+            return None;
+        }
+        let width = world.width.max(1);
+        let mut area: HashMap<u16, usize> = HashMap::new();
+        for lock_index in world.tiles.iter().filter_map(|tile| tile.lock_index) {
+            *area.entry(lock_index).or_default() += 1;
+        }
+        let locks = world
+            .tiles
+            .iter()
+            .enumerate()
+            .filter_map(|(index, tile)| {
+                let tiles = area.get(&(index as u16)).copied().unwrap_or(0);
+                let (owner_uid, access_uids) = match &tile.extra {
+                    Some(TileExtra::Lock { owner_uid, access_uids, .. }) => (Some(*owner_uid), access_uids.clone()),
+                    _ if tiles > 0 => (None, Vec::new()),
+                    _ => return None,
+                };
+                Some(Lock {
+                    x: index as u32 % width,
+                    y: index as u32 / width,
+                    item_id: tile.foreground,
+                    owner_uid,
+                    access_uids,
+                    tiles,
+                })
+            })
+            .collect();
+        Some(Self {
+            name: world.name.clone().unwrap_or_default(),
+            taken_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            width: world.width,
+            height: world.height,
+            tiles: world.tiles.clone(),
+            dropped: world.dropped.clone(),
+            locks,
+        })
+    }
+
+    /// The file name the snapshot is saved under, without extension. Characters of the world name
+    /// that [`Snapshot::load`] doesn't take in ids become `_`.
+    pub fn id(&self) -> String {
+        let name: String = self.name.chars().map(|c| if is_id_char(c) { c } else { '_' }).collect();
+        format!("{}-{}", name, self.taken_at)
+    }
+
+    /// Draws every tile as a `tile_pixels` square: the foreground's colour, or the background's
+    /// at half brightness.
+    pub fn render_png(&self, config: &SnapshotConfig) -> Result<Vec<u8>, png::EncodingError> {
+        let scale = config.tile_pixels.max(1);
+        let width = self.width.checked_mul(scale).ok_or(png::EncodingError::LimitsExceeded)?;
+        let height = self.height.checked_mul(scale).ok_or(png::EncodingError::LimitsExceeded)?;
+        let empty = parse_color(&config.empty).unwrap_or([0, 0, 0]);
+        let mut pixels = vec![0u8; width as usize * height as usize * 3];
+        for (index, tile) in self.tiles.iter().enumerate().take(self.width as usize * self.height as usize) {
+            let color = match (tile.foreground, tile.background) {
+                (0, 0) => empty,
+                (0, background) => item_color(config, background).map(|channel| channel / 2),
+                (foreground, _) => item_color(config, foreground),
+            };
+            let (tile_x, tile_y) = (index as u32 % self.width, index as u32 / self.width);
+            for y in tile_y * scale..(tile_y + 1) * scale {
+                let row = (y as usize * width as usize + (tile_x * scale) as usize) * 3;
+                for pixel in pixels[row..row + scale as usize * 3].chunks_exact_mut(3) {
+                    pixel.copy_from_slice(&color);
+                }
+            }
+        }
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&pixels)?;
+        Ok(out)
+    }
+
+    /// Writes `<id>.json` and `<id>.png` to `config.dir`. Returns the path of the JSON file.
+    pub fn save(&self, config: &SnapshotConfig) -> io::Result<PathBuf> {
+        let dir = Path::new(&config.dir);
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.json", self.id()));
+        std::fs::write(&path, serde_json::to_vec(self)?)?;
+        let png = self.render_png(config).map_err(io::Error::other)?;
+        std::fs::write(path.with_extension("png"), png)?;
+        Ok(path)
+    }
+
+    /// Loads the snapshot saved as `id`. Ids may only contain letters, digits, `-` and `_`.
+    pub fn load(config: &SnapshotConfig, id: &str) -> io::Result<Self> {
+        if id.is_empty() || !id.chars().all(is_id_char) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid snapshot id {:?}", id)));
+        }
+        let data = std::fs::read(Path::new(&config.dir).join(format!("{}.json", id)))?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+}
+
+fn is_id_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_'
+}
+
+/// Ids of the saved snapshots, oldest first.
+pub fn list(config: &SnapshotConfig) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(&config.dir) else {
+        return Vec::new();
+    };
+    let mut snapshots: Vec<(u64, String)> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .filter_map(|path| {
+            let id = path.file_stem()?.to_str()?.to_string();
+            let taken_at = id.rsplit_once('-')?.1.parse().ok()?;
+            Some((taken_at, id))
+        })
+        .collect();
+    snapshots.sort();
+    snapshots.into_iter().map(|(_, id)| id).collect()
+}
+
+fn parse_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    Some([(value >> 16) as u8, (value >> 8) as u8, value as u8])
+}
+
+/// The palette colour of an item, or one derived from its id so each item looks the same in
+/// every render.
+fn item_color(config: &SnapshotConfig, item_id: u16) -> [u8; 3] {
+    if let Some(color) = config.palette.get(&item_id).and_then(|color| parse_color(color)) {
+        return color;
+    }
+    let hash = (item_id as u32).wrapping_mul(2_654_435_761);
+    [(hash >> 24) as u8 | 0x40, (hash >> 16) as u8 | 0x40, (hash >> 8) as u8 | 0x40]
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TileChange {
+    pub x: u32,
+    pub y: u32,
+    pub before: Tile,
+    pub after: Tile,
+}
+
+/// What changed between two snapshots of a world.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SnapshotDiff {
+    pub from: String,
+    pub to: String,
+    /// Changed tiles in the area both snapshots cover.
+    pub tiles: Vec<TileChange>,
+    pub dropped_added: Vec<DroppedItem>,
+    pub dropped_removed: Vec<DroppedItem>,
+    pub locks_added: Vec<Lock>,
+    pub locks_removed: Vec<Lock>,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+            && self.dropped_added.is_empty()
+            && self.dropped_removed.is_empty()
+            && self.locks_added.is_empty()
+            && self.locks_removed.is_empty()
+    }
+
+    /// A one-line summary for the game console.
+    pub fn summary(&self) -> String {
+        format!(
+            "{} tiles changed, {} items dropped, {} picked up, {} locks added, {} removed since {}",
+            self.tiles.len(),
+            self.dropped_added.len(),
+            self.dropped_removed.len(),
+            self.locks_added.len(),
+            self.locks_removed.len(),
+            self.from
+        )
+    }
+}
+
+/// Compares two snapshots. Dropped items are matched by uid, locks by position and contents.
+pub fn diff(before: &Snapshot, after: &Snapshot) -> SnapshotDiff {
+    let mut tiles = Vec::new();
+    for y in 0..before.height.min(after.height) {
+        for x in 0..before.width.min(after.width) {
+            let old = before.tiles.get((y * before.width + x) as usize);
+            let new = after.tiles.get((y * after.width + x) as usize);
+            if let (Some(old), Some(new)) = (old, new) {
+                if old != new {
+                    tiles.push(TileChange { x, y, before: old.clone(), after: new.clone() });
+                }
+            }
+        }
+    }
+    let missing_from = |items: &[DroppedItem], others: &[DroppedItem]| -> Vec<DroppedItem> {
+        items.iter().filter(|item| !others.iter().any(|other| other.uid == item.uid)).cloned().collect()
+    };
+    let locks_missing_from = |locks: &[Lock], others: &[Lock]| -> Vec<Lock> {
+        locks.iter().filter(|lock| !others.contains(lock)).cloned().collect()
+    };
+    SnapshotDiff {
+        from: before.id(),
+        to: after.id(),
+        tiles,
+        dropped_added: missing_from(&after.dropped, &before.dropped),
+        dropped_removed: missing_from(&before.dropped, &after.dropped),
+        locks_added: locks_missing_from(&after.locks, &before.locks),
+        locks_removed: locks_missing_from(&before.locks, &after.locks),
+    }
+}
+
+/// Takes a snapshot of the current world.
+pub fn take() -> Option<Snapshot> {
+    world::with(|world| Snapshot::take(world))
+}
+
+fn console(message: &str) {
+    let data = variant_handler::console_message(message);
+    resend_packet(&Packet::reliable(&data), &EPacketType::NetMessageGamePacket, 0, true);
+}
+
+/// Handles `/snapshot` and `/snapshot diff <id>` typed in game. Returns true when the text
+/// packet was such a command and must not be forwarded.
+pub fn handle(message: &str) -> bool {
+    if text_parse::get_field(message, "action") != Some("input") {
+        return false;
+    }
+    let Some(text) = text_parse::get_field(message, "text") else {
+        return false;
+    };
+    let mut words = text.split_whitespace();
+    if !words.next().is_some_and(|command| command.eq_ignore_ascii_case(COMMAND)) {
+        return false;
+    }
+    let config = utils::config::get_snapshot_config();
+    let Some(snapshot) = take() else {
+        console("`4No world to snapshot.``");
+        return true;
+    };
+    match (words.next(), words.next()) {
+        (None, _) => match snapshot.save(&config) {
+            Ok(path) => {
+                info!("Saved snapshot {}", path.display());
+                console(&format!("`2Saved snapshot {}.``", snapshot.id()));
+            }
+            Err(e) => console(&format!("`4Failed to save the snapshot: {}``", e)),
+        },
+        (Some("diff"), Some(id)) => match Snapshot::load(&config, id) {
+            Ok(before) => console(&diff(&before, &snapshot).summary()),
+            Err(e) => console(&format!("`4Failed to load snapshot {}: {}``", id, e)),
+        },
+        _ => console("Usage: /snapshot, or /snapshot diff <id>"),
+    }
+    true
+}
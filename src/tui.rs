@@ -0,0 +1,288 @@
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, Paragraph, Row, Table, TableState, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use crate::capture::{self, CapturedPacket};
+use crate::filter::Filter;
+use crate::metrics::Direction;
+use crate::packet_handler::GamePacket;
+use crate::{global, logging, world};
+
+/// How often the screen is redrawn when no key is pressed.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Runs the terminal UI until the user quits. Logs should go to a file while it runs.
+pub fn run() -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = App::default().run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+#[derive(Default)]
+struct App {
+    filter: String,
+    editing_filter: bool,
+    /// Id of the selected packet, or None to follow the newest one.
+    selected: Option<u64>,
+    detail_scroll: u16,
+    quit: bool,
+}
+
+/// A captured packet as shown in the packet list.
+struct PacketRow {
+    id: u64,
+    time: SystemTime,
+    direction: Direction,
+    channel_id: u8,
+    size: usize,
+    summary: String,
+}
+
+impl App {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        while !self.quit {
+            let rows = self.rows();
+            terminal.draw(|frame| self.draw(frame, &rows))?;
+            if event::poll(REFRESH_INTERVAL)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press {
+                        self.on_key(key, &rows);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Captured packets matching the filter, oldest first.
+    fn rows(&self) -> Vec<PacketRow> {
+        let expression = Filter::parse(&self.filter).ok();
+        let search = self.filter.to_lowercase();
+        capture::with(|capture| {
+            capture
+                .iter()
+                .filter(|packet| match &expression {
+                    _ if search.is_empty() => true,
+                    Some(expression) => expression.matches_captured(packet),
+                    None => matches_search(packet, &search),
+                })
+                .map(|packet| PacketRow {
+                    id: packet.id,
+                    time: packet.time,
+                    direction: packet.direction,
+                    channel_id: packet.channel_id,
+                    size: packet.data.len(),
+                    summary: packet.summary.clone(),
+                })
+                .collect()
+        })
+    }
+
+    fn on_key(&mut self, key: KeyEvent, rows: &[PacketRow]) {
+        if self.editing_filter {
+            match key.code {
+                KeyCode::Enter => self.editing_filter = false,
+                KeyCode::Esc => {
+                    self.filter.clear();
+                    self.editing_filter = false;
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Char(c) => self.filter.push(c),
+                _ => {}
+            }
+            return;
+        }
+        let index = self.selected.and_then(|id| rows.iter().position(|row| row.id == id));
+        let select = |index: usize| rows.get(index).map(|row| row.id);
+        match key.code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.quit = true,
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::Up | KeyCode::Char('k') => {
+                let index = index.unwrap_or(rows.len()).saturating_sub(1);
+                self.selected = select(index);
+                self.detail_scroll = 0;
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = index.and_then(|index| select(index + 1)).or(self.selected);
+                self.detail_scroll = 0;
+            }
+            KeyCode::Home => {
+                self.selected = select(0);
+                self.detail_scroll = 0;
+            }
+            KeyCode::End | KeyCode::Char('f') => {
+                self.selected = None;
+                self.detail_scroll = 0;
+            }
+            KeyCode::PageUp => self.detail_scroll = self.detail_scroll.saturating_sub(10),
+            KeyCode::PageDown => self.detail_scroll = self.detail_scroll.saturating_add(10),
+            _ => {}
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame, rows: &[PacketRow]) {
+        let [body, footer] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [side, main] = Layout::horizontal([Constraint::Length(36), Constraint::Min(0)]).areas(body);
+        let [session_area, players_area] = Layout::vertical([Constraint::Length(5), Constraint::Min(0)]).areas(side);
+        let [list_area, detail_area] = Layout::vertical([Constraint::Percentage(55), Constraint::Percentage(45)]).areas(main);
+
+        self.draw_session(frame, session_area);
+        self.draw_players(frame, players_area);
+        let selected = self.draw_packets(frame, list_area, rows);
+        self.draw_detail(frame, detail_area, selected);
+
+        let footer_text = if self.editing_filter {
+            match Filter::parse(&self.filter) {
+                Err(e) if !self.filter.is_empty() => format!("Filter: {}_  (text search, {})", self.filter, e),
+                _ => format!("Filter: {}_  (Enter to apply, Esc to clear)", self.filter),
+            }
+        } else {
+            format!("q quit  / filter [{}]  ↑↓ select  End follow  PgUp/PgDn scroll detail", self.filter)
+        };
+        frame.render_widget(Paragraph::new(footer_text).style(Style::default().add_modifier(Modifier::REVERSED)), footer);
+    }
+
+    fn draw_session(&self, frame: &mut Frame, area: Rect) {
+        let (id, state) = {
+            let session = global().session.lock().unwrap();
+            (session.id, session.state.to_string())
+        };
+        let world_name = world::with(|world| world.name.clone()).unwrap_or_else(|| "-".to_string());
+        let lines = vec![
+            Line::from(format!("#{} {}", id, state)),
+            Line::from(format!("World: {}", world_name)),
+            Line::from(format!("Captured: {}", capture::with(|capture| capture.len()))),
+        ];
+        frame.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Session")), area);
+    }
+
+    fn draw_players(&self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = world::with(|world| {
+            world
+                .players
+                .values()
+                .map(|player| {
+                    let local = if Some(player.net_id) == world.local_net_id { "*" } else { " " };
+                    ListItem::new(format!(
+                        "{}{} ({}) {:.0},{:.0}",
+                        local,
+                        player.name,
+                        player.net_id,
+                        player.position.x / 32.0,
+                        player.position.y / 32.0
+                    ))
+                })
+                .collect()
+        });
+        let title = format!("Players ({})", items.len());
+        frame.render_widget(List::new(items).block(Block::default().borders(Borders::ALL).title(title)), area);
+    }
+
+    /// Draws the packet list and returns the id of the highlighted packet.
+    fn draw_packets(&self, frame: &mut Frame, area: Rect, rows: &[PacketRow]) -> Option<u64> {
+        let index = match self.selected {
+            Some(id) => rows.iter().position(|row| row.id == id),
+            None => rows.len().checked_sub(1),
+        };
+        let table_rows = rows.iter().map(|row| {
+            let style = match row.direction {
+                Direction::FromClient => Style::default().fg(Color::Cyan),
+                Direction::FromServer => Style::default().fg(Color::Yellow),
+            };
+            Row::new(vec![
+                row.id.to_string(),
+                format_time(row.time),
+                match row.direction {
+                    Direction::FromClient => "C>S".to_string(),
+                    Direction::FromServer => "S>C".to_string(),
+                },
+                row.channel_id.to_string(),
+                row.size.to_string(),
+                row.summary.clone(),
+            ])
+            .style(style)
+        });
+        let title = if self.selected.is_none() { "Packets (following)" } else { "Packets" };
+        let table = Table::new(
+            table_rows,
+            [
+                Constraint::Length(7),
+                Constraint::Length(12),
+                Constraint::Length(4),
+                Constraint::Length(3),
+                Constraint::Length(6),
+                Constraint::Min(0),
+            ],
+        )
+        .header(Row::new(vec!["#", "time", "dir", "ch", "size", "packet"]).style(Style::default().add_modifier(Modifier::BOLD)))
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut state = TableState::default().with_selected(index);
+        frame.render_stateful_widget(table, area, &mut state);
+        index.map(|index| rows[index].id)
+    }
+
+    fn draw_detail(&self, frame: &mut Frame, area: Rect, selected: Option<u64>) {
+        let packet = selected.and_then(|id| capture::with(|capture| capture.get(id).cloned()));
+        let text = match packet {
+            Some(packet) => describe(&packet),
+            None => "No packet selected".to_string(),
+        };
+        let paragraph = Paragraph::new(text)
+            .block(Block::default().borders(Borders::ALL).title("Detail"))
+            .wrap(Wrap { trim: false })
+            .scroll((self.detail_scroll, 0));
+        frame.render_widget(paragraph, area);
+    }
+}
+
+/// Plain text search for filters that aren't valid expressions.
+fn matches_search(packet: &CapturedPacket, search: &str) -> bool {
+    packet.summary.to_lowercase().contains(search) || packet.direction.as_str().contains(search)
+}
+
+/// Wall-clock time of day in UTC, with milliseconds.
+fn format_time(time: SystemTime) -> String {
+    let millis = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() % 86_400_000;
+    format!("{:02}:{:02}:{:02}.{:03}", millis / 3_600_000, millis / 60_000 % 60, millis / 1000 % 60, millis % 1000)
+}
+
+/// The decoded fields of a captured packet, followed by its hex dump.
+fn describe(packet: &CapturedPacket) -> String {
+    let mut out = format!(
+        "#{} session {} {} channel {} ({} bytes)\n\n",
+        packet.id,
+        packet.session,
+        packet.direction.as_str(),
+        packet.channel_id,
+        packet.data.len()
+    );
+    match packet.decode() {
+        Ok((packet_type, GamePacket::Tank { tank_packet, variants, trailing })) => {
+            out.push_str(&format!("{:?}\n{:#?}\n", packet_type, tank_packet));
+            if let Some(variants) = variants {
+                out.push_str("\nVariantList\n");
+                for (index, variant) in variants.iter().enumerate() {
+                    out.push_str(&format!("  [{}] {:?}\n", index, variant));
+                }
+            }
+            if !trailing.is_empty() {
+                out.push_str(&format!("\n{} trailing bytes\n", trailing.len()));
+            }
+        }
+        Ok((packet_type, GamePacket::Text(text))) => out.push_str(&format!("{:?}\n{}\n", packet_type, text)),
+        Ok((packet_type, GamePacket::Raw)) => out.push_str(&format!("{:?}\n", packet_type)),
+        Err(e) => out.push_str(&format!("Failed to decode: {}\n", e)),
+    }
+    out.push('\n');
+    out.push_str(&logging::hex_dump(&packet.data));
+    out
+}
@@ -1,7 +1,302 @@
+use std::collections::BTreeMap;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
     pub web_server_port: u16,
     pub enet_server_port: u16,
-}
\ No newline at end of file
+    #[serde(default)]
+    pub reconnect: ReconnectPolicy,
+    #[serde(default)]
+    pub ping: PingConfig,
+    #[serde(default)]
+    pub headless: HeadlessConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub hooks: Hooks,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    #[serde(default)]
+    pub plugins: PluginConfig,
+    #[serde(default)]
+    pub snapshots: SnapshotConfig,
+    #[serde(default)]
+    pub capture: CaptureConfig,
+    #[serde(default)]
+    pub dashboard: DashboardConfig,
+    /// The game's `items.dat`, needed for pathfinding.
+    #[serde(default = "default_items_dat")]
+    pub items_dat: String,
+}
+
+fn default_items_dat() -> String {
+    "items.dat".to_string()
+}
+
+/// Built-in rewrites that can be switched off at runtime.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hooks {
+    /// Set `mstate|1` on the local player's `OnSpawn`.
+    pub spawn_mod_state: bool,
+    /// Drop `NetGamePacketAppIntegrityFail` instead of forwarding it.
+    pub block_app_integrity_fail: bool,
+    /// Open the proxy's settings menu when the local player is wrenched, instead of the profile.
+    pub wrench_menu: bool,
+}
+
+impl Default for Hooks {
+    fn default() -> Self {
+        Self {
+            spawn_mod_state: true,
+            block_app_integrity_fail: true,
+            wrench_menu: false,
+        }
+    }
+}
+
+/// Where world snapshots are saved and how they are drawn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnapshotConfig {
+    pub dir: String,
+    /// Width and height of a tile in the PNG render.
+    pub tile_pixels: u32,
+    /// `#rrggbb` colours by item id. Other items get a colour derived from their id.
+    pub palette: BTreeMap<u16, String>,
+    /// Colour of tiles with neither foreground nor background.
+    pub empty: String,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        let palette = [(2, "#8b5a2b"), (6, "#c0392b"), (8, "#3c3c3c"), (14, "#5a3e28"), (242, "#f1c40f")];
+        Self {
+            dir: "snapshots".to_string(),
+            tile_pixels: 4,
+            palette: palette.into_iter().map(|(item_id, color)| (item_id, color.to_string())).collect(),
+            empty: "#60a8e0".to_string(),
+        }
+    }
+}
+
+/// Whether packets are kept for the dashboard's packet feed and `/api/packets`. The terminal
+/// UI always keeps them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureConfig {
+    pub enabled: bool,
+}
+
+/// Who can reach the web server and the dashboard on it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DashboardConfig {
+    /// Address the web server listens on. Anything but a loopback address needs `token`.
+    pub bind: String,
+    /// Required by the dashboard and its API when set, as `Authorization: Bearer <token>` or a
+    /// `token` query parameter.
+    pub token: String,
+}
+
+impl Default for DashboardConfig {
+    fn default() -> Self {
+        Self { bind: "127.0.0.1".to_string(), token: String::new() }
+    }
+}
+
+/// Resource limits for the WASM plugins in the `plugins` directory.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginConfig {
+    /// Limits for plugins without an entry in `overrides`.
+    pub limits: PluginLimits,
+    /// Limits for individual plugins, by file name without `.wasm`.
+    pub overrides: BTreeMap<String, PluginLimits>,
+}
+
+impl PluginConfig {
+    pub fn limits_for(&self, name: &str) -> &PluginLimits {
+        self.overrides.get(name).unwrap_or(&self.limits)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginLimits {
+    /// Fuel a plugin may burn handling one packet, roughly one unit per instruction.
+    pub fuel: u64,
+    /// Largest the plugin's linear memory may grow, in bytes.
+    pub max_memory: usize,
+    /// Packets a plugin may inject while handling one packet.
+    pub max_injections: usize,
+}
+
+impl Default for PluginLimits {
+    fn default() -> Self {
+        Self {
+            fuel: 10_000_000,
+            max_memory: 16 * 1024 * 1024,
+            max_injections: 16,
+        }
+    }
+}
+
+/// Sessions the proxy runs without a game client.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeadlessConfig {
+    /// Start a headless session when the proxy starts.
+    pub start: bool,
+    /// Keep the session when the game client leaves, instead of disconnecting from the server.
+    pub keep_alive: bool,
+    /// The login text to send, as a game client sends it. Empty to reuse the last client's.
+    pub login: String,
+    /// Sent to `server_data.php` like a game client would.
+    pub version: String,
+    pub platform: String,
+    pub protocol: String,
+}
+
+impl Default for HeadlessConfig {
+    fn default() -> Self {
+        Self {
+            start: false,
+            keep_alive: false,
+            login: String::new(),
+            version: "5.11".to_string(),
+            platform: "0".to_string(),
+            protocol: "216".to_string(),
+        }
+    }
+}
+
+/// When the proxy answers the server's pings itself instead of waiting for the game client.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PingConfig {
+    /// Answer pings the game client hasn't answered after `client_timeout_ms`, and any ping
+    /// received while resuming.
+    pub answer: bool,
+    pub client_timeout_ms: u64,
+}
+
+impl Default for PingConfig {
+    fn default() -> Self {
+        Self {
+            answer: true,
+            client_timeout_ms: 2000,
+        }
+    }
+}
+
+/// What to do when the Growtopia server drops the proxy while the game client is still connected.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReconnectPolicy {
+    /// Reconnect to the last sub-server instead of kicking the game client.
+    pub enabled: bool,
+    pub initial_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub max_attempts: u32,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            initial_delay_ms: 1000,
+            max_delay_ms: 30000,
+            max_attempts: 5,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Delay before the given attempt, doubling from `initial_delay_ms` up to `max_delay_ms`.
+    pub fn delay(&self, attempt: u32) -> std::time::Duration {
+        let factor = 1u64.checked_shl(attempt.saturating_sub(1)).unwrap_or(u64::MAX);
+        let delay = self.initial_delay_ms.saturating_mul(factor).min(self.max_delay_ms);
+        std::time::Duration::from_millis(delay)
+    }
+}
+
+/// Log output format and verbosity. `RUST_LOG` overrides `level` and `modules` when set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// Default level for the proxy's own modules.
+    pub level: String,
+    /// Levels for individual modules, e.g. `"growtopia_proxy::enet": "debug"`.
+    pub modules: BTreeMap<String, String>,
+    /// Write one JSON object per line instead of plain text.
+    pub json: bool,
+    /// Packet types, tank packet types or function names whose payload is hex dumped.
+    pub hex_dump: Vec<String>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            modules: BTreeMap::new(),
+            json: false,
+            hex_dump: Vec::new(),
+        }
+    }
+}
+
+impl LoggingConfig {
+    /// The filter directives for `level` and `modules`. Other crates only log warnings.
+    pub fn directives(&self) -> String {
+        let mut directives = vec!["warn".to_string(), format!("growtopia_proxy={}", self.level)];
+        directives.extend(self.modules.iter().map(|(module, level)| format!("{}={}", module, level)));
+        directives.join(",")
+    }
+}
+
+/// A packet rewrite applied to every packet matching a `filter` expression.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    #[serde(default)]
+    pub name: String,
+    #[serde(rename = "match")]
+    pub expression: String,
+    #[serde(flatten)]
+    pub action: RuleAction,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RuleAction {
+    /// Don't forward the packet.
+    Drop,
+    /// Replace `find` in a text packet, or in the string variants of a function call. `variant`
+    /// limits it to one variant.
+    Replace {
+        find: String,
+        replace: String,
+        #[serde(default)]
+        variant: Option<usize>,
+    },
+    /// Set `key|value` in a text packet, or in a string variant of a function call
+    /// (`variant`, default 1) such as the `OnSpawn` message.
+    SetField {
+        key: String,
+        value: String,
+        #[serde(default)]
+        variant: Option<usize>,
+    },
+    /// Replace a variant of a function call. Numbers keep the variant's existing type.
+    SetVariant {
+        index: usize,
+        value: serde_json::Value,
+    },
+}
@@ -1,5 +1,5 @@
 #[repr(u32)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EPacketType {
     NetMessageUnknown,
     NetMessageServerHello,
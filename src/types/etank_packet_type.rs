@@ -1,7 +1,6 @@
-use serde_repr::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Serialize_repr, Deserialize_repr, Debug)]
-#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ETankPacketType {
     NetGamePacketState,
     NetGamePacketCallFunction,
@@ -50,6 +49,8 @@ pub enum ETankPacketType {
     NetGamePacketPVENPCPositionUpdate,
     NetGamePacketSetExtraMods,
     NetGamePacketOnStepTileMod,
+    /// A type this proxy doesn't know yet, kept so the packet is re-encoded unchanged.
+    Unknown(u8),
 }
 
 impl Default for ETankPacketType {
@@ -108,7 +109,74 @@ impl From<u8> for ETankPacketType {
             44 => ETankPacketType::NetGamePacketPVENPCPositionUpdate,
             45 => ETankPacketType::NetGamePacketSetExtraMods,
             46 => ETankPacketType::NetGamePacketOnStepTileMod,
-            _ => ETankPacketType::NetGamePacketState,
+            _ => ETankPacketType::Unknown(value),
         }
     }
+}
+
+impl From<ETankPacketType> for u8 {
+    fn from(value: ETankPacketType) -> Self {
+        match value {
+            ETankPacketType::NetGamePacketState => 0,
+            ETankPacketType::NetGamePacketCallFunction => 1,
+            ETankPacketType::NetGamePacketUpdateStatus => 2,
+            ETankPacketType::NetGamePacketTileChangeRequest => 3,
+            ETankPacketType::NetGamePacketSendMapData => 4,
+            ETankPacketType::NetGamePacketSendTileUpdateData => 5,
+            ETankPacketType::NetGamePacketSendTileUpdateDataMultiple => 6,
+            ETankPacketType::NetGamePacketTileActivateRequest => 7,
+            ETankPacketType::NetGamePacketTileApplyDamage => 8,
+            ETankPacketType::NetGamePacketSendInventoryState => 9,
+            ETankPacketType::NetGamePacketItemActivateRequest => 10,
+            ETankPacketType::NetGamePacketItemActivateObjectRequest => 11,
+            ETankPacketType::NetGamePacketSendTileTreeState => 12,
+            ETankPacketType::NetGamePacketModifyItemInventory => 13,
+            ETankPacketType::NetGamePacketItemChangeObject => 14,
+            ETankPacketType::NetGamePacketSendLock => 15,
+            ETankPacketType::NetGamePacketSendItemDatabaseData => 16,
+            ETankPacketType::NetGamePacketSendParticleEffect => 17,
+            ETankPacketType::NetGamePacketSetIconState => 18,
+            ETankPacketType::NetGamePacketItemEffect => 19,
+            ETankPacketType::NetGamePacketSetCharacterState => 20,
+            ETankPacketType::NetGamePacketPingReply => 21,
+            ETankPacketType::NetGamePacketPingRequest => 22,
+            ETankPacketType::NetGamePacketGotPunched => 23,
+            ETankPacketType::NetGamePacketAppCheckResponse => 24,
+            ETankPacketType::NetGamePacketAppIntegrityFail => 25,
+            ETankPacketType::NetGamePacketDisconnect => 26,
+            ETankPacketType::NetGamePacketBattleJoin => 27,
+            ETankPacketType::NetGamePacketBattleEvent => 28,
+            ETankPacketType::NetGamePacketUseDoor => 29,
+            ETankPacketType::NetGamePacketSendParental => 30,
+            ETankPacketType::NetGamePacketGoneFishin => 31,
+            ETankPacketType::NetGamePacketSteam => 32,
+            ETankPacketType::NetGamePacketPetBattle => 33,
+            ETankPacketType::NetGamePacketNpc => 34,
+            ETankPacketType::NetGamePacketSpecial => 35,
+            ETankPacketType::NetGamePacketSendParticleEffectV2 => 36,
+            ETankPacketType::NetGameActivateArrowToItem => 37,
+            ETankPacketType::NetGameSelectTileIndex => 38,
+            ETankPacketType::NetGamePacketSendPlayerTributeData => 39,
+            ETankPacketType::NetGamePacketFTUESetItemToQuickInventory => 40,
+            ETankPacketType::NetGamePacketPVENpc => 41,
+            ETankPacketType::NetGamePacketPVPCardBattle => 42,
+            ETankPacketType::NetGamePacketPVEApplyPlayerDamage => 43,
+            ETankPacketType::NetGamePacketPVENPCPositionUpdate => 44,
+            ETankPacketType::NetGamePacketSetExtraMods => 45,
+            ETankPacketType::NetGamePacketOnStepTileMod => 46,
+            ETankPacketType::Unknown(value) => value,
+        }
+    }
+}
+
+impl Serialize for ETankPacketType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8((*self).into())
+    }
+}
+
+impl<'de> Deserialize<'de> for ETankPacketType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u8::deserialize(deserializer).map(Self::from)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,227 @@
+//! The item database the game client keeps as `items.dat`.
+//!
+//! | field | type |
+//! |---|---|
+//! | version | u16 |
+//! | item count | u32 |
+//! | items | see [`Item::decode`] |
+//!
+//! Everything is little-endian and strings are a u16 length followed by the bytes. Only the
+//! fields the proxy uses are kept; the rest are skipped.
+
+use std::fmt;
+use std::io::{self, Cursor};
+use std::path::Path;
+use byteorder::{LittleEndian, ReadBytesExt};
+use serde::Serialize;
+use crate::utils::cursor::{read_bytes, read_string, skip};
+
+/// Newest `items.dat` version whose layout is known.
+pub const MAX_VERSION: u16 = 22;
+
+/// Key the item names are XORed with, starting at the item id.
+const NAME_KEY: &[u8] = b"PBG892FXX982ABC*";
+
+/// How a tile of an item stops players.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionType {
+    None,
+    Solid,
+    /// A platform: players jump up through it and stand on top.
+    JumpThrough,
+    /// An entrance that only lets players with access to its lock through, unless it's public.
+    Gateway,
+    /// Solid while switched off.
+    IfOff,
+    /// Can only be passed in the direction it faces.
+    OneWay,
+    VipEntrance,
+    /// A platform players can also drop down through.
+    JumpDown,
+    Adventure,
+    /// Solid while switched on.
+    IfOn,
+    TeamEntrance,
+    GuildEntrance,
+    Cloud,
+    FriendEntrance,
+    Other(u8),
+}
+
+impl From<u8> for CollisionType {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => CollisionType::None,
+            1 => CollisionType::Solid,
+            2 => CollisionType::JumpThrough,
+            3 => CollisionType::Gateway,
+            4 => CollisionType::IfOff,
+            5 => CollisionType::OneWay,
+            6 => CollisionType::VipEntrance,
+            7 => CollisionType::JumpDown,
+            8 => CollisionType::Adventure,
+            9 => CollisionType::IfOn,
+            10 => CollisionType::TeamEntrance,
+            11 => CollisionType::GuildEntrance,
+            12 => CollisionType::Cloud,
+            13 => CollisionType::FriendEntrance,
+            other => CollisionType::Other(other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Item {
+    pub id: u32,
+    pub editable_type: u8,
+    pub category: u8,
+    pub action_type: u8,
+    pub name: String,
+    pub texture: String,
+    pub collision_type: CollisionType,
+    /// Hits to break the block, times six.
+    pub break_hits: u8,
+    pub clothing_type: u8,
+    pub rarity: u16,
+    pub max_amount: u8,
+    /// Seconds for a tree of this item to grow.
+    pub grow_time: u32,
+}
+
+#[derive(Debug)]
+pub enum ItemDatabaseError {
+    Io(io::Error),
+    UnsupportedVersion(u16),
+}
+
+impl fmt::Display for ItemDatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ItemDatabaseError::Io(err) => write!(f, "failed to read item database: {}", err),
+            ItemDatabaseError::UnsupportedVersion(version) => write!(f, "unsupported item database version {}", version),
+        }
+    }
+}
+
+impl From<io::Error> for ItemDatabaseError {
+    fn from(err: io::Error) -> Self {
+        ItemDatabaseError::Io(err)
+    }
+}
+
+fn skip_strings(cursor: &mut Cursor<&[u8]>, count: usize) -> io::Result<()> {
+    for _ in 0..count {
+        let len = cursor.read_u16::<LittleEndian>()?;
+        skip(cursor, len as u64)?;
+    }
+    Ok(())
+}
+
+impl Item {
+    /// Reads one item of a database of the given version:
+    ///
+    /// id (u32), editable type, category, action type, hit sound (u8), name (XORed from version
+    /// 3), texture, texture hash (u32), kind (u8), an u32, texture x and y, spread type, stripey
+    /// wallpaper, collision type, break hits (u8), drop chance (u32), clothing type (u8), rarity
+    /// (u16), max amount (u8), extra file, its hash and audio volume (u32), pet name, prefix,
+    /// suffix and ability, seed and tree sprites (4 u8), seed colours, ingredient, grow time
+    /// (u32), two u16, three strings and 80 reserved bytes, followed by the fields newer versions
+    /// added.
+    pub fn decode(cursor: &mut Cursor<&[u8]>, version: u16) -> io::Result<Self> {
+        let id = cursor.read_u32::<LittleEndian>()?;
+        let editable_type = cursor.read_u8()?;
+        let category = cursor.read_u8()?;
+        let action_type = cursor.read_u8()?;
+        skip(cursor, 1)?;
+        let mut name = read_bytes(cursor)?;
+        if version >= 3 {
+            for (index, byte) in name.iter_mut().enumerate() {
+                *byte ^= NAME_KEY[(id as usize + index) % NAME_KEY.len()];
+            }
+        }
+        let name = String::from_utf8_lossy(&name).into_owned();
+        let texture = read_string(cursor)?;
+        skip(cursor, 4 + 1 + 4 + 4)?;
+        let collision_type = CollisionType::from(cursor.read_u8()?);
+        let break_hits = cursor.read_u8()?;
+        skip(cursor, 4)?;
+        let clothing_type = cursor.read_u8()?;
+        let rarity = cursor.read_u16::<LittleEndian>()?;
+        let max_amount = cursor.read_u8()?;
+        skip_strings(cursor, 1)?;
+        skip(cursor, 8)?;
+        skip_strings(cursor, 4)?;
+        skip(cursor, 4 + 4 + 4 + 4)?;
+        let grow_time = cursor.read_u32::<LittleEndian>()?;
+        skip(cursor, 4)?;
+        skip_strings(cursor, 3)?;
+        skip(cursor, 80)?;
+        if version >= 11 {
+            skip_strings(cursor, 1)?;
+        }
+        if version >= 12 {
+            skip(cursor, 13)?;
+        }
+        if version >= 13 {
+            skip(cursor, 4)?;
+        }
+        if version >= 14 {
+            skip(cursor, 4)?;
+        }
+        if version >= 15 {
+            skip(cursor, 25)?;
+            skip_strings(cursor, 1)?;
+        }
+        if version >= 16 {
+            skip_strings(cursor, 1)?;
+        }
+        if version >= 17 {
+            skip(cursor, 4)?;
+        }
+        if version >= 18 {
+            skip(cursor, 4)?;
+        }
+        if version >= 19 {
+            skip(cursor, 9)?;
+        }
+        if version >= 21 {
+            skip(cursor, 2)?;
+        }
+        if version >= 22 {
+            skip_strings(cursor, 1)?;
+        }
+        Ok(Self { id, editable_type, category, action_type, name, texture, collision_type, break_hits, clothing_type, rarity, max_amount, grow_time })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ItemDatabase {
+    pub version: u16,
+    /// Indexed by item id.
+    pub items: Vec<Item>,
+}
+
+impl ItemDatabase {
+    pub fn decode(data: &[u8]) -> Result<Self, ItemDatabaseError> {
+        let mut cursor = Cursor::new(data);
+        let version = cursor.read_u16::<LittleEndian>()?;
+        if version > MAX_VERSION {
+            return Err(ItemDatabaseError::UnsupportedVersion(version));
+        }
+        let count = cursor.read_u32::<LittleEndian>()?;
+        let items = (0..count).map(|_| Item::decode(&mut cursor, version)).collect::<io::Result<_>>()?;
+        Ok(Self { version, items })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ItemDatabaseError> {
+        Self::decode(&std::fs::read(path)?)
+    }
+
+    pub fn get(&self, id: u16) -> Option<&Item> {
+        self.items
+            .get(id as usize)
+            .filter(|item| item.id == id as u32)
+            .or_else(|| self.items.iter().find(|item| item.id == id as u32))
+    }
+}
@@ -2,6 +2,9 @@ pub mod epacket_type;
 pub mod etank_packet_type;
 pub mod packet_flag;
 pub mod tank_packet;
+pub mod tank_view;
 pub mod player;
 pub mod vector2;
+pub mod world_data;
+pub mod item_database;
 pub mod config;
\ No newline at end of file
@@ -1,8 +1,46 @@
 use super::etank_packet_type::ETankPacketType;
+use crate::types::epacket_type::EPacketType;
 use crate::types::packet_flag::PacketFlag;
+use byteorder::{ByteOrder, LittleEndian};
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+/// Size of the tank header on the wire, not counting the 4-byte message type before it.
+///
+/// | offset | field |
+/// |---|---|
+/// | 0 | `type` (u8) |
+/// | 1..4 | `unk1`, `unk2`, `unk3` (u8) |
+/// | 4 | `net_id` (u32) |
+/// | 8 | `sec_id` (u32) |
+/// | 12 | `flags` (u32) |
+/// | 16 | `unk6` (f32) |
+/// | 20 | `value` (u32) |
+/// | 24 | `vector_x`, `vector_y`, `vector_x2`, `vector_y2`, `unk12` (f32) |
+/// | 44 | `int_x`, `int_y` (i32) |
+/// | 52 | `extended_data_length` (u32) |
+///
+/// Everything is little-endian.
+pub const TANK_PACKET_SIZE: usize = 56;
+
+#[derive(Debug)]
+pub enum TankPacketError {
+    TooShort(usize),
+    ExtendedDataLength { declared: u32, available: usize },
+}
+
+impl fmt::Display for TankPacketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TankPacketError::TooShort(len) => write!(f, "expected {} header bytes, got {}", TANK_PACKET_SIZE, len),
+            TankPacketError::ExtendedDataLength { declared, available } => {
+                write!(f, "extended data length {} exceeds the {} bytes available", declared, available)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct TankPacket {
     pub r#type: ETankPacketType,
     pub unk1: u8,
@@ -21,4 +59,75 @@ pub struct TankPacket {
     pub int_x: i32,
     pub int_y: i32,
     pub extended_data_length: u32,
-}
\ No newline at end of file
+}
+
+impl TankPacket {
+    /// Decodes a tank header from the bytes following the message type.
+    ///
+    /// Returns the header, the `extended_data_length` bytes after it and whatever trails them.
+    pub fn decode(data: &[u8]) -> Result<(Self, &[u8], &[u8]), TankPacketError> {
+        if data.len() < TANK_PACKET_SIZE {
+            return Err(TankPacketError::TooShort(data.len()));
+        }
+        let tank_packet = Self {
+            r#type: ETankPacketType::from(data[0]),
+            unk1: data[1],
+            unk2: data[2],
+            unk3: data[3],
+            net_id: LittleEndian::read_u32(&data[4..8]),
+            sec_id: LittleEndian::read_u32(&data[8..12]),
+            flags: PacketFlag::from_u32(LittleEndian::read_u32(&data[12..16])),
+            unk6: LittleEndian::read_f32(&data[16..20]),
+            value: LittleEndian::read_u32(&data[20..24]),
+            vector_x: LittleEndian::read_f32(&data[24..28]),
+            vector_y: LittleEndian::read_f32(&data[28..32]),
+            vector_x2: LittleEndian::read_f32(&data[32..36]),
+            vector_y2: LittleEndian::read_f32(&data[36..40]),
+            unk12: LittleEndian::read_f32(&data[40..44]),
+            int_x: LittleEndian::read_i32(&data[44..48]),
+            int_y: LittleEndian::read_i32(&data[48..52]),
+            extended_data_length: LittleEndian::read_u32(&data[52..56]),
+        };
+
+        let rest = &data[TANK_PACKET_SIZE..];
+        let declared = tank_packet.extended_data_length;
+        if declared as usize > rest.len() {
+            return Err(TankPacketError::ExtendedDataLength { declared, available: rest.len() });
+        }
+        let (extended_data, trailing) = rest.split_at(declared as usize);
+        Ok((tank_packet, extended_data, trailing))
+    }
+
+    pub fn encode(&self) -> [u8; TANK_PACKET_SIZE] {
+        let mut data = [0u8; TANK_PACKET_SIZE];
+        data[0] = self.r#type.into();
+        data[1] = self.unk1;
+        data[2] = self.unk2;
+        data[3] = self.unk3;
+        LittleEndian::write_u32(&mut data[4..8], self.net_id);
+        LittleEndian::write_u32(&mut data[8..12], self.sec_id);
+        LittleEndian::write_u32(&mut data[12..16], self.flags.to_u32());
+        LittleEndian::write_f32(&mut data[16..20], self.unk6);
+        LittleEndian::write_u32(&mut data[20..24], self.value);
+        LittleEndian::write_f32(&mut data[24..28], self.vector_x);
+        LittleEndian::write_f32(&mut data[28..32], self.vector_y);
+        LittleEndian::write_f32(&mut data[32..36], self.vector_x2);
+        LittleEndian::write_f32(&mut data[36..40], self.vector_y2);
+        LittleEndian::write_f32(&mut data[40..44], self.unk12);
+        LittleEndian::write_i32(&mut data[44..48], self.int_x);
+        LittleEndian::write_i32(&mut data[48..52], self.int_y);
+        LittleEndian::write_u32(&mut data[52..56], self.extended_data_length);
+        data
+    }
+
+    /// Builds a complete `NetMessageGamePacket` message, updating `extended_data_length` to match.
+    pub fn to_message(&mut self, extended_data: &[u8], trailing: &[u8]) -> Vec<u8> {
+        self.extended_data_length = extended_data.len() as u32;
+        let mut data = Vec::with_capacity(4 + TANK_PACKET_SIZE + extended_data.len() + trailing.len());
+        data.extend_from_slice(&(EPacketType::NetMessageGamePacket as u32).to_le_bytes());
+        data.extend_from_slice(&self.encode());
+        data.extend_from_slice(extended_data);
+        data.extend_from_slice(trailing);
+        data
+    }
+}
@@ -0,0 +1,497 @@
+//! Typed views over [`TankPacket`] for packet types whose generic fields carry a fixed meaning.
+//!
+//! ```ignore
+//! if let Some(mut request) = tank_packet.view::<TileChangeRequest>() {
+//!     request.item_id = 18;
+//!     request.write(&mut tank_packet);
+//! }
+//! ```
+//!
+//! Fields that aren't named by a view are left untouched by [`TankView::write`].
+
+use crate::types::etank_packet_type::ETankPacketType;
+use crate::types::packet_flag::PacketFlag;
+use crate::types::tank_packet::TankPacket;
+use crate::types::vector2::Vector2;
+
+/// Item id the client sends in a [`TileChangeRequest`] when punching.
+pub const FIST_ITEM_ID: u32 = 18;
+
+pub trait TankView: Sized {
+    const TYPE: ETankPacketType;
+
+    fn read(tank_packet: &TankPacket) -> Self;
+
+    /// Writes the view's fields back into `tank_packet`, leaving other fields as they were.
+    fn write(&self, tank_packet: &mut TankPacket);
+
+    /// Builds a fresh packet of this view's type.
+    fn to_tank_packet(&self) -> TankPacket {
+        let mut tank_packet = TankPacket {
+            r#type: Self::TYPE,
+            ..Default::default()
+        };
+        self.write(&mut tank_packet);
+        tank_packet
+    }
+}
+
+impl TankPacket {
+    /// Returns the typed view of this packet if it is of the view's type.
+    pub fn view<T: TankView>(&self) -> Option<T> {
+        if self.r#type == T::TYPE {
+            Some(T::read(self))
+        } else {
+            None
+        }
+    }
+}
+
+/// `NetGamePacketState`, sent for player movement.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct State {
+    pub net_id: u32,
+    pub flags: PacketFlag,
+    pub position: Vector2,
+    pub velocity: Vector2,
+    /// Tile being punched or built on, when `PUNCH` or `PLACE` is set in `flags`.
+    pub tile_x: i32,
+    pub tile_y: i32,
+}
+
+impl TankView for State {
+    const TYPE: ETankPacketType = ETankPacketType::NetGamePacketState;
+
+    fn read(tank_packet: &TankPacket) -> Self {
+        Self {
+            net_id: tank_packet.net_id,
+            flags: tank_packet.flags,
+            position: Vector2 { x: tank_packet.vector_x, y: tank_packet.vector_y },
+            velocity: Vector2 { x: tank_packet.vector_x2, y: tank_packet.vector_y2 },
+            tile_x: tank_packet.int_x,
+            tile_y: tank_packet.int_y,
+        }
+    }
+
+    fn write(&self, tank_packet: &mut TankPacket) {
+        tank_packet.net_id = self.net_id;
+        tank_packet.flags = self.flags;
+        tank_packet.vector_x = self.position.x;
+        tank_packet.vector_y = self.position.y;
+        tank_packet.vector_x2 = self.velocity.x;
+        tank_packet.vector_y2 = self.velocity.y;
+        tank_packet.int_x = self.tile_x;
+        tank_packet.int_y = self.tile_y;
+    }
+}
+
+/// `NetGamePacketTileChangeRequest`, sent to place an item on a tile or punch it with the fist.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TileChangeRequest {
+    pub net_id: u32,
+    pub item_id: u32,
+    pub tile_x: i32,
+    pub tile_y: i32,
+    pub position: Vector2,
+}
+
+impl TileChangeRequest {
+    pub fn is_punch(&self) -> bool {
+        self.item_id == FIST_ITEM_ID
+    }
+}
+
+impl TankView for TileChangeRequest {
+    const TYPE: ETankPacketType = ETankPacketType::NetGamePacketTileChangeRequest;
+
+    fn read(tank_packet: &TankPacket) -> Self {
+        Self {
+            net_id: tank_packet.net_id,
+            item_id: tank_packet.value,
+            tile_x: tank_packet.int_x,
+            tile_y: tank_packet.int_y,
+            position: Vector2 { x: tank_packet.vector_x, y: tank_packet.vector_y },
+        }
+    }
+
+    fn write(&self, tank_packet: &mut TankPacket) {
+        tank_packet.net_id = self.net_id;
+        tank_packet.value = self.item_id;
+        tank_packet.int_x = self.tile_x;
+        tank_packet.int_y = self.tile_y;
+        tank_packet.vector_x = self.position.x;
+        tank_packet.vector_y = self.position.y;
+    }
+}
+
+/// `NetGamePacketTileActivateRequest`, sent when entering a door or using a tile.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TileActivateRequest {
+    pub net_id: u32,
+    pub tile_x: i32,
+    pub tile_y: i32,
+    pub position: Vector2,
+}
+
+impl TankView for TileActivateRequest {
+    const TYPE: ETankPacketType = ETankPacketType::NetGamePacketTileActivateRequest;
+
+    fn read(tank_packet: &TankPacket) -> Self {
+        Self {
+            net_id: tank_packet.net_id,
+            tile_x: tank_packet.int_x,
+            tile_y: tank_packet.int_y,
+            position: Vector2 { x: tank_packet.vector_x, y: tank_packet.vector_y },
+        }
+    }
+
+    fn write(&self, tank_packet: &mut TankPacket) {
+        tank_packet.net_id = self.net_id;
+        tank_packet.int_x = self.tile_x;
+        tank_packet.int_y = self.tile_y;
+        tank_packet.vector_x = self.position.x;
+        tank_packet.vector_y = self.position.y;
+    }
+}
+
+/// `NetGamePacketTileApplyDamage`, the server's answer to a punch that didn't break the tile.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TileApplyDamage {
+    pub net_id: u32,
+    pub damage: u32,
+    pub tile_x: i32,
+    pub tile_y: i32,
+}
+
+impl TankView for TileApplyDamage {
+    const TYPE: ETankPacketType = ETankPacketType::NetGamePacketTileApplyDamage;
+
+    fn read(tank_packet: &TankPacket) -> Self {
+        Self {
+            net_id: tank_packet.net_id,
+            damage: tank_packet.value,
+            tile_x: tank_packet.int_x,
+            tile_y: tank_packet.int_y,
+        }
+    }
+
+    fn write(&self, tank_packet: &mut TankPacket) {
+        tank_packet.net_id = self.net_id;
+        tank_packet.value = self.damage;
+        tank_packet.int_x = self.tile_x;
+        tank_packet.int_y = self.tile_y;
+    }
+}
+
+/// `NetGamePacketItemActivateRequest`, sent when wearing or consuming an inventory item.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ItemActivateRequest {
+    pub item_id: u32,
+}
+
+impl TankView for ItemActivateRequest {
+    const TYPE: ETankPacketType = ETankPacketType::NetGamePacketItemActivateRequest;
+
+    fn read(tank_packet: &TankPacket) -> Self {
+        Self { item_id: tank_packet.value }
+    }
+
+    fn write(&self, tank_packet: &mut TankPacket) {
+        tank_packet.value = self.item_id;
+    }
+}
+
+/// `NetGamePacketItemActivateObjectRequest`, sent to pick up a dropped object.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ItemActivateObjectRequest {
+    pub net_id: u32,
+    pub object_uid: u32,
+    pub position: Vector2,
+}
+
+impl TankView for ItemActivateObjectRequest {
+    const TYPE: ETankPacketType = ETankPacketType::NetGamePacketItemActivateObjectRequest;
+
+    fn read(tank_packet: &TankPacket) -> Self {
+        Self {
+            net_id: tank_packet.net_id,
+            object_uid: tank_packet.value,
+            position: Vector2 { x: tank_packet.vector_x, y: tank_packet.vector_y },
+        }
+    }
+
+    fn write(&self, tank_packet: &mut TankPacket) {
+        tank_packet.net_id = self.net_id;
+        tank_packet.value = self.object_uid;
+        tank_packet.vector_x = self.position.x;
+        tank_packet.vector_y = self.position.y;
+    }
+}
+
+/// `NetGamePacketSendTileTreeState`, sent when a tree is harvested.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SendTileTreeState {
+    pub net_id: u32,
+    /// Item id left on the tile, usually 0.
+    pub item_id: u32,
+    pub tile_x: i32,
+    pub tile_y: i32,
+}
+
+impl TankView for SendTileTreeState {
+    const TYPE: ETankPacketType = ETankPacketType::NetGamePacketSendTileTreeState;
+
+    fn read(tank_packet: &TankPacket) -> Self {
+        Self {
+            net_id: tank_packet.net_id,
+            item_id: tank_packet.value,
+            tile_x: tank_packet.int_x,
+            tile_y: tank_packet.int_y,
+        }
+    }
+
+    fn write(&self, tank_packet: &mut TankPacket) {
+        tank_packet.net_id = self.net_id;
+        tank_packet.value = self.item_id;
+        tank_packet.int_x = self.tile_x;
+        tank_packet.int_y = self.tile_y;
+    }
+}
+
+/// `NetGamePacketModifyItemInventory`, sent by the server when an item is taken from the inventory.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ModifyItemInventory {
+    pub item_id: u32,
+    pub removed: u8,
+    pub added: u8,
+}
+
+impl TankView for ModifyItemInventory {
+    const TYPE: ETankPacketType = ETankPacketType::NetGamePacketModifyItemInventory;
+
+    fn read(tank_packet: &TankPacket) -> Self {
+        Self {
+            item_id: tank_packet.value,
+            removed: tank_packet.unk2,
+            added: tank_packet.unk3,
+        }
+    }
+
+    fn write(&self, tank_packet: &mut TankPacket) {
+        tank_packet.value = self.item_id;
+        tank_packet.unk2 = self.removed;
+        tank_packet.unk3 = self.added;
+    }
+}
+
+/// `NetGamePacketItemChangeObject`, sent when a dropped object appears, changes or is collected.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ItemChangeObject {
+    /// [`ItemChangeObject::NEW_OBJECT`], [`ItemChangeObject::UPDATE_OBJECT`] or the net id of the collecting player.
+    pub net_id: u32,
+    /// Item id for new objects, the object's uid otherwise.
+    pub value: u32,
+    pub position: Vector2,
+    pub amount: f32,
+    pub object_flags: u8,
+}
+
+impl ItemChangeObject {
+    pub const NEW_OBJECT: u32 = u32::MAX;
+    pub const UPDATE_OBJECT: u32 = u32::MAX - 2;
+
+    pub fn is_new_object(&self) -> bool {
+        self.net_id == Self::NEW_OBJECT
+    }
+
+    pub fn is_update(&self) -> bool {
+        self.net_id == Self::UPDATE_OBJECT
+    }
+
+    pub fn is_collect(&self) -> bool {
+        !self.is_new_object() && !self.is_update()
+    }
+}
+
+impl TankView for ItemChangeObject {
+    const TYPE: ETankPacketType = ETankPacketType::NetGamePacketItemChangeObject;
+
+    fn read(tank_packet: &TankPacket) -> Self {
+        Self {
+            net_id: tank_packet.net_id,
+            value: tank_packet.value,
+            position: Vector2 { x: tank_packet.vector_x, y: tank_packet.vector_y },
+            amount: tank_packet.unk6,
+            object_flags: tank_packet.unk1,
+        }
+    }
+
+    fn write(&self, tank_packet: &mut TankPacket) {
+        tank_packet.net_id = self.net_id;
+        tank_packet.value = self.value;
+        tank_packet.vector_x = self.position.x;
+        tank_packet.vector_y = self.position.y;
+        tank_packet.unk6 = self.amount;
+        tank_packet.unk1 = self.object_flags;
+    }
+}
+
+/// `NetGamePacketSendLock`, sent when a lock is placed. The extended data lists the locked tiles.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SendLock {
+    pub owner_user_id: u32,
+    pub lock_item_id: u32,
+    pub tile_x: i32,
+    pub tile_y: i32,
+}
+
+impl TankView for SendLock {
+    const TYPE: ETankPacketType = ETankPacketType::NetGamePacketSendLock;
+
+    fn read(tank_packet: &TankPacket) -> Self {
+        Self {
+            owner_user_id: tank_packet.net_id,
+            lock_item_id: tank_packet.value,
+            tile_x: tank_packet.int_x,
+            tile_y: tank_packet.int_y,
+        }
+    }
+
+    fn write(&self, tank_packet: &mut TankPacket) {
+        tank_packet.net_id = self.owner_user_id;
+        tank_packet.value = self.lock_item_id;
+        tank_packet.int_x = self.tile_x;
+        tank_packet.int_y = self.tile_y;
+    }
+}
+
+/// `NetGamePacketSetCharacterState`, sent by the server to set a player's movement abilities.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SetCharacterState {
+    pub net_id: u32,
+    pub flags: PacketFlag,
+    pub hack_type: u8,
+    /// Extra build range. Stored on the wire as `range + 128`.
+    pub build_range: i8,
+    /// Extra punch range. Stored on the wire as `range + 128`.
+    pub punch_range: i8,
+    pub water_speed: f32,
+    pub punch_effect: u32,
+    pub acceleration: f32,
+    pub speed: f32,
+    pub gravity: f32,
+}
+
+impl TankView for SetCharacterState {
+    const TYPE: ETankPacketType = ETankPacketType::NetGamePacketSetCharacterState;
+
+    fn read(tank_packet: &TankPacket) -> Self {
+        Self {
+            net_id: tank_packet.net_id,
+            flags: tank_packet.flags,
+            hack_type: tank_packet.unk1,
+            build_range: tank_packet.unk2.wrapping_sub(128) as i8,
+            punch_range: tank_packet.unk3.wrapping_sub(128) as i8,
+            water_speed: tank_packet.unk6,
+            punch_effect: tank_packet.value,
+            acceleration: tank_packet.vector_x2,
+            speed: tank_packet.vector_y2,
+            gravity: tank_packet.unk12,
+        }
+    }
+
+    fn write(&self, tank_packet: &mut TankPacket) {
+        tank_packet.net_id = self.net_id;
+        tank_packet.flags = self.flags;
+        tank_packet.unk1 = self.hack_type;
+        tank_packet.unk2 = (self.build_range as u8).wrapping_add(128);
+        tank_packet.unk3 = (self.punch_range as u8).wrapping_add(128);
+        tank_packet.unk6 = self.water_speed;
+        tank_packet.value = self.punch_effect;
+        tank_packet.vector_x2 = self.acceleration;
+        tank_packet.vector_y2 = self.speed;
+        tank_packet.unk12 = self.gravity;
+    }
+}
+
+/// `NetGamePacketPingRequest`, sent by the server to check the client is alive.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PingRequest {
+    /// Server tick in milliseconds, echoed back in [`PingReply::time`].
+    pub time: u32,
+}
+
+impl TankView for PingRequest {
+    const TYPE: ETankPacketType = ETankPacketType::NetGamePacketPingRequest;
+
+    fn read(tank_packet: &TankPacket) -> Self {
+        Self { time: tank_packet.value }
+    }
+
+    fn write(&self, tank_packet: &mut TankPacket) {
+        tank_packet.value = self.time;
+    }
+}
+
+/// `NetGamePacketPingReply`, the client's answer to a [`PingRequest`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PingReply {
+    /// Hash the server uses to check the reply wasn't forged.
+    pub hash: u32,
+    pub time: u32,
+    pub build_range: f32,
+    pub punch_range: f32,
+    pub speed: f32,
+    pub gravity: f32,
+}
+
+impl TankView for PingReply {
+    const TYPE: ETankPacketType = ETankPacketType::NetGamePacketPingReply;
+
+    fn read(tank_packet: &TankPacket) -> Self {
+        Self {
+            hash: tank_packet.net_id,
+            time: tank_packet.value,
+            build_range: tank_packet.vector_x,
+            punch_range: tank_packet.vector_y,
+            speed: tank_packet.vector_x2,
+            gravity: tank_packet.vector_y2,
+        }
+    }
+
+    fn write(&self, tank_packet: &mut TankPacket) {
+        tank_packet.net_id = self.hash;
+        tank_packet.value = self.time;
+        tank_packet.vector_x = self.build_range;
+        tank_packet.vector_y = self.punch_range;
+        tank_packet.vector_x2 = self.speed;
+        tank_packet.vector_y2 = self.gravity;
+    }
+}
+
+/// `NetGamePacketGotPunched`, sent when a player is punched by another.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GotPunched {
+    pub net_id: u32,
+    pub punched_by: u32,
+    pub velocity: Vector2,
+}
+
+impl TankView for GotPunched {
+    const TYPE: ETankPacketType = ETankPacketType::NetGamePacketGotPunched;
+
+    fn read(tank_packet: &TankPacket) -> Self {
+        Self {
+            net_id: tank_packet.net_id,
+            punched_by: tank_packet.sec_id,
+            velocity: Vector2 { x: tank_packet.vector_x2, y: tank_packet.vector_y2 },
+        }
+    }
+
+    fn write(&self, tank_packet: &mut TankPacket) {
+        tank_packet.net_id = self.net_id;
+        tank_packet.sec_id = self.punched_by;
+        tank_packet.vector_x2 = self.velocity.x;
+        tank_packet.vector_y2 = self.velocity.y;
+    }
+}
@@ -0,0 +1,314 @@
+//! The world layout sent in `NetGamePacketSendMapData` and the tiles of tile update packets.
+//!
+//! | field | type |
+//! |---|---|
+//! | version | u16 |
+//! | flags | u32 |
+//! | name | u16 length + bytes |
+//! | width, height, tile count | u32 |
+//! | unknown | 5 bytes |
+//! | tiles | see [`Tile::decode`] |
+//! | unknown | 12 bytes |
+//! | dropped item count, last dropped uid | u32 |
+//! | dropped items | see [`DroppedItem`] |
+//!
+//! Everything is little-endian. The weather and other data after the dropped items are ignored.
+
+use std::fmt;
+use std::io::{self, Cursor};
+use bitflags::bitflags;
+use byteorder::{LittleEndian, ReadBytesExt};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::utils::cursor::{read_string, skip};
+
+bitflags! {
+    /// Flags of a tile. Bits without a name are kept as-is.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct TileFlags: u16 {
+        const HAS_EXTRA_DATA = 0x1;
+        /// The tile is inside a lock's area; `lock_index` follows the flags.
+        const LOCKED = 0x2;
+        const SPLICED = 0x4;
+        const WILL_SPAWN_SEEDS = 0x8;
+        const SEEDLING = 0x10;
+        const FLIPPED_X = 0x20;
+        const ON = 0x40;
+        const PUBLIC = 0x80;
+        const BACKGROUND_ON = 0x100;
+        const ALT_MODE = 0x200;
+        const WET = 0x400;
+        const GLUED = 0x800;
+        const ON_FIRE = 0x1000;
+        const PAINTED_RED = 0x2000;
+        const PAINTED_GREEN = 0x4000;
+        const PAINTED_BLUE = 0x8000;
+
+        const _ = !0;
+    }
+}
+
+impl Serialize for TileFlags {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(self.bits())
+    }
+}
+
+impl<'de> Deserialize<'de> for TileFlags {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(TileFlags::from_bits_retain(u16::deserialize(deserializer)?))
+    }
+}
+
+#[derive(Debug)]
+pub enum WorldDataError {
+    Io(io::Error),
+    /// A tile has extra data of a type whose size isn't known, so nothing after it can be read.
+    UnknownExtra { kind: u8 },
+    /// The tile count doesn't match the width and height.
+    Dimensions { width: u32, height: u32, tile_count: usize },
+}
+
+impl fmt::Display for WorldDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorldDataError::Io(err) => write!(f, "truncated world data: {}", err),
+            WorldDataError::UnknownExtra { kind } => write!(f, "unknown tile extra data type {}", kind),
+            WorldDataError::Dimensions { width, height, tile_count } => {
+                write!(f, "{} tiles in a {}x{} world", tile_count, width, height)
+            }
+        }
+    }
+}
+
+impl From<io::Error> for WorldDataError {
+    fn from(err: io::Error) -> Self {
+        WorldDataError::Io(err)
+    }
+}
+
+/// Extra data some tiles carry, such as a door's label or a lock's access list.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TileExtra {
+    Door { label: String, flags: u8 },
+    Sign { text: String },
+    Lock { settings: u8, owner_uid: u32, access_uids: Vec<u32>, minimum_level: u8 },
+    Seed { time_passed: u32, fruit_count: u8 },
+    DisplayBlock { item_id: u32 },
+    VendingMachine { item_id: u32, price: i32 },
+    /// A type whose fields aren't named here, with its raw bytes.
+    Other { kind: u8, data: Vec<u8> },
+}
+
+/// Field sizes of the extra data types without their own `TileExtra` variant.
+#[derive(Clone, Copy)]
+enum Field {
+    U8,
+    U16,
+    U32,
+    Str,
+    Bytes(u64),
+}
+
+fn layout(kind: u8) -> Option<&'static [Field]> {
+    use Field::*;
+    let layout: &[Field] = match kind {
+        6 | 7 | 12 => &[Str, Str, Str, U8],
+        8 | 16 => &[U8],
+        9 | 15 | 27 | 38 | 39 | 40 | 41 | 57 => &[U32],
+        10 => &[U32, U8],
+        11 => &[U32, Str],
+        17 | 34 | 45 | 51 | 52 | 58 | 59 => &[],
+        18 => &[U8, U32],
+        19 => &[Bytes(18)],
+        20 | 33 => &[Str],
+        21 => &[Str, U32, U8],
+        28 => &[U16, U32],
+        30 => &[U8, U32],
+        35 => &[U32, Str],
+        42 => &[Bytes(21)],
+        43 => &[Bytes(16)],
+        53 => &[U32, U32],
+        _ => return None,
+    };
+    Some(layout)
+}
+
+/// Item id of the guild lock, whose lock data has 16 more bytes.
+const GUILD_LOCK_ITEM_ID: u16 = 5814;
+
+impl TileExtra {
+    fn decode(cursor: &mut Cursor<&[u8]>, foreground: u16) -> Result<Self, WorldDataError> {
+        let kind = cursor.read_u8()?;
+        let extra = match kind {
+            1 => TileExtra::Door { label: read_string(cursor)?, flags: cursor.read_u8()? },
+            2 => {
+                let text = read_string(cursor)?;
+                cursor.read_u32::<LittleEndian>()?;
+                TileExtra::Sign { text }
+            }
+            3 => {
+                let settings = cursor.read_u8()?;
+                let owner_uid = cursor.read_u32::<LittleEndian>()?;
+                let access_count = cursor.read_u32::<LittleEndian>()?;
+                let access_uids = (0..access_count).map(|_| cursor.read_u32::<LittleEndian>()).collect::<io::Result<_>>()?;
+                let minimum_level = cursor.read_u8()?;
+                skip(cursor, if foreground == GUILD_LOCK_ITEM_ID { 23 } else { 7 })?;
+                TileExtra::Lock { settings, owner_uid, access_uids, minimum_level }
+            }
+            4 => TileExtra::Seed { time_passed: cursor.read_u32::<LittleEndian>()?, fruit_count: cursor.read_u8()? },
+            23 => TileExtra::DisplayBlock { item_id: cursor.read_u32::<LittleEndian>()? },
+            24 => TileExtra::VendingMachine { item_id: cursor.read_u32::<LittleEndian>()?, price: cursor.read_i32::<LittleEndian>()? },
+            kind => {
+                let layout = layout(kind).ok_or(WorldDataError::UnknownExtra { kind })?;
+                let start = cursor.position() as usize;
+                for field in layout {
+                    match field {
+                        Field::U8 => skip(cursor, 1)?,
+                        Field::U16 => skip(cursor, 2)?,
+                        Field::U32 => skip(cursor, 4)?,
+                        Field::Str => {
+                            let len = cursor.read_u16::<LittleEndian>()?;
+                            skip(cursor, len as u64)?;
+                        }
+                        Field::Bytes(len) => skip(cursor, *len)?,
+                    }
+                }
+                let data = cursor.get_ref()[start..cursor.position() as usize].to_vec();
+                TileExtra::Other { kind, data }
+            }
+        };
+        Ok(extra)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Tile {
+    pub foreground: u16,
+    pub background: u16,
+    pub parent: u16,
+    pub flags: TileFlags,
+    /// Index of the lock tile whose area covers this tile, when `LOCKED` is set.
+    pub lock_index: Option<u16>,
+    pub extra: Option<TileExtra>,
+}
+
+impl Tile {
+    /// Reads one tile: foreground, background, parent and flags (u16 each), then the lock index
+    /// (u16) when `LOCKED` is set and the extra data when `HAS_EXTRA_DATA` is set.
+    pub fn decode(cursor: &mut Cursor<&[u8]>) -> Result<Self, WorldDataError> {
+        let foreground = cursor.read_u16::<LittleEndian>()?;
+        let background = cursor.read_u16::<LittleEndian>()?;
+        let parent = cursor.read_u16::<LittleEndian>()?;
+        let flags = TileFlags::from_bits_retain(cursor.read_u16::<LittleEndian>()?);
+        let lock_index = match flags.contains(TileFlags::LOCKED) {
+            true => Some(cursor.read_u16::<LittleEndian>()?),
+            false => None,
+        };
+        let extra = match flags.contains(TileFlags::HAS_EXTRA_DATA) {
+            true => Some(TileExtra::decode(cursor, foreground)?),
+            false => None,
+        };
+        Ok(Self { foreground, background, parent, flags, lock_index, extra })
+    }
+}
+
+/// An item lying in the world. 16 bytes: id (u16), x and y in pixels (f32), amount (u8), flags
+/// (u8) and uid (u32).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DroppedItem {
+    pub uid: u32,
+    pub item_id: u16,
+    pub x: f32,
+    pub y: f32,
+    pub amount: u8,
+    pub flags: u8,
+}
+
+impl DroppedItem {
+    fn decode(cursor: &mut Cursor<&[u8]>) -> io::Result<Self> {
+        let item_id = cursor.read_u16::<LittleEndian>()?;
+        let x = cursor.read_f32::<LittleEndian>()?;
+        let y = cursor.read_f32::<LittleEndian>()?;
+        let amount = cursor.read_u8()?;
+        let flags = cursor.read_u8()?;
+        let uid = cursor.read_u32::<LittleEndian>()?;
+        Ok(Self { uid, item_id, x, y, amount, flags })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorldData {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// Row by row from the top left.
+    pub tiles: Vec<Tile>,
+    pub dropped: Vec<DroppedItem>,
+    pub last_dropped_uid: u32,
+    /// Index of the first tile that couldn't be read. It and the tiles after it are left empty,
+    /// and the dropped items are unknown.
+    pub unparsed_from: Option<usize>,
+}
+
+impl WorldData {
+    /// Decodes the extended data of a `SendMapData` packet. Fails only when the header is broken;
+    /// a tile that can't be read sets `unparsed_from` instead.
+    pub fn decode(data: &[u8]) -> Result<Self, WorldDataError> {
+        let mut cursor = Cursor::new(data);
+        cursor.read_u16::<LittleEndian>()?;
+        cursor.read_u32::<LittleEndian>()?;
+        let name = read_string(&mut cursor)?;
+        let width = cursor.read_u32::<LittleEndian>()?;
+        let height = cursor.read_u32::<LittleEndian>()?;
+        let tile_count = cursor.read_u32::<LittleEndian>()? as usize;
+        skip(&mut cursor, 5)?;
+
+        if width as u64 * height as u64 != tile_count as u64 {
+            return Err(WorldDataError::Dimensions { width, height, tile_count });
+        }
+        // Each tile is at least 8 bytes, so a larger count is a broken header.
+        if tile_count > data.len() / 8 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        let mut world = Self { name, width, height, tiles: vec![Tile::default(); tile_count], ..Default::default() };
+        for index in 0..tile_count {
+            match Tile::decode(&mut cursor) {
+                Ok(tile) => world.tiles[index] = tile,
+                Err(_) => {
+                    world.unparsed_from = Some(index);
+                    return Ok(world);
+                }
+            }
+        }
+
+        let dropped = (|| -> io::Result<(u32, Vec<DroppedItem>)> {
+            skip(&mut cursor, 12)?;
+            let count = cursor.read_u32::<LittleEndian>()?;
+            let last_uid = cursor.read_u32::<LittleEndian>()?;
+            let items = (0..count).map(|_| DroppedItem::decode(&mut cursor)).collect::<io::Result<_>>()?;
+            Ok((last_uid, items))
+        })();
+        if let Ok((last_uid, items)) = dropped {
+            world.last_dropped_uid = last_uid;
+            world.dropped = items;
+        }
+        Ok(world)
+    }
+}
+
+/// Decodes the extended data of a `SendTileUpdateDataMultiple` packet: x and y (i32) followed by
+/// a tile, repeated until the data ends or x is -1.
+pub fn decode_tile_updates(data: &[u8]) -> Result<Vec<(i32, i32, Tile)>, WorldDataError> {
+    let mut cursor = Cursor::new(data);
+    let mut updates = Vec::new();
+    while (cursor.position() as usize) < data.len() {
+        let x = cursor.read_i32::<LittleEndian>()?;
+        if x == -1 {
+            break;
+        }
+        let y = cursor.read_i32::<LittleEndian>()?;
+        updates.push((x, y, Tile::decode(&mut cursor)?));
+    }
+    Ok(updates)
+}
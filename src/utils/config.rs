@@ -1,4 +1,6 @@
+use std::io;
 use std::path::Path;
+use std::sync::Mutex;
 use crate::types;
 
 pub fn init() {
@@ -7,6 +9,17 @@ pub fn init() {
         let config = types::config::Config {
             web_server_port: 443,
             enet_server_port: 17111,
+            reconnect: types::config::ReconnectPolicy::default(),
+            ping: types::config::PingConfig::default(),
+            headless: types::config::HeadlessConfig::default(),
+            logging: types::config::LoggingConfig::default(),
+            hooks: types::config::Hooks::default(),
+            rules: Vec::new(),
+            plugins: types::config::PluginConfig::default(),
+            snapshots: types::config::SnapshotConfig::default(),
+            capture: types::config::CaptureConfig::default(),
+            dashboard: types::config::DashboardConfig::default(),
+            items_dat: "items.dat".to_string(),
         };
         let config_json = serde_json::to_string_pretty(&config).unwrap();
         std::fs::write("config.json", config_json).expect("Failed to write config file");
@@ -18,6 +31,13 @@ fn get_config() -> types::config::Config {
     serde_json::from_str(&config_json).expect("Failed to parse config file")
 }
 
+/// Reads `config.json`, failing instead of panicking when it is missing, broken or half-written.
+/// For reloads while the proxy runs.
+pub fn read() -> io::Result<types::config::Config> {
+    let config_json = std::fs::read_to_string("config.json")?;
+    Ok(serde_json::from_str(&config_json)?)
+}
+
 pub fn get_web_server_port() -> u16 {
     get_config().web_server_port
 }
@@ -26,16 +46,74 @@ pub fn get_enet_server_port() -> u16 {
     get_config().enet_server_port
 }
 
-pub fn set_web_server_port(port: u16) {
+pub fn get_reconnect_policy() -> types::config::ReconnectPolicy {
+    get_config().reconnect
+}
+
+pub fn get_ping_config() -> types::config::PingConfig {
+    get_config().ping
+}
+
+pub fn get_headless_config() -> types::config::HeadlessConfig {
+    get_config().headless
+}
+
+pub fn get_logging_config() -> types::config::LoggingConfig {
+    get_config().logging
+}
+
+pub fn get_hooks() -> types::config::Hooks {
+    get_config().hooks
+}
+
+pub fn get_rules() -> Vec<types::config::Rule> {
+    get_config().rules
+}
+
+pub fn get_plugin_config() -> types::config::PluginConfig {
+    get_config().plugins
+}
+
+pub fn get_snapshot_config() -> types::config::SnapshotConfig {
+    get_config().snapshots
+}
+
+pub fn get_capture_config() -> types::config::CaptureConfig {
+    get_config().capture
+}
+
+pub fn get_dashboard_config() -> types::config::DashboardConfig {
+    get_config().dashboard
+}
+
+pub fn get_items_dat() -> String {
+    get_config().items_dat
+}
+
+/// Changes `config.json` through `f`. Writers take turns, so concurrent changes to different
+/// sections don't undo each other, and the file is replaced whole so readers never see half of it.
+pub fn update(f: impl FnOnce(&mut types::config::Config)) {
+    static WRITE: Mutex<()> = Mutex::new(());
+    let _write = WRITE.lock().unwrap_or_else(|e| e.into_inner());
     let mut config = get_config();
-    config.web_server_port = port;
+    f(&mut config);
     let config_json = serde_json::to_string_pretty(&config).unwrap();
-    std::fs::write("config.json", config_json).expect("Failed to write config file");
+    std::fs::write("config.json.tmp", config_json).expect("Failed to write config file");
+    std::fs::rename("config.json.tmp", "config.json").expect("Failed to write config file");
+}
+
+pub fn set_hooks(hooks: types::config::Hooks) {
+    update(|config| config.hooks = hooks);
+}
+
+pub fn set_rules(rules: Vec<types::config::Rule>) {
+    update(|config| config.rules = rules);
+}
+
+pub fn set_web_server_port(port: u16) {
+    update(|config| config.web_server_port = port);
 }
 
 pub fn set_enet_server_port(port: u16) {
-    let mut config = get_config();
-    config.enet_server_port = port;
-    let config_json = serde_json::to_string_pretty(&config).unwrap();
-    std::fs::write("config.json", config_json).expect("Failed to write config file");
-}
\ No newline at end of file
+    update(|config| config.enet_server_port = port);
+}
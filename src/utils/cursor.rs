@@ -0,0 +1,27 @@
+//! Reading the little-endian binary formats of the game: `items.dat`, map data and tile updates.
+
+use std::io::{self, Cursor, Read};
+use byteorder::{LittleEndian, ReadBytesExt};
+
+/// Reads a u16 length followed by that many bytes.
+pub fn read_bytes(cursor: &mut Cursor<&[u8]>) -> io::Result<Vec<u8>> {
+    let len = cursor.read_u16::<LittleEndian>()?;
+    let mut bytes = vec![0; len as usize];
+    cursor.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Reads a u16 length followed by that many bytes of text. Invalid UTF-8 is replaced.
+pub fn read_string(cursor: &mut Cursor<&[u8]>) -> io::Result<String> {
+    Ok(String::from_utf8_lossy(&read_bytes(cursor)?).into_owned())
+}
+
+/// Moves past `len` bytes, failing when there are fewer left.
+pub fn skip(cursor: &mut Cursor<&[u8]>, len: u64) -> io::Result<()> {
+    let position = cursor.position() + len;
+    if position > cursor.get_ref().len() as u64 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    cursor.set_position(position);
+    Ok(())
+}
@@ -1,3 +1,5 @@
 pub mod text_parse;
 pub mod variant;
-pub mod config;
\ No newline at end of file
+pub mod config;
+pub mod watch;
+pub mod cursor;
\ No newline at end of file
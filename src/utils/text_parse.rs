@@ -23,4 +23,55 @@ pub fn map_to_string(map: &HashMap<String, String>) -> String {
 
 pub fn vec_to_string(vec: &Vec<String>) -> String {
     vec.join("|")
-}
\ No newline at end of file
+}
+
+/// Removes the `` `x `` colour codes from game text.
+pub fn strip_colors(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        if c == '`' {
+            chars.next();
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Sets `key|value` in `key|value` lines, keeping the order of the other lines and the leading
+/// `|` of client lines such as `|text|hi`. Appends the line when the key is missing.
+pub fn set_field(input: &str, key: &str, value: &str) -> String {
+    let mut found = false;
+    let mut lines: Vec<String> = input
+        .split('\n')
+        .map(|line| {
+            let field = line.trim_start_matches('|');
+            match field.split_once('|') {
+                Some((line_key, _)) if line_key == key && !found => {
+                    found = true;
+                    format!("{}{}|{}", &line[..line.len() - field.len()], key, value)
+                }
+                _ => line.to_string(),
+            }
+        })
+        .collect();
+    if !found {
+        let line = format!("{}|{}", key, value);
+        match lines.last() {
+            Some(last) if last.is_empty() => lines.insert(lines.len() - 1, line),
+            _ => lines.push(line),
+        }
+    }
+    lines.join("\n")
+}
+
+/// The value of a `key|value` line in a client text packet such as `action|input\n|text|hi`,
+/// where lines after the first may start with `|`.
+pub fn get_field<'a>(message: &'a str, key: &str) -> Option<&'a str> {
+    message
+        .lines()
+        .filter_map(|line| line.trim_start_matches('|').split_once('|'))
+        .find(|(line_key, _)| *line_key == key)
+        .map(|(_, value)| value.trim_end())
+}
@@ -1,4 +1,4 @@
-use byteorder::{ByteOrder, LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use serde::Deserialize;
 use serde::Serialize;
 use std::io::prelude::*;
@@ -6,39 +6,46 @@ use std::io::Cursor;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum VariantType {
-    Unknown,
+    Unknown(u8),
     Float,
     String,
     Vec2,
     Vec3,
     Unsigned,
+    Vec4,
     Signed,
 }
 
 impl From<u8> for VariantType {
     fn from(value: u8) -> Self {
         match value {
-            0 => VariantType::Unknown,
             1 => VariantType::Float,
             2 => VariantType::String,
             3 => VariantType::Vec2,
             4 => VariantType::Vec3,
             5 => VariantType::Unsigned,
+            8 => VariantType::Vec4,
             9 => VariantType::Signed,
-            _ => VariantType::Unknown,
+            other => VariantType::Unknown(other),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Variant {
     Float(f32),
     String(String),
+    /// A string payload that is not valid UTF-8, kept as raw bytes.
+    Bytes(Vec<u8>),
     Vec2((f32, f32)),
     Vec3((f32, f32, f32)),
+    /// Proton's rect type, also used for colours.
+    Vec4((f32, f32, f32, f32)),
     Unsigned(u32),
     Signed(i32),
-    Unknown,
+    /// A type we can't decode. The payload size of an unknown type can't be known,
+    /// so `data` holds every byte after the type tag up to the end of the list.
+    Unknown { kind: u8, data: Vec<u8> },
 }
 
 impl Variant {
@@ -46,11 +53,13 @@ impl Variant {
         match self {
             Variant::Float(value) => value.to_string(),
             Variant::String(value) => value.clone(),
+            Variant::Bytes(value) => String::from_utf8_lossy(value).into_owned(),
             Variant::Vec2((x, y)) => format!("{}, {}", x, y),
             Variant::Vec3((x, y, z)) => format!("{}, {}, {}", x, y, z),
+            Variant::Vec4((x, y, z, w)) => format!("{}, {}, {}, {}", x, y, z, w),
             Variant::Unsigned(value) => value.to_string(),
             Variant::Signed(value) => value.to_string(),
-            Variant::Unknown => "Unknown".to_string(),
+            Variant::Unknown { .. } => "Unknown".to_string(),
         }
     }
 
@@ -68,27 +77,58 @@ impl Variant {
         }
     }
 
+    pub fn as_vec4(&self) -> (f32, f32, f32, f32) {
+        match self {
+            Variant::Vec4(value) => *value,
+            _ => (0.0, 0.0, 0.0, 0.0),
+        }
+    }
+
     pub fn as_uint32(&self) -> u32 {
         match self {
             Variant::Unsigned(value) => *value,
             _ => 0,
         }
     }
+
+    fn type_id(&self) -> u8 {
+        match self {
+            Variant::Float(_) => 1,
+            Variant::String(_) | Variant::Bytes(_) => 2,
+            Variant::Vec2(_) => 3,
+            Variant::Vec3(_) => 4,
+            Variant::Unsigned(_) => 5,
+            Variant::Vec4(_) => 8,
+            Variant::Signed(_) => 9,
+            Variant::Unknown { kind, .. } => *kind,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct VariantList {
     variants: Vec<Variant>,
+    /// The index byte sent with each variant, in the same order as `variants`.
+    indices: Vec<u8>,
+    /// Entries counted by the header but swallowed by a trailing `Variant::Unknown`.
+    unparsed: u8,
+    /// Bytes left over after the last declared variant.
+    trailing: Vec<u8>,
 }
 
 impl VariantList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
     pub fn deserialize(data: &[u8]) -> Result<Self, std::io::Error> {
         let mut cursor = Cursor::new(data);
         let size = cursor.read_u8()?;
         let mut variants = Vec::with_capacity(size as usize);
+        let mut indices = Vec::with_capacity(size as usize);
 
-        for _ in 0..size {
-            let _index = cursor.read_u8()?;
+        for parsed in 0..size {
+            let index = cursor.read_u8()?;
             let var_type: VariantType = cursor.read_u8()?.into();
 
             let variant = match var_type {
@@ -98,10 +138,15 @@ impl VariantList {
                 }
                 VariantType::String => {
                     let len = cursor.read_u32::<LittleEndian>()? as usize;
+                    if len > data.len() - cursor.position() as usize {
+                        return Err(std::io::ErrorKind::UnexpectedEof.into());
+                    }
                     let mut buffer = vec![0; len];
                     cursor.read_exact(&mut buffer)?;
-                    let value = String::from_utf8(buffer).unwrap();
-                    Variant::String(value)
+                    match String::from_utf8(buffer) {
+                        Ok(value) => Variant::String(value),
+                        Err(err) => Variant::Bytes(err.into_bytes()),
+                    }
                 }
                 VariantType::Vec2 => {
                     let x = cursor.read_f32::<LittleEndian>()?;
@@ -114,6 +159,13 @@ impl VariantList {
                     let z = cursor.read_f32::<LittleEndian>()?;
                     Variant::Vec3((x, y, z))
                 }
+                VariantType::Vec4 => {
+                    let x = cursor.read_f32::<LittleEndian>()?;
+                    let y = cursor.read_f32::<LittleEndian>()?;
+                    let z = cursor.read_f32::<LittleEndian>()?;
+                    let w = cursor.read_f32::<LittleEndian>()?;
+                    Variant::Vec4((x, y, z, w))
+                }
                 VariantType::Unsigned => {
                     let value = cursor.read_u32::<LittleEndian>()?;
                     Variant::Unsigned(value)
@@ -122,101 +174,83 @@ impl VariantList {
                     let value = cursor.read_i32::<LittleEndian>()?;
                     Variant::Signed(value)
                 }
-                VariantType::Unknown => Variant::Unknown,
+                VariantType::Unknown(kind) => {
+                    let mut rest = Vec::new();
+                    cursor.read_to_end(&mut rest)?;
+                    variants.push(Variant::Unknown { kind, data: rest });
+                    indices.push(index);
+                    return Ok(Self {
+                        variants,
+                        indices,
+                        unparsed: size - parsed - 1,
+                        trailing: Vec::new(),
+                    });
+                }
             };
 
             variants.push(variant);
+            indices.push(index);
         }
 
-        Ok(Self { variants })
+        let mut trailing = Vec::new();
+        cursor.read_to_end(&mut trailing)?;
+
+        Ok(Self {
+            variants,
+            indices,
+            unparsed: 0,
+            trailing,
+        })
     }
 
     pub fn serialize(&self) -> Vec<u8> {
-        let mut mem_needed = 0;
-        let mut vars_used = 0;
-
-        for variant in &self.variants {
-            let var_size = match variant {
-                Variant::String(s) => s.len() + 4,
-                Variant::Float(_) => 4,
-                Variant::Vec2(_) => 8,
-                Variant::Vec3(_) => 12,
-                Variant::Unsigned(_) => 4,
-                Variant::Signed(_) => 4,
-                Variant::Unknown => 0,
-            };
-            if var_size > 0 {
-                vars_used += 1;
-                mem_needed += var_size;
-            }
-        }
+        let mut data = Vec::new();
+        data.push((self.variants.len() + self.unparsed as usize) as u8);
 
-        let total = mem_needed + 1 + (vars_used * 2);
-        let mut data = vec![0u8; total];
-        let mut p = &mut data[..];
-
-        p[0] = vars_used as u8;
-        p = &mut p[1..];
-
-        for (idx, variant) in self.variants.iter().enumerate() {
-            let var_size = match variant {
-                Variant::String(s) => s.len() + 4,
-                Variant::Float(_) => 4,
-                Variant::Vec2(_) => 8,
-                Variant::Vec3(_) => 12,
-                Variant::Unsigned(_) => 4,
-                Variant::Signed(_) => 4,
-                Variant::Unknown => 0,
-            };
-            if var_size > 0 {
-                p[0] = idx as u8;
-                p[1] = match variant {
-                    Variant::Float(_) => 1,
-                    Variant::String(_) => 2,
-                    Variant::Vec2(_) => 3,
-                    Variant::Vec3(_) => 4,
-                    Variant::Unsigned(_) => 5,
-                    Variant::Signed(_) => 9,
-                    Variant::Unknown => 0,
-                };
-                p = &mut p[2..];
-
-                match variant {
-                    Variant::Float(value) => {
-                        LittleEndian::write_f32(&mut p[..4], *value);
-                        p = &mut p[4..];
-                    }
-                    Variant::String(value) => {
-                        let len = value.len() as u32;
-                        LittleEndian::write_u32(&mut p[..4], len);
-                        p = &mut p[4..];
-                        p[..len as usize].copy_from_slice(value.as_bytes());
-                        p = &mut p[len as usize..];
-                    }
-                    Variant::Vec2((x, y)) => {
-                        LittleEndian::write_f32(&mut p[..4], *x);
-                        LittleEndian::write_f32(&mut p[4..8], *y);
-                        p = &mut p[8..];
-                    }
-                    Variant::Vec3((x, y, z)) => {
-                        LittleEndian::write_f32(&mut p[..4], *x);
-                        LittleEndian::write_f32(&mut p[4..8], *y);
-                        LittleEndian::write_f32(&mut p[8..12], *z);
-                        p = &mut p[12..];
-                    }
-                    Variant::Unsigned(value) => {
-                        LittleEndian::write_u32(&mut p[..4], *value);
-                        p = &mut p[4..];
-                    }
-                    Variant::Signed(value) => {
-                        LittleEndian::write_i32(&mut p[..4], *value);
-                        p = &mut p[4..];
-                    }
-                    Variant::Unknown => {}
+        for (index, variant) in self.indices.iter().zip(&self.variants) {
+            data.push(*index);
+            data.push(variant.type_id());
+
+            match variant {
+                Variant::Float(value) => {
+                    data.write_f32::<LittleEndian>(*value).unwrap();
+                }
+                Variant::String(value) => {
+                    data.write_u32::<LittleEndian>(value.len() as u32).unwrap();
+                    data.extend_from_slice(value.as_bytes());
+                }
+                Variant::Bytes(value) => {
+                    data.write_u32::<LittleEndian>(value.len() as u32).unwrap();
+                    data.extend_from_slice(value);
+                }
+                Variant::Vec2((x, y)) => {
+                    data.write_f32::<LittleEndian>(*x).unwrap();
+                    data.write_f32::<LittleEndian>(*y).unwrap();
+                }
+                Variant::Vec3((x, y, z)) => {
+                    data.write_f32::<LittleEndian>(*x).unwrap();
+                    data.write_f32::<LittleEndian>(*y).unwrap();
+                    data.write_f32::<LittleEndian>(*z).unwrap();
+                }
+                Variant::Vec4((x, y, z, w)) => {
+                    data.write_f32::<LittleEndian>(*x).unwrap();
+                    data.write_f32::<LittleEndian>(*y).unwrap();
+                    data.write_f32::<LittleEndian>(*z).unwrap();
+                    data.write_f32::<LittleEndian>(*w).unwrap();
+                }
+                Variant::Unsigned(value) => {
+                    data.write_u32::<LittleEndian>(*value).unwrap();
+                }
+                Variant::Signed(value) => {
+                    data.write_i32::<LittleEndian>(*value).unwrap();
+                }
+                Variant::Unknown { data: raw, .. } => {
+                    data.extend_from_slice(raw);
                 }
             }
         }
 
+        data.extend_from_slice(&self.trailing);
         data
     }
 
@@ -227,4 +261,29 @@ impl VariantList {
     pub fn set(&mut self, index: usize, variant: Variant) {
         self.variants[index] = variant;
     }
-}
\ No newline at end of file
+
+    /// Appends `variant` with the index after the highest one in the list. Returns false,
+    /// leaving the list alone, when it ends in a `Variant::Unknown`: its raw data runs to the end
+    /// of the packet, so nothing can be written after it.
+    pub fn push(&mut self, variant: Variant) -> bool {
+        if self.unparsed > 0 || matches!(self.variants.last(), Some(Variant::Unknown { .. })) {
+            return false;
+        }
+        let index = self.indices.iter().max().map_or(0, |index| index.wrapping_add(1));
+        self.indices.push(index);
+        self.variants.push(variant);
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.variants.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.variants.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Variant> {
+        self.variants.iter()
+    }
+}
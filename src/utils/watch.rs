@@ -0,0 +1,66 @@
+//! Polls files on disk for changes, for the modules that hot-reload them.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often a [`Watch`] looks at the disk.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The files in `dir` ending in `.extension`, in file name order, with their modification times.
+pub fn files(dir: &Path, extension: &str) -> BTreeMap<PathBuf, SystemTime> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return BTreeMap::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|file_extension| file_extension == extension))
+        .filter_map(|path| {
+            let modified = std::fs::metadata(&path).and_then(|metadata| metadata.modified()).ok()?;
+            Some((path, modified))
+        })
+        .collect()
+}
+
+/// The modification times seen at the last check.
+#[derive(Debug, Default)]
+pub struct Watch {
+    modified: BTreeMap<PathBuf, SystemTime>,
+    checked: Option<Instant>,
+}
+
+impl Watch {
+    /// Whether the `.extension` files in `dir` were added, removed or modified since the last
+    /// change. Looks at most once per [`CHECK_INTERVAL`].
+    pub fn dir_changed(&mut self, dir: &Path, extension: &str) -> bool {
+        self.changed(|| Some(files(dir, extension)))
+    }
+
+    /// Whether `path` was modified since the last change. A missing file is not a change.
+    pub fn file_changed(&mut self, path: &Path) -> bool {
+        self.changed(|| {
+            let modified = std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()?;
+            Some(BTreeMap::from([(path.to_path_buf(), modified)]))
+        })
+    }
+
+    /// Reports the files as changed at the next check, for a reload that failed.
+    pub fn retry(&mut self) {
+        self.modified.clear();
+    }
+
+    fn changed(&mut self, scan: impl FnOnce() -> Option<BTreeMap<PathBuf, SystemTime>>) -> bool {
+        if self.checked.is_some_and(|checked| checked.elapsed() < CHECK_INTERVAL) {
+            return false;
+        }
+        self.checked = Some(Instant::now());
+        match scan() {
+            Some(modified) if modified != self.modified => {
+                self.modified = modified;
+                true
+            }
+            _ => false,
+        }
+    }
+}
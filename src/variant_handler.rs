@@ -1,27 +1,39 @@
-use std::net::SocketAddr;
-use log::info;
-use rusty_enet::Packet;
-use crate::{global, utils};
+use std::net::{IpAddr, SocketAddr};
+use log::{debug, info, warn};
+use crate::dialog::Dialog;
+use crate::session::{self, Redirect};
+use crate::utils;
 use crate::packet_handler::resend_packet;
 use crate::types::epacket_type::EPacketType;
+use crate::types::etank_packet_type::ETankPacketType;
+use crate::types::packet_flag::PacketFlag;
 use crate::types::tank_packet::TankPacket;
 use crate::utils::text_parse;
 use crate::utils::variant::{Variant, VariantList};
 
-pub fn handle(data: &[u8], tank_packet: &mut TankPacket, packet_id: &[u8]) -> Option<Packet> {
-    let mut variant = VariantList::deserialize(&data).unwrap();
-    let function_call: String = variant.get(0).unwrap().as_string();
-    info!("Received function call: {}", function_call);
+/// Returns the re-encoded message when the call was rewritten.
+pub fn handle(variant: &mut VariantList, tank_packet: &mut TankPacket, trailing: &[u8]) -> Option<Vec<u8>> {
+    let function_call: String = variant.get(0)?.as_string();
 
     match function_call.as_str() {
         "OnSendToServer" => {
-            let port = variant.get(1).unwrap().as_int32();
-            let server_data = variant.get(4).unwrap().as_string();
+            let port = variant.get(1)?.as_int32();
+            let server_data = variant.get(4)?.as_string();
             let mut parsed_server_data = text_parse::parse_and_store_as_vec(&server_data);
-            let ip = parsed_server_data.get(0).unwrap().to_string();
-            let mut global_server_data = global().server_data.lock().unwrap();
-            global_server_data.insert("server".to_string(), ip);
-            global_server_data.insert("port".to_string(), port.to_string());
+            let ip = match parsed_server_data.first()?.parse::<IpAddr>() {
+                Ok(ip) => ip,
+                Err(e) => {
+                    warn!("OnSendToServer has an invalid address {:?}: {}", parsed_server_data[0], e);
+                    return None;
+                }
+            };
+            session::redirect(Redirect {
+                address: SocketAddr::new(ip, port as u16),
+                token: variant.get(2).map(Variant::as_int32).unwrap_or_default(),
+                user: variant.get(3).map(Variant::as_int32).unwrap_or_default(),
+                door_id: parsed_server_data.get(1).cloned().unwrap_or_default(),
+                uuid: parsed_server_data.get(2).cloned().unwrap_or_default(),
+            });
 
             parsed_server_data[0] = "127.0.0.1".to_string();
             let enet_server_port = utils::config::get_enet_server_port();
@@ -29,21 +41,23 @@ pub fn handle(data: &[u8], tank_packet: &mut TankPacket, packet_id: &[u8]) -> Op
             variant.set(4, Variant::String(text_parse::vec_to_string(&parsed_server_data)));
 
             let serialized = variant.serialize();
-            let packet = recreate_variant(&serialized, tank_packet, packet_id);
+            let packet = recreate_variant(&serialized, tank_packet, trailing);
             Some(packet)
         }
         "OnConsoleMessage" => {
-            let message = variant.get(1).unwrap().as_string();
+            let message = variant.get(1)?.as_string();
             info!("Received console message: {}", message);
             None
         },
         "OnDialogRequest" => {
-            let message = variant.get(1).unwrap().as_string();
-            info!("Received dialog request: {}", message);
+            let message = variant.get(1)?.as_string();
+            let dialog = Dialog::parse(&message);
+            info!("Received dialog {} with {} elements", dialog.name().unwrap_or("(unnamed)"), dialog.elements.len());
+            debug!("Dialog request: {}", message);
             None
         },
-        "OnSpawn" => {
-            let message = variant.get(1).unwrap().as_string();
+        "OnSpawn" if utils::config::get_hooks().spawn_mod_state => {
+            let message = variant.get(1)?.as_string();
             let mut parsed_message = text_parse::parse_and_store_as_map(&message);
 
             if let Some(val) = parsed_message.get("type") {
@@ -53,7 +67,7 @@ pub fn handle(data: &[u8], tank_packet: &mut TankPacket, packet_id: &[u8]) -> Op
                     variant.set(1, Variant::String(new_message));
 
                     let serialized = variant.serialize();
-                    let packet = recreate_variant(&serialized, tank_packet, packet_id);
+                    let packet = recreate_variant(&serialized, tank_packet, trailing);
                     Some(packet)
                 } else {
                     None
@@ -68,14 +82,33 @@ pub fn handle(data: &[u8], tank_packet: &mut TankPacket, packet_id: &[u8]) -> Op
     }
 }
 
-pub fn recreate_variant(serialized: &Vec<u8>, tank_packet: &mut TankPacket, packet_id: &[u8]) -> Packet {
-    tank_packet.extended_data_length = serialized.len() as u32;
-    let serialized_tank_packet = bincode::serialize(&tank_packet).unwrap();
-    let mut data = vec![0; 4 + serialized_tank_packet.len() + serialized.len()];
-    data[0..4].copy_from_slice(&packet_id);
-    data[4..4 + serialized_tank_packet.len()].copy_from_slice(&serialized_tank_packet);
-    data[4 + serialized_tank_packet.len()..].copy_from_slice(&serialized);
-    *tank_packet = bincode::deserialize(&data[4..]).unwrap();
+pub fn recreate_variant(serialized: &[u8], tank_packet: &mut TankPacket, trailing: &[u8]) -> Vec<u8> {
+    tank_packet.to_message(serialized, trailing)
+}
+
+/// Builds an `OnConsoleMessage` call for the game client.
+pub fn console_message(message: &str) -> Vec<u8> {
+    let mut variant = VariantList::new();
+    variant.push(Variant::String("OnConsoleMessage".to_string()));
+    variant.push(Variant::String(message.to_string()));
+    call_function(&variant, u32::MAX)
+}
 
-    Packet::new(&*data, rusty_enet::PacketKind::Reliable)
-}
\ No newline at end of file
+/// Builds an `OnDialogRequest` call that shows `dialog` on the game client.
+pub fn dialog_request(dialog: &Dialog) -> Vec<u8> {
+    let mut variant = VariantList::new();
+    variant.push(Variant::String("OnDialogRequest".to_string()));
+    variant.push(Variant::String(dialog.to_string()));
+    call_function(&variant, u32::MAX)
+}
+
+/// Builds a `CallFunction` packet for `variant`. `net_id` is `u32::MAX` for calls not aimed at a player.
+pub fn call_function(variant: &VariantList, net_id: u32) -> Vec<u8> {
+    let mut tank_packet = TankPacket {
+        r#type: ETankPacketType::NetGamePacketCallFunction,
+        net_id,
+        flags: PacketFlag::EXTENDED,
+        ..Default::default()
+    };
+    tank_packet.to_message(&variant.serialize(), &[])
+}
@@ -0,0 +1,291 @@
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use log::warn;
+use crate::global;
+use crate::packet_handler::GamePacket;
+use crate::types::etank_packet_type::ETankPacketType;
+use crate::types::player::Player;
+use crate::types::tank_packet::{TankPacket, TANK_PACKET_SIZE};
+use crate::types::tank_view::{ItemChangeObject, SendLock, SendTileTreeState, TileChangeRequest};
+use crate::types::vector2::Vector2;
+use crate::types::world_data::{self, DroppedItem, Tile, TileFlags, WorldData};
+use crate::utils::text_parse;
+use crate::utils::variant::VariantList;
+
+/// The world the game client is in, as far as the proxy has seen.
+#[derive(Debug, Default)]
+pub struct World {
+    pub name: Option<String>,
+    /// Players by net id, including the local player.
+    pub players: BTreeMap<u32, Player>,
+    pub local_net_id: Option<u32>,
+    pub width: u32,
+    pub height: u32,
+    /// Row by row from the top left. Empty until the map data arrives.
+    pub tiles: Vec<Tile>,
+    pub dropped: Vec<DroppedItem>,
+    pub last_dropped_uid: u32,
+}
+
+impl World {
+    pub fn local_player(&self) -> Option<&Player> {
+        self.players.get(&self.local_net_id?)
+    }
+
+    /// Applies a `CallFunction` packet from the server.
+    pub fn apply_call(&mut self, variants: &VariantList) {
+        let Some(function) = variants.get(0).map(|function| function.as_string()) else {
+            return;
+        };
+        match function.as_str() {
+            "OnSpawn" => {
+                if let Some(message) = variants.get(1) {
+                    let player = parse_spawn(&message.as_string());
+                    if player.r#type == "local" {
+                        self.local_net_id = Some(player.net_id);
+                    }
+                    self.players.insert(player.net_id, player);
+                }
+            }
+            "OnRemove" => {
+                if let Some(message) = variants.get(1) {
+                    let parsed = text_parse::parse_and_store_as_map(&message.as_string());
+                    if let Some(net_id) = parsed.get("netID").and_then(|net_id| net_id.parse::<u32>().ok()) {
+                        self.players.remove(&net_id);
+                    }
+                }
+            }
+            "OnRequestWorldSelectMenu" => self.leave(),
+            _ => {}
+        }
+    }
+
+    /// Applies a tank packet from either side.
+    pub fn apply_tank(&mut self, tank_packet: &TankPacket) {
+        if tank_packet.r#type == ETankPacketType::NetGamePacketState {
+            if let Some(player) = self.players.get_mut(&tank_packet.net_id) {
+                player.position = Vector2 { x: tank_packet.vector_x, y: tank_packet.vector_y };
+            }
+        }
+    }
+
+    pub fn tile(&self, x: i32, y: i32) -> Option<&Tile> {
+        self.tiles.get(self.index(x, y)?)
+    }
+
+    pub fn tile_mut(&mut self, x: i32, y: i32) -> Option<&mut Tile> {
+        let index = self.index(x, y)?;
+        self.tiles.get_mut(index)
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        let (x, y) = (u32::try_from(x).ok()?, u32::try_from(y).ok()?);
+        (x < self.width && y < self.height).then(|| y as usize * self.width as usize + x as usize)
+    }
+
+    /// Replaces the tiles and dropped items with those of a world's map data.
+    pub fn load(&mut self, data: WorldData) {
+        self.name = Some(data.name);
+        self.width = data.width;
+        self.height = data.height;
+        self.tiles = data.tiles;
+        self.dropped = data.dropped;
+        self.last_dropped_uid = data.last_dropped_uid;
+    }
+
+    /// Applies a tile or dropped item update from the server. `extended_data` is the data after
+    /// the tank header.
+    pub fn apply_update(&mut self, tank_packet: &TankPacket, extended_data: &[u8]) {
+        match tank_packet.r#type {
+            ETankPacketType::NetGamePacketSendMapData => match WorldData::decode(extended_data) {
+                Ok(data) => {
+                    if let Some(index) = data.unparsed_from {
+                        warn!("World {} could only be read up to tile {} of {}", data.name, index, data.tiles.len());
+                    }
+                    self.load(data);
+                }
+                Err(e) => warn!("Failed to read the map data: {}", e),
+            },
+            ETankPacketType::NetGamePacketSendTileUpdateData => match Tile::decode(&mut Cursor::new(extended_data)) {
+                Ok(tile) => self.set_tile(tank_packet.int_x, tank_packet.int_y, tile),
+                Err(e) => warn!("Failed to read the tile update at {},{}: {}", tank_packet.int_x, tank_packet.int_y, e),
+            },
+            ETankPacketType::NetGamePacketSendTileUpdateDataMultiple => match world_data::decode_tile_updates(extended_data) {
+                Ok(updates) => {
+                    for (x, y, tile) in updates {
+                        self.set_tile(x, y, tile);
+                    }
+                }
+                Err(e) => warn!("Failed to read the tile updates: {}", e),
+            },
+            ETankPacketType::NetGamePacketTileChangeRequest => {
+                if let Some(request) = tank_packet.view::<TileChangeRequest>() {
+                    self.change_tile(&request);
+                }
+            }
+            ETankPacketType::NetGamePacketSendTileTreeState => {
+                if let Some(state) = tank_packet.view::<SendTileTreeState>() {
+                    if let Some(tile) = self.tile_mut(state.tile_x, state.tile_y) {
+                        tile.foreground = state.item_id as u16;
+                        clear_extra(tile);
+                    }
+                }
+            }
+            ETankPacketType::NetGamePacketSendLock => {
+                if let Some(lock) = tank_packet.view::<SendLock>() {
+                    self.lock(&lock, extended_data);
+                }
+            }
+            ETankPacketType::NetGamePacketItemChangeObject => {
+                if let Some(change) = tank_packet.view::<ItemChangeObject>() {
+                    self.change_object(&change);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn set_tile(&mut self, x: i32, y: i32, tile: Tile) {
+        if let Some(existing) = self.tile_mut(x, y) {
+            *existing = tile;
+        }
+    }
+
+    /// A fist breaks the foreground, or the background when there is none. Any other item is
+    /// placed in front, or behind when the foreground is taken. Items database lookups would be
+    /// needed to place a background on an empty tile correctly.
+    fn change_tile(&mut self, request: &TileChangeRequest) {
+        let Some(tile) = self.tile_mut(request.tile_x, request.tile_y) else {
+            return;
+        };
+        let item_id = request.item_id as u16;
+        match (request.is_punch(), tile.foreground) {
+            (true, 0) => tile.background = 0,
+            (true, _) => {
+                tile.foreground = 0;
+                clear_extra(tile);
+            }
+            (false, 0) => tile.foreground = item_id,
+            (false, _) => tile.background = item_id,
+        }
+    }
+
+    /// Places the lock and marks the tiles listed in the extended data (u16 indices) as its area.
+    fn lock(&mut self, lock: &SendLock, extended_data: &[u8]) {
+        let Some(lock_index) = self.index(lock.tile_x, lock.tile_y) else {
+            return;
+        };
+        let Some(tile) = self.tile_mut(lock.tile_x, lock.tile_y) else {
+            return;
+        };
+        tile.foreground = lock.lock_item_id as u16;
+        for tile in self.tiles.iter_mut().filter(|tile| tile.lock_index == Some(lock_index as u16)) {
+            tile.lock_index = None;
+            tile.flags.remove(TileFlags::LOCKED);
+        }
+        for index in extended_data.chunks_exact(2).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]])) {
+            if let Some(tile) = self.tiles.get_mut(index as usize) {
+                tile.lock_index = Some(lock_index as u16);
+                tile.flags.insert(TileFlags::LOCKED);
+            }
+        }
+    }
+
+    fn change_object(&mut self, change: &ItemChangeObject) {
+        if change.is_new_object() {
+            self.last_dropped_uid += 1;
+            self.dropped.push(DroppedItem {
+                uid: self.last_dropped_uid,
+                item_id: change.value as u16,
+                x: change.position.x,
+                y: change.position.y,
+                amount: change.amount as u8,
+                flags: change.object_flags,
+            });
+        } else if change.is_update() {
+            if let Some(item) = self.dropped.iter_mut().find(|item| item.uid == change.value) {
+                item.amount = change.amount as u8;
+            }
+        } else {
+            self.dropped.retain(|item| item.uid != change.value);
+        }
+    }
+
+    /// Applies a text packet from the game client.
+    pub fn apply_client_text(&mut self, message: &str) {
+        let parsed = text_parse::parse_and_store_as_map(message);
+        match parsed.get("action").map(String::as_str) {
+            Some("join_request") => {
+                self.leave();
+                self.name = parsed.get("name").map(|name| name.to_uppercase());
+            }
+            Some("quit_to_exit") | Some("quit") => self.leave(),
+            _ => {}
+        }
+    }
+
+    pub fn leave(&mut self) {
+        self.name = None;
+        self.players.clear();
+        self.local_net_id = None;
+        self.width = 0;
+        self.height = 0;
+        self.tiles.clear();
+        self.dropped.clear();
+        self.last_dropped_uid = 0;
+    }
+}
+
+fn clear_extra(tile: &mut Tile) {
+    tile.extra = None;
+    tile.flags.remove(TileFlags::HAS_EXTRA_DATA);
+}
+
+/// Builds a player from an `OnSpawn` message.
+pub fn parse_spawn(message: &str) -> Player {
+    let parsed = text_parse::parse_and_store_as_map(message);
+    let get = |key: &str| parsed.get(key).cloned().unwrap_or_default();
+    let position = text_parse::parse_and_store_as_vec(&get("posXY"));
+    Player {
+        r#type: get("type"),
+        avatar: get("spawn"),
+        net_id: get("netID").parse().unwrap_or_default(),
+        online_id: get("onlineID"),
+        e_id: get("eid"),
+        ip: get("ip"),
+        colrect: get("colrect"),
+        title_icon: get("titleIcon"),
+        mstate: get("mstate").parse().unwrap_or_default(),
+        user_id: get("userID").parse().unwrap_or_default(),
+        invis: get("invis") == "1",
+        name: text_parse::strip_colors(&get("name")),
+        country: get("country"),
+        position: Vector2 {
+            x: position.first().and_then(|x| x.parse().ok()).unwrap_or_default(),
+            y: position.get(1).and_then(|y| y.parse().ok()).unwrap_or_default(),
+        },
+    }
+}
+
+/// Updates the tracked world from a packet, given decoded and as received in `data`. `is_client`
+/// as in `packet_handler::handle`.
+pub fn observe(packet: &GamePacket, data: &[u8], is_client: bool) {
+    let mut world = global().world.lock().unwrap();
+    match packet {
+        GamePacket::Tank { variants: Some(variants), .. } if is_client => world.apply_call(variants),
+        GamePacket::Tank { tank_packet, .. } => {
+            world.apply_tank(tank_packet);
+            if is_client {
+                let start = 4 + TANK_PACKET_SIZE;
+                let extended_data = data.get(start..start + tank_packet.extended_data_length as usize).unwrap_or_default();
+                world.apply_update(tank_packet, extended_data);
+            }
+        }
+        GamePacket::Text(message) if !is_client => world.apply_client_text(message),
+        _ => {}
+    }
+}
+
+pub fn with<T>(f: impl FnOnce(&mut World) -> T) -> T {
+    f(&mut global().world.lock().unwrap())
+}
@@ -0,0 +1,151 @@
+// Polls the proxy's JSON API and renders the session, world, packet feed and hooks.
+
+const TILE_SIZE = 32;
+const MAP_WIDTH = 100;
+const MAP_HEIGHT = 60;
+const MAX_ROWS = 1000;
+const HOOK_LABELS = {
+    spawn_mod_state: "Set mod state on spawn",
+    block_app_integrity_fail: "Block app integrity fail",
+    wrench_menu: "Open the proxy menu by wrenching yourself",
+};
+
+let lastPacket = null;
+let filter = "";
+
+// The page is opened as /dashboard?token=... when the proxy has dashboard.token set.
+const token = new URLSearchParams(location.search).get("token");
+
+function api(url, options = {}) {
+    const headers = { ...options.headers };
+    if (token) {
+        headers.Authorization = `Bearer ${token}`;
+    }
+    return fetch(url, { ...options, headers });
+}
+
+async function getJson(url) {
+    const response = await api(url);
+    return response.json();
+}
+
+async function refreshSession() {
+    const session = await getJson("/api/session");
+    document.getElementById("session").textContent = session.id ? `#${session.id} ${session.state}` : "No session";
+}
+
+async function refreshWorld() {
+    const world = await getJson("/api/world");
+    document.getElementById("world-name").textContent = world.name ?? "Not in a world";
+
+    const canvas = document.getElementById("map");
+    const context = canvas.getContext("2d");
+    const scale = Math.min(canvas.width / (world.width || MAP_WIDTH), canvas.height / (world.height || MAP_HEIGHT));
+    context.clearRect(0, 0, canvas.width, canvas.height);
+    for (const tile of world.tiles) {
+        context.fillStyle = tile.foreground ? "#6b4f2c" : "#3b3326";
+        context.fillRect(tile.x * scale, tile.y * scale, scale, scale);
+    }
+    context.fillStyle = "#7fd46b";
+    for (const item of world.dropped) {
+        context.fillRect((item.x / TILE_SIZE) * scale, (item.y / TILE_SIZE) * scale, scale / 2, scale / 2);
+    }
+
+    const list = document.getElementById("players");
+    list.replaceChildren();
+    for (const player of world.players) {
+        context.fillStyle = player.local ? "#f6d26b" : "#e84f4f";
+        context.fillRect((player.x / TILE_SIZE) * scale, (player.y / TILE_SIZE) * scale, scale, scale * 1.5);
+
+        const item = document.createElement("li");
+        item.textContent = `${player.name} (${player.net_id}) ${Math.round(player.x / TILE_SIZE)},${Math.round(player.y / TILE_SIZE)}`;
+        if (player.local) {
+            item.className = "local";
+        }
+        list.appendChild(item);
+    }
+}
+
+async function refreshPackets() {
+    const params = new URLSearchParams();
+    if (lastPacket !== null) {
+        params.set("since", lastPacket);
+    }
+    if (filter) {
+        params.set("filter", filter);
+    }
+    const input = document.getElementById("filter");
+    const response = await api(`/api/packets?${params}`);
+    if (!response.ok) {
+        input.setCustomValidity(await response.text());
+        input.title = input.validationMessage;
+        return;
+    }
+    input.setCustomValidity("");
+    input.title = "";
+    const packets = await response.json();
+    const body = document.getElementById("packets");
+    for (const packet of packets) {
+        const row = document.createElement("tr");
+        row.className = packet.direction;
+        const time = new Date(packet.time).toISOString().slice(11, 23);
+        const direction = packet.direction === "from_client" ? "C>S" : "S>C";
+        for (const value of [packet.id, time, direction, packet.channel_id, packet.size, packet.summary]) {
+            const cell = document.createElement("td");
+            cell.textContent = value;
+            row.appendChild(cell);
+        }
+        body.appendChild(row);
+        lastPacket = packet.id;
+    }
+    while (body.rows.length > MAX_ROWS) {
+        body.deleteRow(0);
+    }
+    if (packets.length && document.getElementById("follow").checked) {
+        body.parentElement.scrollTop = body.parentElement.scrollHeight;
+    }
+}
+
+async function loadHooks() {
+    const hooks = await getJson("/api/hooks");
+    const form = document.getElementById("hooks");
+    form.replaceChildren();
+    for (const [name, enabled] of Object.entries(hooks)) {
+        const label = document.createElement("label");
+        const checkbox = document.createElement("input");
+        checkbox.type = "checkbox";
+        checkbox.name = name;
+        checkbox.checked = enabled;
+        checkbox.addEventListener("change", saveHooks);
+        label.append(checkbox, " ", HOOK_LABELS[name] ?? name);
+        form.appendChild(label);
+    }
+}
+
+async function saveHooks() {
+    const hooks = {};
+    for (const checkbox of document.querySelectorAll("#hooks input")) {
+        hooks[checkbox.name] = checkbox.checked;
+    }
+    await api("/api/hooks", {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        body: JSON.stringify(hooks),
+    });
+}
+
+// Filters are expressions such as `dir=server && fn=="OnConsoleMessage"`, evaluated by the proxy.
+document.getElementById("filter").addEventListener("change", (event) => {
+    filter = event.target.value.trim();
+    lastPacket = null;
+    document.getElementById("packets").replaceChildren();
+});
+
+function poll(refresh, interval) {
+    refresh().catch(console.error).finally(() => setTimeout(() => poll(refresh, interval), interval));
+}
+
+loadHooks().catch(console.error);
+poll(refreshSession, 1000);
+poll(refreshWorld, 1000);
+poll(refreshPackets, 500);
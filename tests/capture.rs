@@ -0,0 +1,29 @@
+use growtopia_proxy::capture::{Capture, CAPACITY};
+use growtopia_proxy::metrics::Direction;
+
+fn push(capture: &mut Capture) -> Option<u64> {
+    capture.push(1, Direction::FromClient, 0, &[2, 0, 0, 0], "NetMessageGenericText".to_string())
+}
+
+#[test]
+fn drops_oldest() {
+    let mut capture = Capture::default();
+    capture.enabled = true;
+    for _ in 0..CAPACITY + 5 {
+        push(&mut capture);
+    }
+    assert_eq!(capture.len(), CAPACITY);
+    assert_eq!(capture.iter().next().map(|packet| packet.id), Some(5));
+    assert_eq!(capture.get(10).map(|packet| packet.id), Some(10));
+    assert!(capture.get(4).is_none());
+    assert_eq!(capture.since(CAPACITY as u64 + 2).count(), 2);
+}
+
+#[test]
+fn ignores_packets_while_off() {
+    let mut capture = Capture::default();
+    assert_eq!(push(&mut capture), None);
+    assert!(capture.is_empty());
+    capture.enabled = true;
+    assert_eq!(push(&mut capture), Some(0));
+}
@@ -0,0 +1,38 @@
+use growtopia_proxy::packet_handler::{decode, GamePacket};
+use growtopia_proxy::types::epacket_type::EPacketType;
+use growtopia_proxy::types::etank_packet_type::ETankPacketType;
+use growtopia_proxy::types::tank_packet::TankPacket;
+use growtopia_proxy::utils::variant::{Variant, VariantList};
+use proptest::prelude::*;
+
+proptest! {
+    #[test]
+    fn arbitrary_bytes_never_panic(data in proptest::collection::vec(any::<u8>(), 0..128)) {
+        let _ = decode(&data);
+    }
+
+    #[test]
+    fn call_function_decodes_variants(function in "On[A-Za-z]{1,16}", text in ".*") {
+        let mut variants = VariantList::new();
+        variants.push(Variant::String(function.clone()));
+        variants.push(Variant::String(text));
+        let extra = variants.serialize();
+        let mut tank_packet = TankPacket {
+            r#type: ETankPacketType::NetGamePacketCallFunction,
+            ..Default::default()
+        };
+        let data = tank_packet.to_message(&extra, &[0]);
+
+        let (packet_type, packet) = decode(&data).unwrap();
+        prop_assert_eq!(packet_type, EPacketType::NetMessageGamePacket);
+        match packet {
+            GamePacket::Tank { tank_packet: decoded, variants: Some(decoded_variants), trailing } => {
+                prop_assert_eq!(decoded, tank_packet);
+                prop_assert_eq!(trailing, vec![0]);
+                prop_assert_eq!(decoded_variants.get(0), Some(&Variant::String(function)));
+                prop_assert_eq!(decoded_variants.serialize(), extra);
+            }
+            other => prop_assert!(false, "unexpected packet {:?}", other),
+        }
+    }
+}
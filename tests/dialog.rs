@@ -0,0 +1,80 @@
+use growtopia_proxy::dialog::{Dialog, DialogReturn, Element};
+use growtopia_proxy::packet_handler::{decode, GamePacket};
+use growtopia_proxy::variant_handler::dialog_request;
+
+const GAZETTE: &str = "set_default_color|`o\n\
+add_label_with_icon|big|`wThe Growtopia Gazette``|left|5016|\n\
+add_spacer|small|\n\
+add_textbox|Welcome back!|left|\n\
+add_text_input|name|Name:|Seth|18|\n\
+add_checkbox|notify|Notify me|1|\n\
+add_image_button|banner|interface/large/news_banner.rttex|bannerlayout|||\n\
+add_button|rules|`wHelp - Rules``|noflags|0|0|\n\
+add_quick_exit|\n\
+end_dialog|gazette||OK|\n";
+
+#[test]
+fn parses_known_and_unknown_elements() {
+    let dialog = Dialog::parse(GAZETTE);
+    assert_eq!(dialog.elements.len(), 10);
+    assert_eq!(dialog.name(), Some("gazette"));
+    assert_eq!(dialog.elements[0], Element::DefaultColor("`o".to_string()));
+    assert_eq!(
+        dialog.elements[1],
+        Element::LabelWithIcon { size: "big".to_string(), text: "`wThe Growtopia Gazette``".to_string(), align: "left".to_string(), item_id: 5016 }
+    );
+    assert_eq!(
+        dialog.get("name"),
+        Some(&Element::TextInput { id: "name".to_string(), label: "Name:".to_string(), value: "Seth".to_string(), max_length: 18, password: false })
+    );
+    assert_eq!(dialog.get("notify"), Some(&Element::Checkbox { id: "notify".to_string(), label: "Notify me".to_string(), checked: true }));
+    assert_eq!(
+        dialog.elements[6],
+        Element::Other {
+            command: "add_image_button".to_string(),
+            args: ["banner", "interface/large/news_banner.rttex", "bannerlayout", "", ""].map(String::from).to_vec(),
+        }
+    );
+    assert_eq!(dialog.elements[9], Element::End { name: "gazette".to_string(), cancel: String::new(), ok: "OK".to_string() });
+}
+
+#[test]
+fn renders_the_wire_format() {
+    assert_eq!(Dialog::parse(GAZETTE).to_string(), GAZETTE);
+    for button in ["add_button|buy|Buy|staticBlueFrame|1|\n", "add_button|close|Close|\n", "add_button|x|X|off|0|0|\n"] {
+        assert_eq!(Dialog::parse(button).to_string(), button);
+    }
+    let built = Dialog::new()
+        .label("Proxy")
+        .checkbox("spawn_mod_state", "Mod state", false)
+        .text_input("greeting", "Greeting", "hi", 32)
+        .button("save", "Save")
+        .end("proxy_settings", "Cancel", "");
+    assert_eq!(
+        built.to_string(),
+        "add_label|big|Proxy|left|\n\
+         add_checkbox|spawn_mod_state|Mod state|0|\n\
+         add_text_input|greeting|Greeting|hi|32|\n\
+         add_button|save|Save|noflags|0|0|\n\
+         end_dialog|proxy_settings|Cancel||\n"
+    );
+    assert_eq!(Dialog::parse(&built.to_string()), built);
+
+    let Ok((_, GamePacket::Tank { variants: Some(variants), .. })) = decode(&dialog_request(&built)) else {
+        panic!("not a function call");
+    };
+    assert_eq!(variants.get(0).unwrap().as_string(), "OnDialogRequest");
+    assert_eq!(Dialog::parse(&variants.get(1).unwrap().as_string()), built);
+}
+
+#[test]
+fn parses_dialog_returns() {
+    let reply = DialogReturn::parse("action|dialog_return\ndialog_name|proxy_settings\nbuttonClicked|save\n\nspawn_mod_state|1\ngreeting|a|b\n").unwrap();
+    assert_eq!(reply.name, "proxy_settings");
+    assert_eq!(reply.button.as_deref(), Some("save"));
+    assert!(reply.checked("spawn_mod_state"));
+    assert!(!reply.checked("missing"));
+    assert_eq!(reply.get("greeting"), Some("a|b"));
+    assert_eq!(DialogReturn::parse(&reply.to_string()), Some(reply));
+    assert_eq!(DialogReturn::parse("action|input\n|text|hi"), None);
+}
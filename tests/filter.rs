@@ -0,0 +1,128 @@
+use growtopia_proxy::capture::Capture;
+use growtopia_proxy::filter::{Filter, FilterInput, MAX_DEPTH};
+use growtopia_proxy::metrics::Direction;
+use growtopia_proxy::packet_handler::{decode, GamePacket};
+use growtopia_proxy::types::epacket_type::EPacketType;
+use growtopia_proxy::types::etank_packet_type::ETankPacketType;
+use growtopia_proxy::types::packet_flag::PacketFlag;
+use growtopia_proxy::types::tank_packet::TankPacket;
+use growtopia_proxy::variant_handler::console_message;
+use proptest::prelude::*;
+
+fn matches(expression: &str, direction: Direction, data: &[u8]) -> bool {
+    Filter::parse(expression).unwrap().matches_data(direction, 0, data)
+}
+
+fn text(message: &str) -> Vec<u8> {
+    let mut data = vec![3, 0, 0, 0];
+    data.extend_from_slice(message.as_bytes());
+    data
+}
+
+fn punch(int_x: i32) -> Vec<u8> {
+    let mut tank_packet = TankPacket {
+        r#type: ETankPacketType::NetGamePacketTileChangeRequest,
+        flags: PacketFlag::PUNCH | PacketFlag::WALK,
+        int_x,
+        int_y: 4,
+        value: 18,
+        ..Default::default()
+    };
+    tank_packet.to_message(&[], &[])
+}
+
+#[test]
+fn matches_function_calls() {
+    let data = console_message("hello");
+    assert!(matches(r#"dir=server && tank.type==CallFunction && fn=="OnConsoleMessage""#, Direction::FromServer, &data));
+    assert!(matches("type==GamePacket && tank.type==NetGamePacketCallFunction", Direction::FromServer, &data));
+    assert!(matches(r#"var.1 ~= "HELL""#, Direction::FromServer, &data));
+    assert!(!matches("dir=client", Direction::FromServer, &data));
+    assert!(!matches(r#"fn=="OnSpawn""#, Direction::FromServer, &data));
+}
+
+#[test]
+fn matches_tank_fields_and_flags() {
+    let data = punch(12);
+    assert!(matches("tank.flags.punch && tank.int_x > 10", Direction::FromClient, &data));
+    assert!(matches("tank.flags.walk && !tank.flags.place && tank.value == 18", Direction::FromClient, &data));
+    assert!(!matches("tank.flags.punch && tank.int_x > 12", Direction::FromClient, &data));
+    assert!(matches("tank.int_x >= 12 && (tank.int_y < 0 || tank.int_y == 4)", Direction::FromClient, &data));
+    assert!(matches("tank.flags == 0x201", Direction::FromClient, &data));
+}
+
+#[test]
+fn matches_text_fields() {
+    let data = text("action|join_request\nname|START\ninvitedWorld|0");
+    assert!(matches(r#"type=GameMessage && text.action == join_request && text.name == "start""#, Direction::FromClient, &data));
+    assert!(matches(r#"text ~= "invitedWorld""#, Direction::FromClient, &data));
+    assert!(!matches("tank.net_id == 0", Direction::FromClient, &data));
+    assert!(matches("!tank.type", Direction::FromClient, &data));
+}
+
+#[test]
+fn matches_chat_text() {
+    let data = text("action|input\n|text|hi there");
+    assert!(matches(r#"text.action == input && text.text == "hi there""#, Direction::FromClient, &data));
+    assert!(!matches("text.text == hi", Direction::FromClient, &data));
+}
+
+#[test]
+fn matches_captured_packets() {
+    let mut capture = Capture::default();
+    capture.enabled = true;
+    capture.push(1, Direction::FromClient, 0, &punch(12), String::new());
+    capture.push(1, Direction::FromClient, 0, &[4, 0, 0, 0], String::new());
+    let filter = Filter::parse("tank.flags.punch").unwrap();
+    let matching: Vec<u64> = capture.iter().filter(|packet| filter.matches_captured(packet)).map(|packet| packet.id).collect();
+    assert_eq!(matching, [0]);
+    assert!(capture.get(1).unwrap().decoded().is_none());
+}
+
+#[test]
+fn matches_decoded_input() {
+    let data = console_message("hi");
+    let (packet_type, packet) = decode(&data).unwrap();
+    assert_eq!(packet_type, EPacketType::NetMessageGamePacket);
+    assert!(matches!(packet, GamePacket::Tank { .. }));
+    let input = FilterInput { direction: Direction::FromServer, channel_id: 1, size: data.len(), packet_type, packet: Some(&packet) };
+    assert!(Filter::parse("channel == 1 && size > 56").unwrap().matches(&input));
+}
+
+#[test]
+fn reports_errors() {
+    let error = Filter::parse("tank.nope == 1").unwrap_err();
+    assert_eq!(error.position, 0);
+    assert!(Filter::parse("fn == ").is_err());
+    assert!(Filter::parse("(dir=server").is_err());
+    assert!(Filter::parse(r#"fn == "open"#).is_err());
+    assert!(Filter::parse("dir=server server").is_err());
+    assert!(Filter::parse("size > 0x1g").is_err());
+}
+
+#[test]
+fn limits_nesting() {
+    let nested = |depth: usize| format!("{}{}dir=server{}", "!".repeat(depth), "(".repeat(depth), ")".repeat(depth));
+    assert!(Filter::parse(&nested(MAX_DEPTH / 2)).is_ok());
+    assert_eq!(Filter::parse(&nested(MAX_DEPTH)).unwrap_err().message, "expression nested too deeply");
+    assert!(Filter::parse(&"!".repeat(100_000)).is_err());
+    assert!(Filter::parse(&"(".repeat(100_000)).is_err());
+    let long = vec!["dir=server"; 100_000].join(" && ");
+    assert!(matches(&long, Direction::FromServer, &console_message("hello")));
+}
+
+#[test]
+fn not_equal_matches_absent_fields() {
+    let data = text("action|join_request");
+    assert!(matches("tank.net_id != 0", Direction::FromClient, &data));
+    assert!(matches("text.name != start", Direction::FromClient, &data));
+    assert!(!matches("tank.net_id == 0", Direction::FromClient, &data));
+    assert!(!matches("text.name < start", Direction::FromClient, &data));
+}
+
+proptest! {
+    #[test]
+    fn parse_never_panics(input in ".{0,64}") {
+        let _ = Filter::parse(&input);
+    }
+}
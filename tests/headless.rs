@@ -0,0 +1,39 @@
+use std::net::SocketAddr;
+use growtopia_proxy::headless::login_text;
+use growtopia_proxy::session::{apply_redirect, Redirect};
+
+fn redirect() -> Redirect {
+    Redirect {
+        address: "127.0.0.1:17091".parse::<SocketAddr>().unwrap(),
+        token: 42,
+        user: 7,
+        door_id: "door".to_string(),
+        uuid: "abc".to_string(),
+    }
+}
+
+#[test]
+fn applies_redirect_credentials() {
+    let login = "tankIDName|\nuser|0\ntoken|0\nUUIDToken|\ndoorID|\ngame_version|5.11";
+    assert_eq!(
+        apply_redirect(login, &redirect()),
+        "tankIDName|\nuser|7\ntoken|42\nUUIDToken|abc\ndoorID|door\ngame_version|5.11"
+    );
+}
+
+#[test]
+fn appends_missing_redirect_fields() {
+    let login = apply_redirect("requestedName|", &redirect());
+    assert!(login.starts_with("requestedName|\n"));
+    for field in ["user|7", "token|42", "UUIDToken|abc", "doorID|door"] {
+        assert!(login.lines().any(|line| line == field), "{} missing from {:?}", field, login);
+    }
+}
+
+#[test]
+fn prefers_the_configured_login() {
+    let remembered = Some("tankIDName|old\nmeta|x".to_string());
+    assert_eq!(login_text("tankIDName|new\nmeta|x", remembered.clone(), Some("y")), Some("tankIDName|new\nmeta|y".to_string()));
+    assert_eq!(login_text("", remembered, None), Some("tankIDName|old\nmeta|x".to_string()));
+    assert_eq!(login_text("", None, Some("y")), None);
+}
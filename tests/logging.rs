@@ -0,0 +1,29 @@
+use std::collections::BTreeMap;
+use growtopia_proxy::logging::hex_dump;
+use growtopia_proxy::types::config::LoggingConfig;
+
+#[test]
+fn hex_dump_lines() {
+    let data: Vec<u8> = (0x41..0x41 + 18).collect();
+    assert_eq!(
+        hex_dump(&data),
+        "00000000  41 42 43 44 45 46 47 48  49 4a 4b 4c 4d 4e 4f 50  |ABCDEFGHIJKLMNOP|\n\
+         00000010  51 52                                             |QR|"
+    );
+}
+
+#[test]
+fn hex_dump_replaces_unprintable() {
+    assert!(hex_dump(&[0x04, 0x00, b'a', 0xff]).ends_with("|..a.|"));
+    assert_eq!(hex_dump(&[]), "");
+}
+
+#[test]
+fn filter_directives() {
+    let config = LoggingConfig {
+        level: "debug".to_string(),
+        modules: BTreeMap::from([("growtopia_proxy::enet".to_string(), "trace".to_string())]),
+        ..Default::default()
+    };
+    assert_eq!(config.directives(), "warn,growtopia_proxy=debug,growtopia_proxy::enet=trace");
+}
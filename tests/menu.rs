@@ -0,0 +1,48 @@
+use growtopia_proxy::dialog::{DialogReturn, Element};
+use growtopia_proxy::menu::{apply, dialog, rule_ids, DIALOG_NAME};
+use growtopia_proxy::types::config::{Hooks, Rule, RuleAction};
+
+fn rule(name: &str, enabled: bool) -> Rule {
+    Rule { name: name.to_string(), expression: "fn==OnSpawn".to_string(), action: RuleAction::Drop, enabled }
+}
+
+#[test]
+fn lists_hooks_and_rules() {
+    let menu = dialog(&Hooks::default(), &[rule("no spawn", false), rule("", true)]);
+    assert_eq!(menu.name(), Some(DIALOG_NAME));
+    assert!(matches!(menu.get("spawn_mod_state"), Some(Element::Checkbox { checked: true, .. })));
+    assert!(matches!(menu.get("wrench_menu"), Some(Element::Checkbox { checked: false, .. })));
+    assert!(matches!(menu.get("rule_no_spawn"), Some(Element::Checkbox { label, checked: false, .. }) if label == "no spawn"));
+    assert!(matches!(menu.get("rule_fn__OnSpawn"), Some(Element::Checkbox { label, checked: true, .. }) if label == "fn==OnSpawn"));
+}
+
+#[test]
+fn applies_the_reply() {
+    let reply = DialogReturn::parse(&format!(
+        "action|dialog_return\ndialog_name|{}\nbuttonClicked|\nspawn_mod_state|0\nblock_app_integrity_fail|1\nwrench_menu|1\nrule_a|1\nrule_b|0\n",
+        DIALOG_NAME
+    ))
+    .unwrap();
+    let mut hooks = Hooks::default();
+    // "c" was added and "b" moved while the menu was open.
+    let mut rules = vec![rule("c", true), rule("a", false), rule("b", true)];
+    apply(&reply, &mut hooks, &mut rules);
+    assert_eq!(hooks, Hooks { spawn_mod_state: false, block_app_integrity_fail: true, wrench_menu: true });
+    assert_eq!(rules, vec![rule("c", true), rule("a", true), rule("b", false)]);
+}
+
+#[test]
+fn tells_colliding_rules_apart() {
+    let rules = vec![rule("no spawn", true), rule("no-spawn", true), rule("", true), rule("", true), rule("no_spawn_2", true)];
+    let ids = rule_ids(&rules);
+    assert_eq!(ids, ["rule_no_spawn", "rule_no_spawn_2", "rule_fn__OnSpawn", "rule_fn__OnSpawn_2", "rule_no_spawn_2_2"]);
+
+    let reply = DialogReturn::parse(&format!(
+        "action|dialog_return\ndialog_name|{}\nbuttonClicked|\nrule_no_spawn|1\nrule_no_spawn_2|0\nrule_fn__OnSpawn|0\nrule_fn__OnSpawn_2|1\nrule_no_spawn_2_2|1\n",
+        DIALOG_NAME
+    ))
+    .unwrap();
+    let mut rules = rules;
+    apply(&reply, &mut Hooks::default(), &mut rules);
+    assert_eq!(rules.iter().map(|rule| rule.enabled).collect::<Vec<_>>(), [true, false, false, true, true]);
+}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+use growtopia_proxy::metrics::{Direction, Metrics, PeerStats};
+
+#[test]
+fn renders_prometheus_text() {
+    let mut metrics = Metrics::default();
+    metrics.record_packet(Direction::FromServer, 120, "NetMessageGamePacket");
+    metrics.record_tank(Direction::FromServer, "NetGamePacketCallFunction");
+    metrics.record_function(Direction::FromServer, "OnConsoleMessage");
+    metrics.record_packet(Direction::FromClient, 10, "NetMessageGenericText");
+    metrics.record_decode_error(Direction::FromClient);
+    metrics.peers.insert(Direction::FromServer, PeerStats { round_trip_time: Duration::from_millis(25), packet_loss: 0.5 });
+
+    let text = metrics.render();
+    assert!(text.contains("# TYPE growtopia_proxy_packets_total counter\n"));
+    assert!(text.contains("growtopia_proxy_packets_total{scope=\"global\",direction=\"from_server\"} 1\n"));
+    assert!(text.contains("growtopia_proxy_bytes_total{scope=\"session\",direction=\"from_server\"} 120\n"));
+    assert!(text.contains("growtopia_proxy_call_functions_total{scope=\"global\",direction=\"from_server\",function=\"OnConsoleMessage\"} 1\n"));
+    assert!(text.contains("growtopia_proxy_decode_errors_total{scope=\"global\",direction=\"from_client\"} 1\n"));
+    assert!(text.contains("growtopia_proxy_enet_round_trip_seconds{direction=\"from_server\"} 0.025\n"));
+}
+
+#[test]
+fn session_counters_reset_independently() {
+    let mut metrics = Metrics::default();
+    metrics.record_packet(Direction::FromClient, 10, "NetMessageGameMessage");
+    metrics.reset_session();
+    metrics.record_packet(Direction::FromClient, 5, "NetMessageGameMessage");
+
+    assert_eq!(metrics.global.packets[&Direction::FromClient], 2);
+    assert_eq!(metrics.session.packets[&Direction::FromClient], 1);
+    assert_eq!(metrics.session.bytes[&Direction::FromClient], 5);
+}
+
+#[test]
+fn label_values_are_escaped() {
+    let mut metrics = Metrics::default();
+    metrics.record_packet(Direction::FromServer, 1, "Net\"Weird\\\n");
+    assert!(metrics.render().contains("type=\"Net\\\"Weird\\\\\\n\""));
+}
+
+#[test]
+fn counts_unknown_functions_as_other() {
+    let mut metrics = Metrics::default();
+    metrics.record_function(Direction::FromServer, "OnSpawn");
+    metrics.record_function(Direction::FromServer, "OnMadeUp1");
+    metrics.record_function(Direction::FromServer, "OnMadeUp2");
+    assert_eq!(metrics.global.functions.len(), 2);
+    assert_eq!(metrics.global.functions[&(Direction::FromServer, "other".to_string())], 2);
+    assert!(metrics.render().contains("function=\"OnSpawn\"} 1\n"));
+}
+
+#[test]
+fn renders_ping_round_trips() {
+    let mut metrics = Metrics::default();
+    metrics.ping.requests = 3;
+    metrics.ping.proxy_replies = 1;
+    metrics.ping.record_round_trip(Duration::from_millis(100));
+    metrics.ping.record_round_trip(Duration::from_millis(300));
+    assert_eq!(metrics.ping.average_round_trip(), Some(Duration::from_millis(200)));
+
+    let text = metrics.render();
+    assert!(text.contains("growtopia_proxy_ping_requests_total 3\n"));
+    assert!(text.contains("growtopia_proxy_ping_replies_total{by=\"client\"} 2\n"));
+    assert!(text.contains("growtopia_proxy_ping_replies_total{by=\"proxy\"} 1\n"));
+    assert!(text.contains("growtopia_proxy_ping_round_trip_seconds{stat=\"min\"} 0.1\n"));
+    assert!(text.contains("growtopia_proxy_ping_round_trip_seconds{stat=\"last\"} 0.3\n"));
+    metrics.reset_session();
+    assert_eq!(metrics.ping.client_replies, 0);
+}
@@ -0,0 +1,47 @@
+use std::time::Duration;
+use growtopia_proxy::movement::{plan, tile_position, MovementConfig};
+use growtopia_proxy::types::packet_flag::PacketFlag;
+use growtopia_proxy::types::tank_view::{State, TankView};
+use growtopia_proxy::types::vector2::Vector2;
+
+fn config() -> MovementConfig {
+    MovementConfig { speed: 160.0, interval: Duration::from_millis(100) }
+}
+
+#[test]
+fn walks_tile_by_tile() {
+    // 16 pixels per state, so two states per tile.
+    let steps = plan(5, tile_position((1, 2)), &[(1, 2), (2, 2), (3, 2)], &config());
+    assert_eq!(steps.len(), 5);
+    assert!(steps.iter().all(|step| step.delay == Duration::from_millis(100) && step.state.net_id == 5));
+    let positions: Vec<f32> = steps.iter().map(|step| step.state.position.x).collect();
+    assert_eq!(positions, vec![48.0, 64.0, 80.0, 96.0, 96.0]);
+    assert!(steps.iter().all(|step| step.state.position.y == 64.0));
+    assert_eq!(steps[0].state.flags, PacketFlag::WALK | PacketFlag::STANDING);
+    assert_eq!(steps[0].state.velocity, Vector2 { x: 160.0, y: 0.0 });
+    assert_eq!((steps[0].state.tile_x, steps[0].state.tile_y), (-1, -1));
+
+    let end = steps.last().unwrap();
+    assert_eq!(end.state.flags, PacketFlag::STANDING);
+    assert_eq!(end.state.velocity, Vector2::default());
+}
+
+#[test]
+fn sets_facing_and_jump_flags() {
+    let steps = plan(1, tile_position((2, 2)), &[(1, 2), (1, 1), (1, 2)], &config());
+    assert!(steps[..2].iter().all(|step| step.state.flags == PacketFlag::WALK | PacketFlag::STANDING | PacketFlag::FACING_LEFT));
+    assert!(steps[2..4].iter().all(|step| step.state.flags == PacketFlag::JUMP | PacketFlag::FACING_LEFT));
+    assert!(steps[2].state.velocity.y < 0.0);
+    assert!(steps[4..6].iter().all(|step| step.state.flags == PacketFlag::FACING_LEFT && step.state.velocity.y > 0.0));
+    assert_eq!(steps.last().unwrap().state.flags, PacketFlag::STANDING | PacketFlag::FACING_LEFT);
+}
+
+#[test]
+fn builds_state_packets() {
+    let steps = plan(7, Vector2 { x: 40.0, y: 32.0 }, &[(1, 1)], &config());
+    assert_eq!(steps.len(), 2);
+    let packet = steps[0].state.to_tank_packet();
+    assert_eq!(packet.view::<State>(), Some(steps[0].state.clone()));
+    assert_eq!((packet.vector_x, packet.vector_y, packet.vector_x2), (32.0, 32.0, -160.0));
+    assert!(plan(7, tile_position((1, 1)), &[(1, 1)], &config()).is_empty());
+}
@@ -0,0 +1,40 @@
+use growtopia_proxy::types::packet_flag::PacketFlag;
+use proptest::prelude::*;
+
+proptest! {
+    #[test]
+    fn all_bits_round_trip(value in any::<u32>()) {
+        prop_assert_eq!(PacketFlag::from_u32(value).to_u32(), value);
+    }
+
+    #[test]
+    fn serde_matches_raw_value(value in any::<u32>()) {
+        let flags = PacketFlag::from_u32(value);
+        prop_assert_eq!(serde_json::to_string(&flags).unwrap(), value.to_string());
+        prop_assert_eq!(serde_json::from_str::<PacketFlag>(&value.to_string()).unwrap(), flags);
+    }
+
+    #[test]
+    fn display_lists_every_bit(value in any::<u32>()) {
+        let text = PacketFlag::from_u32(value).to_string();
+        let parsed = if value == 0 {
+            PacketFlag::empty()
+        } else {
+            bitflags::parser::from_str::<PacketFlag>(&text).unwrap()
+        };
+        prop_assert_eq!(parsed.to_u32(), value);
+    }
+}
+
+#[test]
+fn builder_sets_and_clears_flags() {
+    let flags = PacketFlag::empty()
+        .with(PacketFlag::WALK, true)
+        .with(PacketFlag::FACING_LEFT, true)
+        .with(PacketFlag::WALK, false)
+        .with(PacketFlag::STANDING, true);
+    assert_eq!(flags, PacketFlag::FACING_LEFT | PacketFlag::STANDING);
+    assert_eq!(flags.to_string(), "FACING_LEFT | STANDING");
+    assert_eq!(PacketFlag::from_u32(0x4000_0001).to_string(), "WALK | 0x40000000");
+    assert_eq!(PacketFlag::empty().to_string(), "NONE");
+}
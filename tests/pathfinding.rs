@@ -0,0 +1,142 @@
+use growtopia_proxy::pathfinding::Pathfinder;
+use growtopia_proxy::types::item_database::{CollisionType, ItemDatabase, ItemDatabaseError};
+use growtopia_proxy::types::world_data::{Tile, TileExtra, TileFlags, WorldData};
+use growtopia_proxy::world::World;
+
+const KEY: &[u8] = b"PBG892FXX982ABC*";
+
+fn string(out: &mut Vec<u8>, value: &[u8]) {
+    out.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    out.extend_from_slice(value);
+}
+
+/// An item as written in a version 14 `items.dat`, with everything but the kept fields zeroed.
+fn item(out: &mut Vec<u8>, id: u32, name: &str, collision_type: u8) {
+    out.extend_from_slice(&id.to_le_bytes());
+    out.extend_from_slice(&[1, 2, 3, 0]);
+    let name: Vec<u8> = name.bytes().enumerate().map(|(index, byte)| byte ^ KEY[(id as usize + index) % KEY.len()]).collect();
+    string(out, &name);
+    string(out, b"tiles_page1.rttex");
+    out.extend_from_slice(&[0; 13]);
+    out.extend_from_slice(&[collision_type, 6]);
+    out.extend_from_slice(&[0; 4]);
+    out.push(0);
+    out.extend_from_slice(&5u16.to_le_bytes());
+    out.push(200);
+    string(out, b"");
+    out.extend_from_slice(&[0; 8]);
+    for _ in 0..4 {
+        string(out, b"");
+    }
+    out.extend_from_slice(&[0; 16]);
+    out.extend_from_slice(&31u32.to_le_bytes());
+    out.extend_from_slice(&[0; 4]);
+    for _ in 0..3 {
+        string(out, b"");
+    }
+    out.extend_from_slice(&[0; 80]);
+    string(out, b"");
+    out.extend_from_slice(&[0; 13 + 4 + 4]);
+}
+
+/// Blank, dirt (solid), a wooden platform, a gateway and a one-way block.
+fn items() -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&14u16.to_le_bytes());
+    out.extend_from_slice(&5u32.to_le_bytes());
+    for (id, name, collision_type) in [(0, "Blank", 0), (1, "Dirt", 1), (2, "Wooden Platform", 2), (3, "Entrance", 3), (4, "One Way", 5)] {
+        item(&mut out, id, name, collision_type);
+    }
+    out
+}
+
+#[test]
+fn decodes_items() {
+    let database = ItemDatabase::decode(&items()).unwrap();
+    assert_eq!((database.version, database.items.len()), (14, 5));
+    let platform = database.get(2).unwrap();
+    assert_eq!(platform.name, "Wooden Platform");
+    assert_eq!(platform.texture, "tiles_page1.rttex");
+    assert_eq!(platform.collision_type, CollisionType::JumpThrough);
+    assert_eq!((platform.break_hits, platform.rarity, platform.max_amount, platform.grow_time), (6, 5, 200, 31));
+    assert!(database.get(9).is_none());
+
+    let mut data = items();
+    data[0] = 99;
+    assert!(matches!(ItemDatabase::decode(&data), Err(ItemDatabaseError::UnsupportedVersion(99))));
+    assert!(matches!(ItemDatabase::decode(&items()[..100]), Err(ItemDatabaseError::Io(_))));
+}
+
+fn tile(foreground: u16) -> Tile {
+    Tile { foreground, ..Default::default() }
+}
+
+/// A world from rows of `.` (empty), `#` (dirt), `-` (platform), `E` (entrance) and `>` (one-way).
+fn world(rows: &[&str]) -> World {
+    let tiles = rows
+        .iter()
+        .flat_map(|row| row.chars())
+        .map(|c| match c {
+            '#' => tile(1),
+            '-' => tile(2),
+            'E' => tile(3),
+            '>' => tile(4),
+            _ => tile(0),
+        })
+        .collect();
+    let mut world = World::default();
+    world.load(WorldData { name: "TEST".to_string(), width: rows[0].len() as u32, height: rows.len() as u32, tiles, ..Default::default() });
+    world
+}
+
+#[test]
+fn walks_around_solid_blocks() {
+    let items = ItemDatabase::decode(&items()).unwrap();
+    let world = world(&["....", ".##.", "..#.", "...."]);
+    let path = Pathfinder::new(&world, &items).find_path((0, 2), (3, 2)).unwrap();
+    assert_eq!(path.first(), Some(&(0, 2)));
+    assert_eq!(path.last(), Some(&(3, 2)));
+    assert_eq!(path.len(), 6);
+    assert!(path.windows(2).all(|step| step[0].0.abs_diff(step[1].0) + step[0].1.abs_diff(step[1].1) == 1));
+    assert!(path.iter().all(|&(x, y)| world.tile(x as i32, y as i32).unwrap().foreground == 0));
+
+    let walled = self::world(&[".#.", ".#.", ".#."]);
+    assert_eq!(Pathfinder::new(&walled, &items).find_path((0, 0), (2, 0)), None);
+    assert_eq!(Pathfinder::new(&walled, &items).find_path((0, 0), (5, 0)), None);
+}
+
+#[test]
+fn passes_platforms_only_from_below() {
+    let items = ItemDatabase::decode(&items()).unwrap();
+    let world = world(&[".", "-", "."]);
+    let pathfinder = Pathfinder::new(&world, &items);
+    assert_eq!(pathfinder.find_path((0, 2), (0, 0)), Some(vec![(0, 2), (0, 1), (0, 0)]));
+    assert_eq!(pathfinder.find_path((0, 0), (0, 2)), None);
+
+    let one_way = self::world(&[".>."]);
+    assert!(Pathfinder::new(&one_way, &items).find_path((0, 0), (2, 0)).is_some());
+    assert!(Pathfinder::new(&one_way, &items).find_path((2, 0), (0, 0)).is_none());
+}
+
+#[test]
+fn opens_entrances_with_access() {
+    let items = ItemDatabase::decode(&items()).unwrap();
+    let mut world = world(&["#.E.", "####"]);
+    world.tiles[0] = Tile {
+        foreground: 202,
+        extra: Some(TileExtra::Lock { settings: 0, owner_uid: 1, access_uids: vec![2], minimum_level: 0 }),
+        ..Default::default()
+    };
+    for index in [1, 2, 3] {
+        world.tiles[index].flags |= TileFlags::LOCKED;
+        world.tiles[index].lock_index = Some(0);
+    }
+    let pathfinder = |user_id| Pathfinder::new(&world, &items).with_user_id(user_id);
+    assert!(pathfinder(Some(1)).find_path((1, 0), (3, 0)).is_some());
+    assert!(pathfinder(Some(2)).find_path((1, 0), (3, 0)).is_some());
+    assert!(pathfinder(Some(3)).find_path((1, 0), (3, 0)).is_none());
+    assert!(pathfinder(None).find_path((1, 0), (3, 0)).is_none());
+
+    world.tiles[2].flags |= TileFlags::PUBLIC;
+    assert!(Pathfinder::new(&world, &items).with_user_id(Some(3)).find_path((1, 0), (3, 0)).is_some());
+}
@@ -0,0 +1,52 @@
+use std::time::{Duration, Instant};
+use growtopia_proxy::ping::{proton_hash, reply_to, PingAction, Pings};
+use growtopia_proxy::types::tank_view::{PingReply, PingRequest};
+
+fn client_reply(time: u32) -> PingReply {
+    PingReply { hash: proton_hash(&time.to_le_bytes()), time, build_range: 128.0, punch_range: 96.0, speed: 300.0, gravity: 900.0 }
+}
+
+#[test]
+fn hashes_like_proton() {
+    assert_eq!(proton_hash(&[]), 0x55555555);
+    assert_eq!(proton_hash(&[1]), 0xaaaaaaab);
+    let reply = reply_to(1000, &client_reply(5));
+    assert_eq!((reply.time, reply.hash), (1000, proton_hash(&1000u32.to_le_bytes())));
+    assert_eq!((reply.build_range, reply.speed), (128.0, 300.0));
+}
+
+#[test]
+fn forwards_pings_the_client_answers() {
+    let mut pings = Pings::default();
+    let now = Instant::now();
+    assert_eq!(pings.request(&PingRequest { time: 1 }, true, now), PingAction::Forward);
+    assert_eq!(pings.reply(&client_reply(1), now + Duration::from_millis(40)), PingAction::Forward);
+    assert!(pings.expire(Duration::from_secs(2), now + Duration::from_secs(5)).is_empty());
+}
+
+#[test]
+fn answers_for_a_paused_client() {
+    let mut pings = Pings::default();
+    let now = Instant::now();
+    pings.reply(&client_reply(0), now);
+    pings.request(&PingRequest { time: 1 }, true, now);
+    pings.request(&PingRequest { time: 2 }, true, now + Duration::from_secs(1));
+    let replies = pings.expire(Duration::from_secs(2), now + Duration::from_millis(2500));
+    assert_eq!(replies, vec![reply_to(1, &client_reply(0))]);
+    // The client wakes up and answers both: the first was already answered.
+    assert_eq!(pings.reply(&client_reply(1), now + Duration::from_secs(3)), PingAction::Drop);
+    assert_eq!(pings.reply(&client_reply(2), now + Duration::from_secs(3)), PingAction::Forward);
+    assert!(pings.expire(Duration::from_secs(2), now + Duration::from_secs(10)).is_empty());
+}
+
+#[test]
+fn answers_right_away_without_a_client() {
+    let mut pings = Pings::default();
+    match pings.request(&PingRequest { time: 7 }, false, Instant::now()) {
+        PingAction::Answer(reply) => {
+            assert_eq!(reply.time, 7);
+            assert_eq!(reply.hash, proton_hash(&7u32.to_le_bytes()));
+        }
+        action => panic!("expected an answer, got {:?}", action),
+    }
+}
@@ -0,0 +1,101 @@
+#![cfg(feature = "plugins")]
+
+use std::path::PathBuf;
+use growtopia_proxy::metrics::Direction;
+use growtopia_proxy::plugins::{Injection, Plugins};
+use growtopia_proxy::rules::Verdict;
+use growtopia_proxy::types::config::{PluginConfig, PluginLimits};
+use growtopia_proxy::variant_handler::console_message;
+
+/// A plugin whose `proxy_on_packet` runs `body`, with `$direction`, `$ptr` and `$len` in scope.
+fn module(body: &str, extra: &str) -> String {
+    format!(
+        r#"(module
+            (import "proxy" "set_packet" (func $set_packet (param i32 i32)))
+            (import "proxy" "inject" (func $inject (param i32 i32 i32)))
+            (memory (export "memory") 1)
+            (func (export "proxy_abi_version") (result i32) i32.const 1)
+            (func (export "proxy_alloc") (param i32) (result i32) i32.const 1024)
+            (func (export "proxy_on_packet") (param $direction i32) (param $ptr i32) (param $len i32) (result i32)
+                {}
+            )
+            {}
+        )"#,
+        body, extra
+    )
+}
+
+fn write(name: &str, source: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("growtopia-proxy-plugins-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{}.wasm", name));
+    std::fs::write(&path, wat::parse_str(source).unwrap()).unwrap();
+    path
+}
+
+fn plugins(name: &str, source: &str, config: PluginConfig) -> Plugins {
+    let path = write(name, source);
+    let mut plugins = Plugins::new(config);
+    plugins.load_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    plugins
+}
+
+#[test]
+fn passes_and_drops() {
+    let mut plugins = plugins("drop", &module("(i32.eqz (local.get $direction))", ""), PluginConfig::default());
+    assert_eq!(plugins.len(), 1);
+    let mut data = console_message("hello");
+    assert_eq!(plugins.run(Direction::FromServer, &mut data), Verdict::Pass);
+    assert_eq!(plugins.run(Direction::FromClient, &mut data), Verdict::Drop);
+    assert_eq!(data, console_message("hello"));
+}
+
+#[test]
+fn replaces_and_injects() {
+    let text = b"\x02\x00\x00\x00action|input\n|text|hi";
+    let data_segment = format!(r#"(data (i32.const 0) "{}")"#, text.iter().map(|byte| format!("\\{:02x}", byte)).collect::<String>());
+    let body = format!(
+        "(call $inject (i32.const 0) (local.get $ptr) (local.get $len)) (call $set_packet (i32.const 0) (i32.const {})) i32.const 2",
+        text.len()
+    );
+    let mut plugins = plugins("replace", &module(&body, &data_segment), PluginConfig::default());
+    let original = console_message("hello");
+    let mut data = original.clone();
+    assert_eq!(plugins.run(Direction::FromClient, &mut data), Verdict::Modified);
+    assert_eq!(data, text);
+    assert_eq!(plugins.take_injected(), vec![Injection { to_client: true, data: original }]);
+    assert!(plugins.take_injected().is_empty());
+}
+
+#[test]
+fn disables_plugins_that_run_out_of_fuel() {
+    let body = "(loop $forever (br $forever)) i32.const 1";
+    let config = PluginConfig { limits: PluginLimits { fuel: 10_000, ..Default::default() }, ..Default::default() };
+    let mut plugins = plugins("spin", &module(body, ""), config);
+    let mut data = console_message("hello");
+    assert_eq!(plugins.run(Direction::FromServer, &mut data), Verdict::Pass);
+    assert_eq!(plugins.run(Direction::FromServer, &mut data), Verdict::Pass);
+    assert_eq!(data, console_message("hello"));
+}
+
+#[test]
+fn limits_memory_per_plugin() {
+    // Drops the packet when growing memory by 64 pages (4 MiB) succeeds.
+    let body = "(i32.ne (memory.grow (i32.const 64)) (i32.const -1))";
+    let mut config = PluginConfig::default();
+    config.overrides.insert("small".to_string(), PluginLimits { max_memory: 2 * 65536, ..Default::default() });
+    let mut data = console_message("hello");
+    assert_eq!(plugins("small", &module(body, ""), config.clone()).run(Direction::FromServer, &mut data), Verdict::Pass);
+    assert_eq!(plugins("large", &module(body, ""), config).run(Direction::FromServer, &mut data), Verdict::Drop);
+}
+
+#[test]
+fn rejects_other_abi_versions() {
+    let source = module("i32.const 0", "").replace("(result i32) i32.const 1)", "(result i32) i32.const 2)");
+    let path = write("version", &source);
+    let mut plugins = Plugins::default();
+    assert!(plugins.load_file(&path).unwrap_err().contains("ABI version 2"));
+    assert!(plugins.is_empty());
+    std::fs::remove_file(&path).unwrap();
+}
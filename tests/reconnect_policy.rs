@@ -0,0 +1,21 @@
+use std::time::Duration;
+use growtopia_proxy::types::config::ReconnectPolicy;
+
+#[test]
+fn delay_doubles_up_to_the_cap() {
+    let policy = ReconnectPolicy {
+        enabled: true,
+        initial_delay_ms: 500,
+        max_delay_ms: 3000,
+        max_attempts: 10,
+    };
+    let delays: Vec<Duration> = (1..=6).map(|attempt| policy.delay(attempt)).collect();
+    assert_eq!(delays, [500, 1000, 2000, 3000, 3000, 3000].map(Duration::from_millis));
+    assert_eq!(policy.delay(200), Duration::from_millis(3000));
+}
+
+#[test]
+fn missing_policy_defaults_to_disabled() {
+    let config: growtopia_proxy::types::config::Config = serde_json::from_str(r#"{"web_server_port": 443, "enet_server_port": 17111}"#).unwrap();
+    assert!(!config.reconnect.enabled);
+}
@@ -0,0 +1,104 @@
+use std::thread;
+use growtopia_proxy::filter::FilterInput;
+use growtopia_proxy::metrics::Direction;
+use growtopia_proxy::packet_handler::{decode, encode, GamePacket};
+use growtopia_proxy::rules::{self, Rules, Verdict};
+use growtopia_proxy::types::config::Config;
+use growtopia_proxy::utils::text_parse::set_field;
+use growtopia_proxy::utils::variant::{Variant, VariantList};
+use growtopia_proxy::utils::watch::CHECK_INTERVAL;
+use growtopia_proxy::variant_handler::console_message;
+
+fn rules(json: &str) -> Rules {
+    let config: Config = serde_json::from_str(&format!(r#"{{"web_server_port": 443, "enet_server_port": 17111, "rules": {}}}"#, json)).unwrap();
+    Rules::new(&config.rules)
+}
+
+fn apply(rules: &Rules, data: &[u8]) -> (Verdict, Vec<u8>) {
+    let (packet_type, mut packet) = decode(data).unwrap();
+    let input = FilterInput { direction: Direction::FromServer, channel_id: 0, size: data.len(), packet_type, packet: None };
+    let verdict = rules.apply(&input, &mut packet);
+    (verdict, encode(packet_type, &mut packet, data).unwrap_or_else(|| data.to_vec()))
+}
+
+fn variants(data: &[u8]) -> VariantList {
+    match decode(data).unwrap().1 {
+        GamePacket::Tank { variants: Some(variants), .. } => variants,
+        other => panic!("not a function call: {:?}", other),
+    }
+}
+
+#[test]
+fn drops_matching_packets() {
+    let rules = rules(r#"[{"match": "fn==\"OnConsoleMessage\" && var.1 ~= \"spam\"", "action": "drop"}]"#);
+    assert_eq!(apply(&rules, &console_message("buy spam")).0, Verdict::Drop);
+    assert_eq!(apply(&rules, &console_message("hello")).0, Verdict::Pass);
+}
+
+#[test]
+fn replaces_strings() {
+    let rules = rules(r#"[{"match": "fn==\"OnConsoleMessage\"", "action": "replace", "find": "world", "replace": "proxy"}]"#);
+    let (verdict, data) = apply(&rules, &console_message("hello world"));
+    assert_eq!(verdict, Verdict::Modified);
+    assert_eq!(variants(&data).get(1), Some(&Variant::String("hello proxy".to_string())));
+    assert_eq!(data, console_message("hello proxy"));
+}
+
+#[test]
+fn sets_fields_and_variants() {
+    let rules = rules(
+        r#"[
+            {"match": "fn==\"OnConsoleMessage\"", "action": "set_field", "key": "mstate", "value": "1"},
+            {"match": "fn==\"OnConsoleMessage\"", "action": "set_variant", "index": 0, "value": "OnTextOverlay"},
+            {"match": "fn==\"OnConsoleMessage\"", "action": "drop", "enabled": false}
+        ]"#,
+    );
+    assert_eq!(rules.len(), 2);
+    let (verdict, data) = apply(&rules, &console_message("spawn|avatar\nmstate|0\nname|x\n"));
+    assert_eq!(verdict, Verdict::Modified);
+    let variants = variants(&data);
+    assert_eq!(variants.get(0), Some(&Variant::String("OnTextOverlay".to_string())));
+    assert_eq!(variants.get(1), Some(&Variant::String("spawn|avatar\nmstate|1\nname|x\n".to_string())));
+}
+
+#[test]
+fn rewrites_text_packets() {
+    let rules = rules(r#"[{"match": "type==GenericText", "action": "set_field", "key": "country", "value": "us"}]"#);
+    let mut data = vec![2, 0, 0, 0];
+    data.extend_from_slice(b"tankIDName|a\ncountry|id\n");
+    let (verdict, data) = apply(&rules, &data);
+    assert_eq!(verdict, Verdict::Modified);
+    assert_eq!(&data[4..], b"tankIDName|a\ncountry|us\n");
+}
+
+#[test]
+fn skips_invalid_expressions() {
+    assert!(rules(r#"[{"match": "nope ==", "action": "drop"}]"#).is_empty());
+}
+
+#[test]
+fn set_field_keeps_line_order() {
+    assert_eq!(set_field("a|1\nb|2", "b", "3"), "a|1\nb|3");
+    assert_eq!(set_field("a|1\n", "c", "3"), "a|1\nc|3\n");
+    assert_eq!(set_field("action|input\n|text|hi", "text", "/help"), "action|input\n|text|/help");
+}
+
+#[test]
+fn keeps_rules_while_the_config_is_broken() {
+    let dir = std::env::temp_dir().join(format!("growtopia-proxy-rules-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::env::set_current_dir(&dir).unwrap();
+    let drop_spam = r#"{"web_server_port": 443, "enet_server_port": 17111, "rules": [{"match": "var.1 ~= \"spam\"", "action": "drop"}]}"#;
+    std::fs::write("config.json", drop_spam).unwrap();
+    let run = |data: &[u8]| {
+        let (packet_type, mut packet) = decode(data).unwrap();
+        let input = FilterInput { direction: Direction::FromServer, channel_id: 0, size: data.len(), packet_type, packet: None };
+        rules::apply(&input, &mut packet)
+    };
+    assert_eq!(run(&console_message("buy spam")), Verdict::Drop);
+
+    thread::sleep(CHECK_INTERVAL);
+    std::fs::write("config.json", &drop_spam[..20]).unwrap();
+    assert_eq!(run(&console_message("buy spam")), Verdict::Drop);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,132 @@
+use growtopia_proxy::metrics::Direction;
+use growtopia_proxy::packet_handler::{decode, encode, GamePacket};
+use growtopia_proxy::rules::Verdict;
+use growtopia_proxy::scripting::Scripts;
+use growtopia_proxy::types::etank_packet_type::ETankPacketType;
+use growtopia_proxy::types::packet_flag::PacketFlag;
+use growtopia_proxy::types::tank_packet::TankPacket;
+use growtopia_proxy::utils::variant::Variant;
+use growtopia_proxy::variant_handler::console_message;
+
+fn scripts(name: &str, source: &str) -> Scripts {
+    let dir = std::env::temp_dir().join(format!("growtopia-proxy-scripts-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("test.rhai"), source).unwrap();
+    let mut scripts = Scripts::new();
+    scripts.load_dir(&dir);
+    std::fs::remove_dir_all(&dir).unwrap();
+    scripts
+}
+
+fn run(scripts: &Scripts, direction: Direction, data: &[u8]) -> (Verdict, Vec<u8>) {
+    let (packet_type, mut packet) = decode(data).unwrap();
+    let verdict = scripts.run(direction, packet_type, &mut packet);
+    (verdict, encode(packet_type, &mut packet, data).unwrap())
+}
+
+#[test]
+fn rewrites_variants() {
+    let scripts = scripts(
+        "variants",
+        r#"
+            on_variant("OnConsoleMessage", |vars| {
+                if vars.direction == "server" && vars.len() == 2 {
+                    let message = vars[1];
+                    message.replace("world", "proxy");
+                    vars[1] = message;
+                }
+            });
+        "#,
+    );
+    assert_eq!(scripts.len(), 1);
+    let (verdict, data) = run(&scripts, Direction::FromServer, &console_message("hello world"));
+    assert_eq!(verdict, Verdict::Modified);
+    assert_eq!(data, console_message("hello proxy"));
+    assert_eq!(run(&scripts, Direction::FromClient, &console_message("hello world")).0, Verdict::Pass);
+}
+
+#[test]
+fn rejects_values_that_are_not_variants() {
+    for assignment in ["vars[1] = ()", "vars[9] = \"x\"", "vars[-1] = \"x\"", "vars.push(())"] {
+        let scripts = scripts(
+            "invalid-variants",
+            &format!(r#"on_variant("OnConsoleMessage", |vars| {{ {}; vars[1] = "changed"; }});"#, assignment),
+        );
+        assert_eq!(run(&scripts, Direction::FromServer, &console_message("hello")).1, console_message("hello"), "{}", assignment);
+    }
+}
+
+#[test]
+fn drops_and_edits_tank_packets() {
+    let scripts = scripts(
+        "tank",
+        r#"
+            on_tank("TileChangeRequest", |tank| {
+                if tank.has_flag("punch") { return false; }
+                tank.int_x += 1;
+                tank.set_flag("place", true);
+            });
+        "#,
+    );
+    let mut punch = TankPacket { r#type: ETankPacketType::NetGamePacketTileChangeRequest, flags: PacketFlag::PUNCH, ..Default::default() };
+    assert_eq!(run(&scripts, Direction::FromClient, &punch.to_message(&[], &[])).0, Verdict::Drop);
+
+    let mut place = TankPacket { r#type: ETankPacketType::NetGamePacketTileChangeRequest, int_x: 4, ..Default::default() };
+    let (verdict, data) = run(&scripts, Direction::FromClient, &place.to_message(&[], &[]));
+    assert_eq!(verdict, Verdict::Modified);
+    let (decoded, _, _) = TankPacket::decode(&data[4..]).unwrap();
+    assert_eq!(decoded.int_x, 5);
+    assert!(decoded.flags.contains(PacketFlag::PLACE));
+}
+
+#[test]
+fn edits_text_fields() {
+    let scripts = scripts("text", r#"on_text(|text| if text.get("action") == "input" { text.set("text", "/help"); });"#);
+    let mut data = vec![2, 0, 0, 0];
+    data.extend_from_slice(b"action|input\n|text|hi");
+    let (verdict, data) = run(&scripts, Direction::FromClient, &data);
+    assert_eq!(verdict, Verdict::Modified);
+    match decode(&data).unwrap().1 {
+        GamePacket::Text(text) => assert_eq!(text, "action|input\n|text|/help"),
+        other => panic!("not text: {:?}", other),
+    }
+}
+
+#[test]
+fn reads_chat_text() {
+    let scripts = scripts("chat", r#"on_text(|text| if text.get("text") == "/ping" && text.get("missing") == () { text.set("text", "pong"); });"#);
+    let mut data = vec![2, 0, 0, 0];
+    data.extend_from_slice(b"action|input\n|text|/ping");
+    let (verdict, data) = run(&scripts, Direction::FromClient, &data);
+    assert_eq!(verdict, Verdict::Modified);
+    assert_eq!(&data[4..], b"action|input\n|text|pong");
+}
+
+#[test]
+fn skips_broken_scripts() {
+    assert!(scripts("unknown", r#"on_tank("NotAType", |tank| false);"#).is_empty());
+    assert!(scripts("syntax", "on_text(|text| ").is_empty());
+    let looping = scripts("loop", "on_text(|text| { loop {} });");
+    let mut data = vec![2, 0, 0, 0];
+    data.extend_from_slice(b"action|input");
+    assert_eq!(run(&looping, Direction::FromClient, &data).0, Verdict::Pass);
+}
+
+#[test]
+fn converts_numbers_by_existing_type() {
+    let scripts = scripts("numbers", r#"on_variant("OnSetPos", |vars| { vars[1] = [1, 2.5]; vars[2] = 7; });"#);
+    let mut variants = growtopia_proxy::utils::variant::VariantList::new();
+    variants.push(Variant::String("OnSetPos".to_string()));
+    variants.push(Variant::Vec2((0.0, 0.0)));
+    variants.push(Variant::Unsigned(0));
+    let data = growtopia_proxy::variant_handler::call_function(&variants, 1);
+    let (_, data) = run(&scripts, Direction::FromServer, &data);
+    match decode(&data).unwrap().1 {
+        GamePacket::Tank { variants: Some(variants), .. } => {
+            assert_eq!(variants.get(1), Some(&Variant::Vec2((1.0, 2.5))));
+            assert_eq!(variants.get(2), Some(&Variant::Unsigned(7)));
+        }
+        other => panic!("not a call: {:?}", other),
+    }
+}
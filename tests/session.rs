@@ -0,0 +1,295 @@
+use std::net::SocketAddr;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use rusty_enet::Packet;
+use growtopia_proxy::global;
+use growtopia_proxy::session::{self, ConnectionState, Redirect};
+
+/// The session is global, so the tests take turns in a directory with their own `config.json`.
+fn setup(config: &str) -> MutexGuard<'static, ()> {
+    static TURN: Mutex<()> = Mutex::new(());
+    static DIR: OnceLock<()> = OnceLock::new();
+    let turn = TURN.lock().unwrap_or_else(|e| e.into_inner());
+    DIR.get_or_init(|| {
+        let dir = std::env::temp_dir().join(format!("growtopia-proxy-session-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::env::set_current_dir(&dir).unwrap();
+    });
+    std::fs::write("config.json", format!(r#"{{"web_server_port": 443, "enet_server_port": 17111{}}}"#, config)).unwrap();
+    session::reset();
+    turn
+}
+
+fn address(port: u16) -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], port))
+}
+
+fn state() -> ConnectionState {
+    global().session.lock().unwrap().state.clone()
+}
+
+fn redirect(port: u16) -> Redirect {
+    Redirect { address: address(port), token: 1, user: 2, door_id: String::new(), uuid: "uuid".to_string() }
+}
+
+fn queues(channel_id: u8) -> bool {
+    session::queue_upstream(channel_id, Packet::reliable(&[channel_id])).is_none()
+}
+
+#[test]
+fn connects_and_flushes_the_queue() {
+    let _turn = setup("");
+    let id = session::id();
+    session::connect(address(1));
+    assert_eq!(session::id(), id + 1);
+    assert!(matches!(state(), ConnectionState::Connecting { address: a, attempts: 1, .. } if a == address(1)));
+    assert!(queues(0) && queues(1));
+    session::upstream_connected();
+    assert_eq!(state(), ConnectionState::Connected { address: address(1) });
+    assert!(!queues(0));
+}
+
+#[test]
+fn retries_then_gives_up() {
+    let _turn = setup("");
+    session::connect(address(1));
+    session::check_timeout();
+    assert!(matches!(state(), ConnectionState::Connecting { attempts: 1, .. }));
+    for attempts in 2..=3 {
+        session::upstream_disconnected();
+        assert!(matches!(state(), ConnectionState::Connecting { attempts: a, .. } if a == attempts));
+        assert!(queues(0));
+    }
+    session::upstream_disconnected();
+    assert_eq!(state(), ConnectionState::Idle);
+    assert!(!queues(0));
+}
+
+#[test]
+fn follows_a_redirect() {
+    let _turn = setup("");
+    session::connect(address(1));
+    session::upstream_connected();
+    session::redirect(redirect(2));
+    assert_eq!(state(), ConnectionState::Redirecting { from: address(1), redirect: redirect(2) });
+    assert!(!queues(0));
+    assert!(session::begin_reconnect());
+    assert!(matches!(state(), ConnectionState::Reconnecting { redirect: r, attempts: 1, .. } if r == redirect(2)));
+    assert!(session::begin_reconnect());
+    assert!(queues(0));
+    session::upstream_connected();
+    assert_eq!(state(), ConnectionState::Connected { address: address(2) });
+}
+
+#[test]
+fn reconnects_when_the_old_sub_server_hangs_up_first() {
+    let _turn = setup("");
+    session::connect(address(1));
+    session::upstream_connected();
+    session::redirect(redirect(2));
+    session::upstream_disconnected();
+    assert!(matches!(state(), ConnectionState::Reconnecting { redirect: r, .. } if r == redirect(2)));
+}
+
+#[test]
+fn ends_when_connected_upstream_drops_without_a_policy() {
+    let _turn = setup("");
+    assert!(!session::begin_reconnect());
+    session::connect(address(1));
+    session::upstream_connected();
+    session::upstream_disconnected();
+    assert_eq!(state(), ConnectionState::Idle);
+}
+
+const RESUME: &str = r#", "reconnect": {"enabled": true, "initial_delay_ms": 100, "max_delay_ms": 1000, "max_attempts": 1}"#;
+
+fn resuming() -> (Instant, u32, bool) {
+    match state() {
+        ConnectionState::Resuming { retry_at, attempts, connected, .. } => (retry_at, attempts, connected),
+        state => panic!("expected resuming, got {:?}", state),
+    }
+}
+
+/// Connects, logs in and loses the connection with the reconnect policy on.
+fn lose_connection() -> Instant {
+    session::connect(address(1));
+    session::upstream_connected();
+    session::remember_login(0, Packet::reliable(b"\x02\0\0\0tankIDName|"));
+    let lost_at = Instant::now();
+    session::upstream_disconnected();
+    lost_at
+}
+
+#[test]
+fn resumes_with_backoff() {
+    let _turn = setup(RESUME);
+    let lost_at = lose_connection();
+    let (retry_at, attempts, _) = resuming();
+    assert_eq!(attempts, 0);
+    assert!(retry_at >= lost_at + Duration::from_millis(100));
+    assert!(queues(0));
+    session::check_timeout();
+    assert_eq!(resuming().1, 0);
+
+    thread::sleep(Duration::from_millis(100));
+    let attempted_at = Instant::now();
+    session::check_timeout();
+    let (retry_at, attempts, connected) = resuming();
+    assert_eq!((attempts, connected), (1, false));
+    assert!(retry_at >= attempted_at + session::CONNECT_TIMEOUT + Duration::from_millis(200));
+
+    session::upstream_connected();
+    assert!(resuming().2);
+    assert!(session::resume_login());
+    assert_eq!(state(), ConnectionState::Connected { address: address(1) });
+    assert!(!session::resume_login());
+}
+
+#[test]
+fn gives_up_resuming_after_the_last_attempt() {
+    let _turn = setup(RESUME);
+    lose_connection();
+    thread::sleep(Duration::from_millis(100));
+    session::check_timeout();
+    session::upstream_disconnected();
+    let (retry_at, attempts, _) = resuming();
+    assert_eq!(attempts, 1);
+    assert!(retry_at <= Instant::now() + Duration::from_millis(200));
+    thread::sleep(Duration::from_millis(200));
+    session::check_timeout();
+    assert_eq!(state(), ConnectionState::Idle);
+}
+
+#[test]
+fn does_not_resume_without_a_login() {
+    let _turn = setup(RESUME);
+    session::connect(address(1));
+    session::upstream_connected();
+    session::upstream_disconnected();
+    assert_eq!(state(), ConnectionState::Idle);
+}
+
+const KEEP_ALIVE: &str = r#", "headless": {"keep_alive": true}"#;
+
+const LOGIN: &str = "tankIDName|proxy";
+
+#[test]
+fn detaches_while_connected() {
+    let _turn = setup(KEEP_ALIVE);
+    session::connect(address(1));
+    session::upstream_connected();
+    assert!(!session::detach());
+    session::remember_login(0, Packet::reliable(b"\x02\0\0\0tankIDName|"));
+    assert!(!session::is_headless());
+    assert!(session::detach());
+    assert!(session::is_headless());
+    assert_eq!(state(), ConnectionState::Connected { address: address(1) });
+}
+
+#[test]
+fn does_not_detach_without_keep_alive() {
+    let _turn = setup("");
+    session::connect(address(1));
+    session::upstream_connected();
+    session::remember_login(0, Packet::reliable(b"\x02\0\0\0tankIDName|"));
+    assert!(!session::detach());
+    assert!(!session::is_headless());
+}
+
+#[test]
+fn starts_headless_with_its_login() {
+    let _turn = setup("");
+    assert!(session::start_headless(address(1), LOGIN));
+    assert!(session::is_headless());
+    assert_eq!(session::login_text().as_deref(), Some(LOGIN));
+    session::connect(address(1));
+    assert!(!session::is_headless());
+}
+
+#[test]
+fn starts_headless_only_when_idle() {
+    let _turn = setup("");
+    session::connect(address(1));
+    let id = session::id();
+    assert!(!session::start_headless(address(2), LOGIN));
+    assert_eq!(session::id(), id);
+    assert!(!session::is_headless());
+    assert!(matches!(state(), ConnectionState::Connecting { address: a, .. } if a == address(1)));
+    session::reset();
+    assert!(session::start_headless(address(2), LOGIN));
+    assert!(!session::start_headless(address(2), LOGIN));
+}
+
+#[test]
+fn follows_a_redirect_while_headless() {
+    let _turn = setup("");
+    assert!(session::start_headless(address(1), LOGIN));
+    session::upstream_connected();
+    session::redirect(redirect(2));
+    assert!(matches!(state(), ConnectionState::Reconnecting { redirect: r, attempts: 1, .. } if r == redirect(2)));
+    assert_eq!(session::login_text(), Some(session::apply_redirect(LOGIN, &redirect(2))));
+    session::upstream_connected();
+    assert_eq!(state(), ConnectionState::Connected { address: address(2) });
+    assert!(session::is_headless());
+}
+
+#[test]
+fn attaches_while_headless() {
+    let _turn = setup("");
+    assert!(session::start_headless(address(1), LOGIN));
+    session::upstream_connected();
+    session::redirect(redirect(2));
+    session::upstream_connected();
+    assert_eq!(session::attach_login("tankIDName|client"), None);
+
+    session::client_connected();
+    assert!(!session::is_headless());
+    assert!(matches!(state(), ConnectionState::Connecting { address: a, attempts: 1, .. } if a == address(2)));
+    assert_eq!(session::attach_login("tankIDName|client"), Some(session::apply_redirect("tankIDName|client", &redirect(2))));
+    assert_eq!(session::attach_login("tankIDName|client"), None);
+}
+
+#[test]
+fn attaches_once_the_headless_session_connects() {
+    let _turn = setup("");
+    assert!(session::start_headless(address(1), LOGIN));
+    session::upstream_connected();
+    session::redirect(redirect(2));
+    session::client_connected();
+    assert!(session::is_headless());
+    assert!(matches!(state(), ConnectionState::Reconnecting { .. }));
+    assert_eq!(session::attach_login("tankIDName|client"), None);
+
+    session::upstream_connected();
+    assert!(!session::is_headless());
+    assert_eq!(state(), ConnectionState::Connected { address: address(2) });
+    assert!(!session::headless_login());
+    assert_eq!(session::attach_login("tankIDName|client"), Some(session::apply_redirect("tankIDName|client", &redirect(2))));
+}
+
+#[test]
+fn stays_headless_when_the_client_leaves_before_attaching() {
+    let _turn = setup("");
+    assert!(session::start_headless(address(1), LOGIN));
+    session::client_connected();
+    session::client_disconnected();
+    session::upstream_connected();
+    assert!(session::is_headless());
+    assert_eq!(state(), ConnectionState::Connected { address: address(1) });
+}
+
+#[test]
+fn logs_in_headless_once_connected() {
+    let _turn = setup("");
+    session::connect(address(1));
+    session::upstream_connected();
+    assert!(!session::headless_login());
+    session::reset();
+    assert!(session::start_headless(address(1), LOGIN));
+    assert!(!session::headless_login());
+    session::upstream_connected();
+    assert!(session::headless_login());
+    session::reset();
+    assert!(!session::headless_login());
+}
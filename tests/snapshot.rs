@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+use growtopia_proxy::snapshot::{diff, list, Snapshot};
+use growtopia_proxy::types::config::SnapshotConfig;
+use growtopia_proxy::types::world_data::{DroppedItem, Tile, TileExtra, WorldData};
+use growtopia_proxy::world::World;
+
+fn tile(foreground: u16, background: u16) -> Tile {
+    Tile { foreground, background, ..Default::default() }
+}
+
+/// A 3x2 world: dirt, lava and nothing on top, a lock owning the tile next to it, a background
+/// and an empty tile below.
+fn world() -> World {
+    let mut lock = tile(242, 0);
+    lock.extra = Some(TileExtra::Lock { settings: 0, owner_uid: 1234, access_uids: vec![5], minimum_level: 0 });
+    let mut locked = tile(0, 0);
+    locked.lock_index = Some(2);
+    let mut world = World::default();
+    world.load(WorldData {
+        name: "START".to_string(),
+        width: 3,
+        height: 2,
+        tiles: vec![tile(2, 14), locked, lock, tile(0, 14), tile(0, 0), tile(6, 0)],
+        dropped: vec![DroppedItem { uid: 1, item_id: 112, x: 0.0, y: 0.0, amount: 3, flags: 0 }],
+        last_dropped_uid: 1,
+        unparsed_from: None,
+    });
+    world
+}
+
+fn config(dir: &str) -> SnapshotConfig {
+    SnapshotConfig {
+        dir: dir.to_string(),
+        tile_pixels: 2,
+        palette: BTreeMap::from([(2, "#8b5a2b".to_string()), (14, "#5a3e28".to_string())]),
+        empty: "#60a8e0".to_string(),
+    }
+}
+
+#[test]
+fn takes_a_snapshot_of_the_world() {
+    assert_eq!(Snapshot::take(&World::default()), None);
+    let snapshot = Snapshot::take(&world()).unwrap();
+    assert_eq!((snapshot.name.as_str(), snapshot.width, snapshot.height), ("START", 3, 2));
+    assert_eq!(snapshot.tiles.len(), 6);
+    assert_eq!(snapshot.locks.len(), 1);
+    let lock = &snapshot.locks[0];
+    assert_eq!((lock.x, lock.y, lock.item_id, lock.owner_uid, lock.tiles), (2, 0, 242, Some(1234), 1));
+    assert_eq!(snapshot.id(), format!("START-{}", snapshot.taken_at));
+
+    let json = serde_json::to_string(&snapshot).unwrap();
+    assert_eq!(serde_json::from_str::<Snapshot>(&json).unwrap(), snapshot);
+}
+
+#[test]
+fn renders_tiles_with_the_palette() {
+    let snapshot = Snapshot::take(&world()).unwrap();
+    let png = snapshot.render_png(&config("snapshots")).unwrap();
+    let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!((info.width, info.height, info.color_type), (6, 4, png::ColorType::Rgb));
+    let pixel = |x: usize, y: usize| &pixels[(y * 6 + x) * 3..(y * 6 + x) * 3 + 3];
+    // Foreground colour over the background, and both pixels of the tile's row.
+    assert_eq!(pixel(0, 0), [0x8b, 0x5a, 0x2b]);
+    assert_eq!(pixel(1, 1), [0x8b, 0x5a, 0x2b]);
+    // A background on its own is drawn at half brightness.
+    assert_eq!(pixel(0, 2), [0x2d, 0x1f, 0x14]);
+    assert_eq!(pixel(2, 3), [0x60, 0xa8, 0xe0]);
+    // Items missing from the palette still get a colour.
+    assert_ne!(pixel(4, 2), [0x60, 0xa8, 0xe0]);
+}
+
+#[test]
+fn diffs_two_snapshots() {
+    let mut world = world();
+    let before = Snapshot::take(&world).unwrap();
+    assert!(diff(&before, &before).is_empty());
+
+    *world.tile_mut(1, 1).unwrap() = tile(8, 0);
+    world.dropped.clear();
+    world.dropped.push(DroppedItem { uid: 2, item_id: 242, ..Default::default() });
+    let after = Snapshot::take(&world).unwrap();
+    let changes = diff(&before, &after);
+    assert_eq!(changes.tiles.len(), 1);
+    assert_eq!((changes.tiles[0].x, changes.tiles[0].y, changes.tiles[0].after.foreground), (1, 1, 8));
+    assert_eq!(changes.dropped_added.iter().map(|item| item.uid).collect::<Vec<_>>(), vec![2]);
+    assert_eq!(changes.dropped_removed.iter().map(|item| item.uid).collect::<Vec<_>>(), vec![1]);
+    assert!(changes.locks_added.is_empty() && changes.locks_removed.is_empty());
+}
+
+#[test]
+fn saves_and_loads_snapshots() {
+    let dir = std::env::temp_dir().join(format!("growtopia-proxy-snapshots-{}", std::process::id()));
+    let config = config(dir.to_str().unwrap());
+    let snapshot = Snapshot::take(&world()).unwrap();
+    let path = snapshot.save(&config).unwrap();
+    assert!(path.with_extension("png").exists());
+    assert_eq!(list(&config), vec![snapshot.id()]);
+    assert_eq!(Snapshot::load(&config, &snapshot.id()).unwrap(), snapshot);
+    assert!(Snapshot::load(&config, "../config").is_err());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn keeps_unsafe_world_names_inside_the_snapshot_dir() {
+    let dir = std::env::temp_dir().join(format!("growtopia-proxy-snapshot-names-{}", std::process::id()));
+    let config = config(dir.join("snapshots").to_str().unwrap());
+    let mut snapshot = Snapshot::take(&world()).unwrap();
+    snapshot.name = "../ESCAPE.ME".to_string();
+    assert_eq!(snapshot.id(), format!("___ESCAPE_ME-{}", snapshot.taken_at));
+    let path = snapshot.save(&config).unwrap();
+    assert_eq!(path.parent(), Some(dir.join("snapshots").as_path()));
+    assert_eq!(Snapshot::load(&config, &snapshot.id()).unwrap(), snapshot);
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,88 @@
+use byteorder::{ByteOrder, LittleEndian};
+use growtopia_proxy::types::etank_packet_type::ETankPacketType;
+use growtopia_proxy::types::packet_flag::PacketFlag;
+use growtopia_proxy::types::tank_packet::{TankPacket, TankPacketError, TANK_PACKET_SIZE};
+use proptest::prelude::*;
+
+fn tank_packet() -> impl Strategy<Value = TankPacket> {
+    (
+        (any::<u8>(), any::<u8>(), any::<u8>(), any::<u8>(), any::<u32>(), any::<u32>(), any::<u32>()),
+        (any::<f32>(), any::<u32>(), any::<f32>(), any::<f32>(), any::<f32>(), any::<f32>(), any::<f32>()),
+        (any::<i32>(), any::<i32>(), any::<u32>()),
+    )
+        .prop_map(|((kind, unk1, unk2, unk3, net_id, sec_id, flags), (unk6, value, vector_x, vector_y, vector_x2, vector_y2, unk12), (int_x, int_y, extended_data_length))| TankPacket {
+            r#type: ETankPacketType::from(kind),
+            unk1,
+            unk2,
+            unk3,
+            net_id,
+            sec_id,
+            flags: PacketFlag::from_u32(flags),
+            unk6,
+            value,
+            vector_x,
+            vector_y,
+            vector_x2,
+            vector_y2,
+            unk12,
+            int_x,
+            int_y,
+            extended_data_length,
+        })
+}
+
+proptest! {
+    #[test]
+    fn layout_matches_wire_header(packet in tank_packet()) {
+        let data = packet.encode();
+        prop_assert_eq!(data.len(), TANK_PACKET_SIZE);
+        prop_assert_eq!(data[0], u8::from(packet.r#type));
+        prop_assert_eq!(LittleEndian::read_u32(&data[4..8]), packet.net_id);
+        prop_assert_eq!(LittleEndian::read_u32(&data[8..12]), packet.sec_id);
+        prop_assert_eq!(LittleEndian::read_u32(&data[12..16]), packet.flags.to_u32());
+        prop_assert_eq!(LittleEndian::read_u32(&data[20..24]), packet.value);
+        prop_assert_eq!(LittleEndian::read_i32(&data[44..48]), packet.int_x);
+        prop_assert_eq!(LittleEndian::read_i32(&data[48..52]), packet.int_y);
+        prop_assert_eq!(LittleEndian::read_u32(&data[52..56]), packet.extended_data_length);
+    }
+
+    #[test]
+    fn headers_round_trip(mut data in any::<[u8; TANK_PACKET_SIZE]>()) {
+        LittleEndian::write_u32(&mut data[52..56], 0);
+        let (packet, extended_data, trailing) = TankPacket::decode(&data).unwrap();
+        prop_assert!(extended_data.is_empty() && trailing.is_empty());
+        prop_assert_eq!(packet.encode(), data);
+    }
+
+    #[test]
+    fn extended_data_is_split_from_trailing(
+        mut packet in tank_packet(),
+        extended in proptest::collection::vec(any::<u8>(), 0..64),
+        trailing in proptest::collection::vec(any::<u8>(), 0..8),
+    ) {
+        let message = packet.to_message(&extended, &trailing);
+        prop_assert_eq!(packet.extended_data_length as usize, extended.len());
+        let (decoded, decoded_extended, decoded_trailing) = TankPacket::decode(&message[4..]).unwrap();
+        prop_assert_eq!(decoded.encode(), packet.encode());
+        prop_assert_eq!(decoded_extended, &extended[..]);
+        prop_assert_eq!(decoded_trailing, &trailing[..]);
+    }
+
+    #[test]
+    fn oversized_extended_data_length_is_rejected(mut packet in tank_packet(), extended in proptest::collection::vec(any::<u8>(), 0..64)) {
+        packet.extended_data_length = extended.len() as u32 + 1;
+        let mut data = packet.encode().to_vec();
+        data.extend(&extended);
+        let is_rejected = matches!(TankPacket::decode(&data), Err(TankPacketError::ExtendedDataLength { .. }));
+        prop_assert!(is_rejected);
+    }
+
+    #[test]
+    fn unknown_types_are_kept(mut data in any::<[u8; TANK_PACKET_SIZE]>(), kind in 47u8..) {
+        data[0] = kind;
+        LittleEndian::write_u32(&mut data[52..56], 0);
+        let (packet, _, _) = TankPacket::decode(&data).unwrap();
+        prop_assert_eq!(packet.r#type, ETankPacketType::Unknown(kind));
+        prop_assert_eq!(packet.encode(), data);
+    }
+}
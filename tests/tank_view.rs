@@ -0,0 +1,55 @@
+use growtopia_proxy::types::etank_packet_type::ETankPacketType;
+use growtopia_proxy::types::tank_packet::TankPacket;
+use growtopia_proxy::types::tank_view::{ItemChangeObject, PingReply, SetCharacterState, State, TankView, TileChangeRequest};
+use growtopia_proxy::types::vector2::Vector2;
+use proptest::prelude::*;
+
+fn header() -> impl Strategy<Value = [u8; 56]> {
+    any::<[u8; 56]>().prop_map(|mut data| {
+        data[0] = 0;
+        data[52..56].copy_from_slice(&[0; 4]);
+        data
+    })
+}
+
+/// Reading a view and writing it back must leave every byte of the header unchanged.
+fn assert_identity<T: TankView>(data: &[u8; 56]) -> Result<(), TestCaseError> {
+    let mut data = *data;
+    data[0] = T::TYPE.into();
+    let (mut tank_packet, _, _) = TankPacket::decode(&data).unwrap();
+    let view = tank_packet.view::<T>().unwrap();
+    view.write(&mut tank_packet);
+    prop_assert_eq!(tank_packet.encode(), data);
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn views_write_back_what_they_read(data in header()) {
+        assert_identity::<State>(&data)?;
+        assert_identity::<TileChangeRequest>(&data)?;
+        assert_identity::<ItemChangeObject>(&data)?;
+        assert_identity::<SetCharacterState>(&data)?;
+        assert_identity::<PingReply>(&data)?;
+    }
+
+    #[test]
+    fn set_character_state_decodes_ranges(build_range in any::<i8>(), punch_range in any::<i8>()) {
+        let state = SetCharacterState { build_range, punch_range, ..Default::default() };
+        let tank_packet = state.to_tank_packet();
+        prop_assert_eq!(tank_packet.unk2 as i16, build_range as i16 + 128);
+        prop_assert_eq!(tank_packet.unk3 as i16, punch_range as i16 + 128);
+        prop_assert_eq!(tank_packet.view::<SetCharacterState>(), Some(state));
+    }
+
+    #[test]
+    fn tile_change_request_names_its_fields(item_id in any::<u32>(), tile_x in any::<i32>(), tile_y in any::<i32>()) {
+        let request = TileChangeRequest { item_id, tile_x, tile_y, position: Vector2 { x: 1.0, y: 2.0 }, ..Default::default() };
+        let tank_packet = request.to_tank_packet();
+        prop_assert_eq!(tank_packet.r#type, ETankPacketType::NetGamePacketTileChangeRequest);
+        prop_assert_eq!(tank_packet.value, item_id);
+        prop_assert_eq!((tank_packet.int_x, tank_packet.int_y), (tile_x, tile_y));
+        prop_assert_eq!(tank_packet.view::<TileChangeRequest>(), Some(request));
+        prop_assert_eq!(tank_packet.view::<State>(), None);
+    }
+}
@@ -0,0 +1,28 @@
+use std::collections::HashMap;
+use growtopia_proxy::utils::text_parse::{map_to_string, parse_and_store_as_map, parse_and_store_as_vec, strip_colors, vec_to_string};
+use proptest::prelude::*;
+
+proptest! {
+    #[test]
+    fn map_round_trips(map in proptest::collection::hash_map("[^|\r\n]*", "[^\r\n]*", 0..16)) {
+        let parsed: HashMap<String, String> = parse_and_store_as_map(&map_to_string(&map));
+        prop_assert_eq!(parsed, map);
+    }
+
+    #[test]
+    fn vec_round_trips(vec in proptest::collection::vec("([^|]*[^|\\s])?", 1..16)) {
+        prop_assert_eq!(parse_and_store_as_vec(&vec_to_string(&vec)), vec);
+    }
+
+    #[test]
+    fn arbitrary_text_never_panics(input in ".*") {
+        let map = parse_and_store_as_map(&input);
+        prop_assert!(map.keys().all(|key| !key.contains('|')));
+        parse_and_store_as_vec(&input);
+    }
+}
+
+#[test]
+fn strips_color_codes() {
+    assert_eq!(strip_colors("`2Hello ``World`"), "Hello World");
+}
@@ -0,0 +1,125 @@
+use byteorder::{LittleEndian, WriteBytesExt};
+use growtopia_proxy::utils::variant::{Variant, VariantList};
+use proptest::prelude::*;
+
+fn variant() -> impl Strategy<Value = Variant> {
+    prop_oneof![
+        any::<f32>().prop_map(Variant::Float),
+        ".*".prop_map(Variant::String),
+        any::<(f32, f32)>().prop_map(Variant::Vec2),
+        any::<(f32, f32, f32)>().prop_map(Variant::Vec3),
+        any::<(f32, f32, f32, f32)>().prop_map(Variant::Vec4),
+        any::<u32>().prop_map(Variant::Unsigned),
+        any::<i32>().prop_map(Variant::Signed),
+    ]
+}
+
+/// Encodes a single entry the way the game does, with an arbitrary index byte.
+fn encoded_entry() -> impl Strategy<Value = Vec<u8>> {
+    (any::<u8>(), prop_oneof![
+        any::<u32>().prop_map(|v| (1u8, v.to_le_bytes().to_vec())),
+        proptest::collection::vec(any::<u8>(), 0..32).prop_map(|s| {
+            let mut data = Vec::new();
+            data.write_u32::<LittleEndian>(s.len() as u32).unwrap();
+            data.extend_from_slice(&s);
+            (2u8, data)
+        }),
+        any::<[u8; 8]>().prop_map(|v| (3u8, v.to_vec())),
+        any::<[u8; 12]>().prop_map(|v| (4u8, v.to_vec())),
+        any::<[u8; 4]>().prop_map(|v| (5u8, v.to_vec())),
+        any::<[u8; 16]>().prop_map(|v| (8u8, v.to_vec())),
+        any::<[u8; 4]>().prop_map(|v| (9u8, v.to_vec())),
+    ])
+        .prop_map(|(index, (kind, payload))| {
+            let mut entry = vec![index, kind];
+            entry.extend_from_slice(&payload);
+            entry
+        })
+}
+
+fn encoded_list() -> impl Strategy<Value = Vec<u8>> {
+    (
+        proptest::collection::vec(encoded_entry(), 0..8),
+        proptest::option::of((any::<u8>(), 10u8..=255, proptest::collection::vec(any::<u8>(), 0..16))),
+        proptest::collection::vec(any::<u8>(), 0..4),
+    )
+        .prop_map(|(entries, unknown, trailing)| {
+            let mut data = vec![(entries.len() + unknown.is_some() as usize) as u8];
+            for entry in &entries {
+                data.extend_from_slice(entry);
+            }
+            match unknown {
+                Some((index, kind, raw)) => {
+                    data.push(index);
+                    data.push(kind);
+                    data.extend_from_slice(&raw);
+                }
+                None => data.extend_from_slice(&trailing),
+            }
+            data
+        })
+}
+
+proptest! {
+    #[test]
+    fn pushed_variants_round_trip(variants in proptest::collection::vec(variant(), 0..16)) {
+        let mut list = VariantList::new();
+        for variant in &variants {
+            list.push(variant.clone());
+        }
+        let serialized = list.serialize();
+        let decoded = VariantList::deserialize(&serialized).unwrap();
+        prop_assert_eq!(decoded.len(), variants.len());
+        prop_assert_eq!(decoded.serialize(), serialized);
+    }
+
+    #[test]
+    fn encoded_lists_are_byte_exact(data in encoded_list()) {
+        let list = VariantList::deserialize(&data).unwrap();
+        prop_assert_eq!(list.serialize(), data);
+    }
+
+    #[test]
+    fn arbitrary_bytes_never_panic(data in proptest::collection::vec(any::<u8>(), 0..256)) {
+        if let Ok(list) = VariantList::deserialize(&data) {
+            prop_assert_eq!(list.serialize(), data);
+        }
+    }
+
+    #[test]
+    fn invalid_utf8_strings_are_kept(tail in proptest::collection::vec(any::<u8>(), 0..32)) {
+        // 0xff never appears in UTF-8.
+        let mut bytes = vec![0xff];
+        bytes.extend(tail);
+        let mut list = VariantList::new();
+        list.push(Variant::Bytes(bytes.clone()));
+        let decoded = VariantList::deserialize(&list.serialize()).unwrap();
+        prop_assert_eq!(decoded.get(0), Some(&Variant::Bytes(bytes)));
+    }
+}
+
+#[test]
+fn pushes_after_the_highest_index() {
+    // Indices 3 and 1, as a server may send them.
+    let mut data = vec![2, 3, 5];
+    data.extend_from_slice(&7u32.to_le_bytes());
+    data.extend_from_slice(&[1, 9]);
+    data.extend_from_slice(&(-1i32).to_le_bytes());
+    let mut list = VariantList::deserialize(&data).unwrap();
+    assert!(list.push(Variant::Unsigned(8)));
+    let mut expected = data.clone();
+    expected[0] = 3;
+    expected.extend_from_slice(&[4, 5]);
+    expected.extend_from_slice(&8u32.to_le_bytes());
+    assert_eq!(list.serialize(), expected);
+}
+
+#[test]
+fn refuses_to_push_after_an_unknown_variant() {
+    let data = vec![2, 0, 5, 1, 0, 0, 0, 1, 42, 0xaa, 0xbb];
+    let mut list = VariantList::deserialize(&data).unwrap();
+    assert!(matches!(list.get(1), Some(Variant::Unknown { kind: 42, .. })));
+    assert!(!list.push(Variant::Unsigned(8)));
+    assert_eq!(list.len(), 2);
+    assert_eq!(list.serialize(), data);
+}
@@ -0,0 +1,30 @@
+use std::thread;
+use growtopia_proxy::utils::watch::{files, Watch, CHECK_INTERVAL};
+
+#[test]
+fn notices_added_files_once_per_interval() {
+    let dir = std::env::temp_dir().join(format!("growtopia-proxy-watch-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("b.rhai"), "").unwrap();
+    std::fs::write(dir.join("a.rhai"), "").unwrap();
+    std::fs::write(dir.join("notes.txt"), "").unwrap();
+    let names: Vec<_> = files(&dir, "rhai").into_keys().map(|path| path.file_name().unwrap().to_owned()).collect();
+    assert_eq!(names, ["a.rhai", "b.rhai"]);
+
+    let mut watch = Watch::default();
+    assert!(watch.dir_changed(&dir, "rhai"));
+    std::fs::write(dir.join("c.rhai"), "").unwrap();
+    assert!(!watch.dir_changed(&dir, "rhai"));
+    thread::sleep(CHECK_INTERVAL);
+    assert!(watch.dir_changed(&dir, "rhai"));
+    thread::sleep(CHECK_INTERVAL);
+    assert!(!watch.dir_changed(&dir, "rhai"));
+    watch.retry();
+    thread::sleep(CHECK_INTERVAL);
+    assert!(watch.dir_changed(&dir, "rhai"));
+
+    let mut watch = Watch::default();
+    assert!(!watch.file_changed(&dir.join("missing.json")));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
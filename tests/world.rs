@@ -0,0 +1,44 @@
+use growtopia_proxy::types::etank_packet_type::ETankPacketType;
+use growtopia_proxy::types::tank_packet::TankPacket;
+use growtopia_proxy::utils::variant::{Variant, VariantList};
+use growtopia_proxy::world::{parse_spawn, World};
+
+const SPAWN: &str = "spawn|avatar\nnetID|3\nuserID|1234\ncolrect|0|0|20|30\nposXY|640|1024\nname|``Cendy``\ncountry|id\ninvis|0\nmstate|0\nsmstate|0\nonlineID|\ntype|local\n";
+
+fn call(function: &str, message: &str) -> VariantList {
+    let mut variants = VariantList::new();
+    variants.push(Variant::String(function.to_string()));
+    variants.push(Variant::String(message.to_string()));
+    variants
+}
+
+#[test]
+fn parses_spawn() {
+    let player = parse_spawn(SPAWN);
+    assert_eq!(player.net_id, 3);
+    assert_eq!(player.user_id, 1234);
+    assert_eq!(player.name, "Cendy");
+    assert_eq!(player.r#type, "local");
+    assert_eq!((player.position.x, player.position.y), (640.0, 1024.0));
+}
+
+#[test]
+fn tracks_players() {
+    let mut world = World::default();
+    world.apply_client_text("action|join_request\nname|start\ninvitedWorld|0");
+    world.apply_call(&call("OnSpawn", SPAWN));
+    world.apply_call(&call("OnSpawn", "spawn|avatar\nnetID|7\nname|`2Other``\nposXY|0|0\ntype|\n"));
+    assert_eq!(world.name.as_deref(), Some("START"));
+    assert_eq!(world.local_player().map(|player| player.net_id), Some(3));
+    assert_eq!(world.players.len(), 2);
+
+    world.apply_tank(&TankPacket { r#type: ETankPacketType::NetGamePacketState, net_id: 7, vector_x: 96.0, vector_y: 64.0, ..Default::default() });
+    assert_eq!(world.players[&7].position.x, 96.0);
+
+    world.apply_call(&call("OnRemove", "netID|7\npId|0\n"));
+    assert_eq!(world.players.len(), 1);
+
+    world.apply_call(&call("OnRequestWorldSelectMenu", ""));
+    assert_eq!(world.name, None);
+    assert!(world.players.is_empty());
+}
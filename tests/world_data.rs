@@ -0,0 +1,180 @@
+use growtopia_proxy::types::etank_packet_type::ETankPacketType;
+use growtopia_proxy::types::tank_packet::TankPacket;
+use growtopia_proxy::types::tank_view::{ItemChangeObject, SendLock, SendTileTreeState, TankView, TileChangeRequest, FIST_ITEM_ID};
+use growtopia_proxy::types::vector2::Vector2;
+use growtopia_proxy::types::world_data::{decode_tile_updates, DroppedItem, Tile, TileExtra, TileFlags, WorldData, WorldDataError};
+use growtopia_proxy::world::World;
+
+fn string(out: &mut Vec<u8>, value: &str) {
+    out.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn tile(out: &mut Vec<u8>, foreground: u16, background: u16, flags: u16) {
+    for value in [foreground, background, 0, flags] {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+/// A 3x2 world: dirt, a door, a sign, a seed, a lock and a locked tile with an unnamed extra.
+fn map_data() -> Vec<u8> {
+    let mut out = vec![0x14, 0, 0x40, 0, 0, 0];
+    string(&mut out, "START");
+    for value in [3u32, 2, 6] {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    out.extend_from_slice(&[0; 5]);
+    tile(&mut out, 2, 14, 0);
+    tile(&mut out, 6, 0, 1);
+    out.push(1);
+    string(&mut out, "EXIT");
+    out.push(0);
+    tile(&mut out, 20, 0, 1);
+    out.push(2);
+    string(&mut out, "hello");
+    out.extend_from_slice(&[0xff; 4]);
+    tile(&mut out, 3, 0, 1 | 0x10);
+    out.push(4);
+    out.extend_from_slice(&60u32.to_le_bytes());
+    out.push(3);
+    tile(&mut out, 242, 0, 1);
+    out.extend_from_slice(&[3, 1]);
+    out.extend_from_slice(&1234u32.to_le_bytes());
+    out.extend_from_slice(&2u32.to_le_bytes());
+    out.extend_from_slice(&5u32.to_le_bytes());
+    out.extend_from_slice(&6u32.to_le_bytes());
+    out.push(0);
+    out.extend_from_slice(&[0; 7]);
+    tile(&mut out, 1420, 0, 1 | 2);
+    out.extend_from_slice(&4u16.to_le_bytes());
+    out.push(8);
+    out.push(5);
+    out.extend_from_slice(&[0; 12]);
+    out.extend_from_slice(&1u32.to_le_bytes());
+    out.extend_from_slice(&7u32.to_le_bytes());
+    out.extend_from_slice(&112u16.to_le_bytes());
+    out.extend_from_slice(&32f32.to_le_bytes());
+    out.extend_from_slice(&0f32.to_le_bytes());
+    out.extend_from_slice(&[10, 0]);
+    out.extend_from_slice(&7u32.to_le_bytes());
+    out
+}
+
+#[test]
+fn decodes_map_data() {
+    let world = WorldData::decode(&map_data()).unwrap();
+    assert_eq!((world.name.as_str(), world.width, world.height), ("START", 3, 2));
+    assert_eq!(world.unparsed_from, None);
+    assert_eq!((world.tiles[0].foreground, world.tiles[0].background), (2, 14));
+    assert_eq!(world.tiles[1].extra, Some(TileExtra::Door { label: "EXIT".to_string(), flags: 0 }));
+    assert_eq!(world.tiles[2].extra, Some(TileExtra::Sign { text: "hello".to_string() }));
+    assert!(world.tiles[3].flags.contains(TileFlags::SEEDLING));
+    assert_eq!(world.tiles[3].extra, Some(TileExtra::Seed { time_passed: 60, fruit_count: 3 }));
+    assert_eq!(
+        world.tiles[4].extra,
+        Some(TileExtra::Lock { settings: 1, owner_uid: 1234, access_uids: vec![5, 6], minimum_level: 0 })
+    );
+    assert_eq!(world.tiles[5].lock_index, Some(4));
+    assert_eq!(world.tiles[5].extra, Some(TileExtra::Other { kind: 8, data: vec![5] }));
+    assert_eq!(world.last_dropped_uid, 7);
+    assert_eq!(world.dropped, vec![DroppedItem { uid: 7, item_id: 112, x: 32.0, y: 0.0, amount: 10, flags: 0 }]);
+}
+
+#[test]
+fn keeps_tiles_before_an_unknown_extra() {
+    let mut data = map_data();
+    // The door's extra data type, after the 30 byte header and two 8 byte tiles.
+    assert_eq!(data[46], 1);
+    data[46] = 200;
+    let world = WorldData::decode(&data).unwrap();
+    assert_eq!(world.unparsed_from, Some(1));
+    assert_eq!(world.tiles.len(), 6);
+    assert_eq!(world.tiles[0].foreground, 2);
+    assert_eq!(world.tiles[1], Tile::default());
+    assert!(world.dropped.is_empty());
+}
+
+#[test]
+fn rejects_a_broken_header() {
+    assert!(WorldData::decode(&[0x14, 0, 0, 0]).is_err());
+    let mut data = map_data();
+    // A tile count larger than the data could hold.
+    data[24] = 0xff;
+    assert!(WorldData::decode(&data).is_err());
+    // A tile count that doesn't match the width and height.
+    let mut data = map_data();
+    data[17] = 3;
+    assert!(matches!(WorldData::decode(&data), Err(WorldDataError::Dimensions { width: 3, height: 3, tile_count: 6 })));
+}
+
+#[test]
+fn ignores_a_lock_outside_the_tiles() {
+    let mut world = World::default();
+    world.load(WorldData { width: 3, height: 2, tiles: vec![Tile::default(); 2], ..Default::default() });
+    world.apply_update(&tank(SendLock { owner_user_id: 1, lock_item_id: 202, tile_x: 2, tile_y: 1 }), &[0, 0]);
+    assert_eq!(world.tiles, vec![Tile::default(); 2]);
+}
+
+#[test]
+fn decodes_multiple_tile_updates() {
+    let mut data = Vec::new();
+    data.extend_from_slice(&1i32.to_le_bytes());
+    data.extend_from_slice(&0i32.to_le_bytes());
+    tile(&mut data, 0, 14, 0);
+    data.extend_from_slice(&2i32.to_le_bytes());
+    data.extend_from_slice(&1i32.to_le_bytes());
+    tile(&mut data, 2, 0, 0);
+    data.extend_from_slice(&(-1i32).to_le_bytes());
+    let updates = decode_tile_updates(&data).unwrap();
+    assert_eq!(updates.len(), 2);
+    assert_eq!((updates[1].0, updates[1].1, updates[1].2.foreground), (2, 1, 2));
+}
+
+fn tank(view: impl TankView) -> TankPacket {
+    view.to_tank_packet()
+}
+
+#[test]
+fn tracks_tiles_and_dropped_items() {
+    let mut world = World::default();
+    let map = TankPacket { r#type: ETankPacketType::NetGamePacketSendMapData, ..Default::default() };
+    world.apply_update(&map, &map_data());
+    assert_eq!(world.name.as_deref(), Some("START"));
+    assert_eq!(world.tile(1, 1).map(|tile| tile.foreground), Some(242));
+
+    let mut update = Vec::new();
+    tile(&mut update, 8, 14, 0);
+    let update_packet = TankPacket { r#type: ETankPacketType::NetGamePacketSendTileUpdateData, int_x: 2, int_y: 0, ..Default::default() };
+    world.apply_update(&update_packet, &update);
+    assert_eq!(world.tile(2, 0), Some(&Tile { foreground: 8, background: 14, ..Default::default() }));
+
+    let punch = |x, y| tank(TileChangeRequest { item_id: FIST_ITEM_ID, tile_x: x, tile_y: y, ..Default::default() });
+    world.apply_update(&punch(1, 0), &[]);
+    assert_eq!(world.tile(1, 0).map(|tile| (tile.foreground, tile.extra.is_none())), Some((0, true)));
+    world.apply_update(&punch(0, 0), &[]);
+    world.apply_update(&punch(0, 0), &[]);
+    assert_eq!(world.tile(0, 0).map(|tile| (tile.foreground, tile.background)), Some((0, 0)));
+    world.apply_update(&tank(TileChangeRequest { item_id: 2, tile_x: 0, tile_y: 0, ..Default::default() }), &[]);
+    world.apply_update(&tank(TileChangeRequest { item_id: 14, tile_x: 0, tile_y: 0, ..Default::default() }), &[]);
+    assert_eq!(world.tile(0, 0).map(|tile| (tile.foreground, tile.background)), Some((2, 14)));
+
+    world.apply_update(&tank(SendTileTreeState { tile_x: 0, tile_y: 1, ..Default::default() }), &[]);
+    assert_eq!(world.tile(0, 1).map(|tile| (tile.foreground, tile.extra.is_none())), Some((0, true)));
+
+    world.apply_update(&tank(SendLock { owner_user_id: 1, lock_item_id: 202, tile_x: 0, tile_y: 0 }), &[1, 0, 3, 0]);
+    assert_eq!(world.tile(0, 0).map(|tile| tile.foreground), Some(202));
+    assert_eq!(world.tile(1, 0).map(|tile| tile.lock_index), Some(Some(0)));
+    assert_eq!(world.tile(0, 1).map(|tile| tile.lock_index), Some(Some(0)));
+    assert!(world.tile(-1, 0).is_none() && world.tile(3, 0).is_none());
+
+    let position = Vector2 { x: 64.0, y: 32.0 };
+    world.apply_update(&tank(ItemChangeObject { net_id: ItemChangeObject::NEW_OBJECT, value: 242, position, amount: 3.0, object_flags: 0 }), &[]);
+    assert_eq!(world.dropped.last().map(|item| (item.uid, item.item_id, item.amount)), Some((8, 242, 3)));
+    world.apply_update(&tank(ItemChangeObject { net_id: ItemChangeObject::UPDATE_OBJECT, value: 8, amount: 5.0, ..Default::default() }), &[]);
+    assert_eq!(world.dropped.last().map(|item| item.amount), Some(5));
+    world.apply_update(&tank(ItemChangeObject { net_id: 3, value: 7, ..Default::default() }), &[]);
+    assert_eq!(world.dropped.iter().map(|item| item.uid).collect::<Vec<_>>(), vec![8]);
+
+    world.leave();
+    assert!(world.tiles.is_empty() && world.dropped.is_empty());
+}